@@ -0,0 +1,49 @@
+#ifndef NCPROJ_H
+#define NCPROJ_H
+
+#include <stddef.h>
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+// opaque record iterator returned by ncproj_dump_iter_new
+typedef struct NcprojDumpIter NcprojDumpIter;
+
+// compute a text index for the shape file over the grid file
+//  - returns 0 on success and -1 on failure
+//  - a zero thread_count uses the number of logical cpus
+int ncproj_index_build(const char *shape_file, const char *grid_file,
+    const char *output_file, uint8_t thread_count);
+
+// start dumping default statistics over all variables in the data files
+//  - returns NULL on failure
+//  - a zero thread_count uses the number of logical cpus
+NcprojDumpIter *ncproj_dump_iter_new(const char *index_file,
+    const char *const *data_files, size_t data_files_len,
+    uint8_t thread_count);
+
+// number of values in each record
+size_t ncproj_dump_iter_columns_len(const NcprojDumpIter *iter);
+
+// name of the value at index - valid until the iterator is freed
+const char *ncproj_dump_iter_column(const NcprojDumpIter *iter,
+    size_t index);
+
+// advance to the next record - returns 1 when a record is available,
+//  0 when iteration is complete, and -1 on failure
+//  - gis_join and values remain valid until the next call
+int ncproj_dump_iter_next(NcprojDumpIter *iter, const char **gis_join,
+    int64_t *timestamp, const float **values);
+
+void ncproj_dump_iter_free(NcprojDumpIter *iter);
+
+// message of the last failure on this thread (NULL if none)
+const char *ncproj_last_error(void);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif
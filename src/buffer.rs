@@ -0,0 +1,175 @@
+use geo_types::{Coordinate, LineString, Polygon};
+
+use crate::shape::Shape;
+
+use std::f64::consts::PI;
+
+// kilometers per degree of latitude
+const KM_PER_DEGREE: f64 = 111.32;
+
+// segments approximating a full circle of rounded corners
+const CIRCLE_SEGMENTS: usize = 32;
+
+// expand a shape by a distance in kilometers into polygons - geographic
+//  coordinates are scaled about the mean latitude of each part and
+//  projected coordinates are assumed to be meters
+//  - raw offset rings self-intersect (e.g. at concave vertices and
+//  overlapping parts) so the result is their union
+pub fn buffer(shape: &Shape, distance: f64, geographic: bool) -> Shape {
+    let rings = match shape {
+        Shape::Lines(lines) => lines.0.iter()
+            .filter(|x| !x.0.is_empty())
+            .map(|line| {
+                // trace the line forward and back as a ring around it
+                let mut ring = line.0.clone();
+                ring.extend(line.0.iter().rev().skip(1)
+                    .take(line.0.len().saturating_sub(2)));
+
+                buffer_ring(&ring, distance, geographic)
+            }).collect(),
+        Shape::Points(points) => points.0.iter()
+            .map(|x| buffer_ring(&[x.0], distance, geographic)).collect(),
+        Shape::Polygons(polygons) => polygons.0.iter()
+            .flat_map(|x| buffer_polygon(x, distance, geographic))
+            .collect(),
+    };
+
+    Shape::Polygons(crate::overlay::union_rings(&rings))
+}
+
+// offset the exterior outwards and the interiors inwards - interiors
+//  closed by the buffer wind about their remains and are covered
+fn buffer_polygon(polygon: &Polygon<f64>, distance: f64,
+        geographic: bool) -> Vec<Vec<Coordinate<f64>>> {
+    // orient rings with the polygon on the left (exterior counterclockwise)
+    let oriented = |ring: &LineString<f64>, counterclockwise: bool| {
+        let mut ring = ring.0.clone();
+        if (ring_area(&ring) > 0.0) != counterclockwise {
+            ring.reverse();
+        }
+
+        ring
+    };
+
+    let mut rings = vec![buffer_ring(&oriented(polygon.exterior(), true),
+        distance, geographic)];
+    for interior in polygon.interiors().iter() {
+        rings.push(buffer_ring(&oriented(interior, false), distance,
+            geographic));
+    }
+
+    rings
+}
+
+// compute the raw offset of a ring to the right of its traversal - left
+//  turns are rounded and right turns joined through the vertex, leaving
+//  loops removed by the union - rings of one vertex become circles
+fn buffer_ring(ring: &[Coordinate<f64>], distance: f64,
+        geographic: bool) -> Vec<Coordinate<f64>> {
+    // remove repeated vertices and the closing vertex
+    let mut points: Vec<Coordinate<f64>> = Vec::new();
+    for point in ring.iter() {
+        if points.last() != Some(point) {
+            points.push(*point);
+        }
+    }
+
+    while points.len() > 1 && points.first() == points.last() {
+        points.pop();
+    }
+
+    if points.is_empty() {
+        return points;
+    }
+
+    // scale coordinates to kilometers
+    let (x_scale, y_scale) = match geographic {
+        true => {
+            let latitude = points.iter().map(|x| x.y).sum::<f64>()
+                / points.len() as f64;
+            (KM_PER_DEGREE * latitude.to_radians().cos().max(1e-6),
+                KM_PER_DEGREE)
+        },
+        false => (0.001, 0.001),
+    };
+
+    let points: Vec<(f64, f64)> = points.iter()
+        .map(|x| (x.x * x_scale, x.y * y_scale)).collect();
+
+    let mut offset = Vec::new();
+    let len = points.len();
+    if len == 1 {
+        arc(&mut offset, points[0], 0.0, 2.0 * PI, distance);
+    }
+
+    for i in (0..len).filter(|_| len > 1) {
+        let (a, b, c) =
+            (points[(i + len - 1) % len], points[i], points[(i + 1) % len]);
+        let (d1, d2) = (direction(a, b), direction(b, c));
+        let (n1, n2) = ((d1.1, -d1.0), (d2.1, -d2.0));
+
+        let cross = d1.0 * d2.1 - d1.1 * d2.0;
+        let dot = d1.0 * d2.0 + d1.1 * d2.1;
+        if cross > 1e-12 || dot < -1.0 + 1e-12 {
+            // round left turns and reversals (line ends)
+            let angle = match cross > 1e-12 {
+                true => cross.atan2(dot),
+                false => PI,
+            };
+
+            arc(&mut offset, b, n1.1.atan2(n1.0), angle, distance);
+            continue;
+        }
+
+        // offset straight vertices once
+        if cross > -1e-12 {
+            offset.push((b.0 + distance * n1.0, b.1 + distance * n1.1));
+            continue;
+        }
+
+        // join right turns through the vertex so the winding number
+        //  of the overlap between adjacent offsets stays positive
+        offset.push((b.0 + distance * n1.0, b.1 + distance * n1.1));
+        offset.push(b);
+        offset.push((b.0 + distance * n2.0, b.1 + distance * n2.1));
+    }
+
+    // scale coordinates back
+    offset.into_iter()
+        .map(|(x, y)| Coordinate { x: x / x_scale, y: y / y_scale })
+        .collect()
+}
+
+// append points of a counterclockwise arc about a center
+fn arc(points: &mut Vec<(f64, f64)>, center: (f64, f64), start: f64,
+        angle: f64, radius: f64) {
+    let steps = ((angle / (2.0 * PI) * CIRCLE_SEGMENTS as f64).ceil()
+        as usize).max(1);
+    for k in 0..steps + 1 {
+        // the end of full circles coincides with the start
+        if k == steps && angle >= 2.0 * PI {
+            break;
+        }
+
+        let theta = start + angle * k as f64 / steps as f64;
+        points.push((center.0 + radius * theta.cos(),
+            center.1 + radius * theta.sin()));
+    }
+}
+
+// compute the unit direction of segment a -> b
+fn direction(a: (f64, f64), b: (f64, f64)) -> (f64, f64) {
+    let length = (b.0 - a.0).hypot(b.1 - a.1);
+    ((b.0 - a.0) / length, (b.1 - a.1) / length)
+}
+
+// compute the signed area of a ring (shoelace)
+fn ring_area(ring: &[Coordinate<f64>]) -> f64 {
+    let mut area = 0.0;
+    for k in 0..ring.len() {
+        let (p, q) = (ring[k], ring[(k + 1) % ring.len()]);
+        area += p.x * q.y - q.x * p.y;
+    }
+
+    area / 2.0
+}
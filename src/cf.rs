@@ -0,0 +1,322 @@
+use chrono::{Datelike, DateTime, NaiveDate, TimeZone, Timelike, Utc};
+
+use crate::dataset::{AttrValue, Dataset, Variable};
+
+use std::error::Error;
+use std::str::FromStr;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Calendar {
+    AllLeap,
+    Day360,
+    NoLeap,
+    Standard,
+}
+
+impl Calendar {
+    fn month_lengths(&self) -> [i64; 12] {
+        match self {
+            Calendar::AllLeap =>
+                [31, 29, 31, 30, 31, 30, 31, 31, 30, 31, 30, 31],
+            Calendar::Day360 =>
+                [30, 30, 30, 30, 30, 30, 30, 30, 30, 30, 30, 30],
+            _ => [31, 28, 31, 30, 31, 30, 31, 31, 30, 31, 30, 31],
+        }
+    }
+
+    // compute the day number of a date in a fixed-length-year calendar
+    fn day_number(&self, year: i32, month: u32, day: u32) -> i64 {
+        let month_lengths = self.month_lengths();
+        let year_length: i64 = month_lengths.iter().sum();
+
+        let mut days = year as i64 * year_length + (day as i64 - 1);
+        for length in month_lengths[..month as usize - 1].iter() {
+            days += length;
+        }
+
+        days
+    }
+
+    // compute the date of a day number in a fixed-length-year calendar
+    fn date(&self, days: i64) -> (i32, u32, u32) {
+        let month_lengths = self.month_lengths();
+        let year_length: i64 = month_lengths.iter().sum();
+
+        let year = days.div_euclid(year_length);
+        let mut remaining = days.rem_euclid(year_length);
+
+        let mut month = 0;
+        while remaining >= month_lengths[month] {
+            remaining -= month_lengths[month];
+            month += 1;
+        }
+
+        (year as i32, month as u32 + 1, remaining as u32 + 1)
+    }
+}
+
+impl FromStr for Calendar {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "all_leap" | "366_day" => Ok(Calendar::AllLeap),
+            "360_day" => Ok(Calendar::Day360),
+            "noleap" | "no_leap" | "365_day" => Ok(Calendar::NoLeap),
+            "standard" | "gregorian" | "proleptic_gregorian" =>
+                Ok(Calendar::Standard),
+            x => Err(format!("unsupported calendar '{}'", x)),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Unit {
+    Days,
+    Hours,
+    Minutes,
+    Seconds,
+}
+
+impl Unit {
+    pub fn seconds(&self) -> f64 {
+        match self {
+            Unit::Days => 86400.0,
+            Unit::Hours => 3600.0,
+            Unit::Minutes => 60.0,
+            Unit::Seconds => 1.0,
+        }
+    }
+}
+
+impl FromStr for Unit {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "days" | "day" | "d" => Ok(Unit::Days),
+            "hours" | "hour" | "hrs" | "hr" | "h" => Ok(Unit::Hours),
+            "minutes" | "minute" | "mins" | "min" => Ok(Unit::Minutes),
+            "seconds" | "second" | "secs" | "sec" | "s" => Ok(Unit::Seconds),
+            x => Err(format!("unsupported time unit '{}'", x)),
+        }
+    }
+}
+
+// cf time units of the form '<unit> since <epoch>'
+#[derive(Clone, Debug, PartialEq)]
+pub struct TimeUnits {
+    pub epoch: DateTime<Utc>,
+    pub unit: Unit,
+}
+
+impl TimeUnits {
+    pub fn timestamp(&self, offset: f64, calendar: Calendar) -> i64 {
+        let seconds = (offset * self.unit.seconds()).round() as i64;
+        if calendar == Calendar::Standard {
+            return self.epoch.timestamp() + seconds;
+        }
+
+        // shift the epoch date within the model calendar
+        let seconds = self.epoch.num_seconds_from_midnight() as i64 + seconds;
+        let days = calendar.day_number(self.epoch.year(),
+            self.epoch.month(), self.epoch.day()) + seconds.div_euclid(86400);
+        let (year, month, day) = calendar.date(days);
+        let seconds_of_day = seconds.rem_euclid(86400);
+
+        // map to the gregorian calendar - years with days which do not
+        //  exist (ex. 360_day february 30th) are mapped proportionally by
+        //  time of year so timesteps remain distinct and ordered
+        let month_lengths = calendar.month_lengths();
+        if (1..=12).all(|x| month_lengths[x as usize - 1]
+                <= gregorian_month_length(year, x) as i64) {
+            return Utc.ymd(year, month, day).and_hms(0, 0, 0).timestamp()
+                + seconds_of_day;
+        }
+
+        let year_start = Utc.ymd(year, 1, 1).and_hms(0, 0, 0).timestamp();
+        let gregorian_year_length =
+            Utc.ymd(year + 1, 1, 1).and_hms(0, 0, 0).timestamp() - year_start;
+        let year_length = month_lengths.iter().sum::<i64>() * 86400;
+        let elapsed = (days - calendar.day_number(year, 1, 1)) * 86400
+            + seconds_of_day;
+
+        year_start + (elapsed as f64 / year_length as f64
+            * gregorian_year_length as f64).round() as i64
+    }
+}
+
+fn gregorian_month_length(year: i32, month: u32) -> u32 {
+    let (next_year, next_month) = match month {
+        12 => (year + 1, 1),
+        x => (year, x + 1),
+    };
+
+    NaiveDate::from_ymd(next_year, next_month, 1)
+        .signed_duration_since(NaiveDate::from_ymd(year, month, 1))
+        .num_days() as u32
+}
+
+impl FromStr for TimeUnits {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let fields: Vec<&str> = s.trim().splitn(3, ' ').collect();
+        if fields.len() != 3 || fields[1].to_lowercase() != "since" {
+            return Err(format!("invalid time units '{}'", s));
+        }
+
+        Ok(TimeUnits {
+            epoch: parse_datetime(fields[2])?,
+            unit: fields[0].parse::<Unit>()?,
+        })
+    }
+}
+
+// parse datetimes such as '1900-01-01', '1979-01-01 00:00:00', or
+//  '1970-01-01T00:00:00Z' - timezones other than utc are unsupported
+pub fn parse_datetime(s: &str) -> Result<DateTime<Utc>, String> {
+    let error = || format!("invalid datetime '{}'", s);
+
+    let s = s.trim().trim_end_matches("UTC").trim_end_matches('Z').trim();
+    let mut fields = s.splitn(2, |c| c == ' ' || c == 'T');
+
+    // parse date
+    let date: Vec<&str> = fields.next().unwrap_or("").split('-').collect();
+    if date.len() != 3 {
+        return Err(error());
+    }
+
+    let year = date[0].parse::<i32>().map_err(|_| error())?;
+    let month = date[1].parse::<u32>().map_err(|_| error())?;
+    let day = date[2].parse::<u32>().map_err(|_| error())?;
+
+    // parse optional time
+    let (mut hour, mut minute, mut second) = (0, 0, 0f64);
+    if let Some(time) = fields.next() {
+        let time: Vec<&str> = time.trim().split(':').collect();
+        hour = time[0].parse::<u32>().map_err(|_| error())?;
+        if let Some(x) = time.get(1) {
+            minute = x.parse::<u32>().map_err(|_| error())?;
+        }
+
+        if let Some(x) = time.get(2) {
+            second = x.parse::<f64>().map_err(|_| error())?;
+        }
+    }
+
+    match Utc.ymd_opt(year, month, day).single() {
+        Some(date) => match date.and_hms_opt(hour, minute, second as u32) {
+            Some(datetime) => Ok(datetime),
+            None => Err(error()),
+        },
+        None => Err(error()),
+    }
+}
+
+// parse time variable values into unix timestamps
+pub fn parse_times(reader: &Dataset) -> Result<Vec<i64>, Box<dyn Error>> {
+    let variable = match reader.variable("time") {
+        Some(variable) => variable,
+        None => return Err("variable time not found".into()),
+    };
+
+    let units = match variable.attribute("units")? {
+        Some(AttrValue::Str(value)) => value.parse::<TimeUnits>()?,
+        Some(x) => return Err(format!(
+            "unsupported time units type '{:?}'", x).into()),
+        None => return Err("time units not found".into()),
+    };
+
+    // cf defaults to the standard calendar when unspecified
+    let calendar = match variable.attribute("calendar")? {
+        Some(AttrValue::Str(value)) => value.parse::<Calendar>()?,
+        Some(x) => return Err(format!(
+            "unsupported calendar type '{:?}'", x).into()),
+        None => Calendar::Standard,
+    };
+
+    let times = crate::get_netcdf_values(reader, "time")?;
+    Ok(times.iter().map(|x| units.timestamp(*x, calendar)).collect())
+}
+
+// compute the [start, end) indices of times within start and end
+pub fn time_range(times: &[i64], start: Option<DateTime<Utc>>,
+        end: Option<DateTime<Utc>>) -> (usize, usize) {
+    let start_index = match start {
+        Some(start) => times.iter()
+            .position(|x| *x >= start.timestamp()).unwrap_or(times.len()),
+        None => 0,
+    };
+
+    let end_index = match end {
+        Some(end) => times.iter()
+            .position(|x| *x > end.timestamp()).unwrap_or(times.len()),
+        None => times.len(),
+    };
+
+    (start_index, end_index)
+}
+
+// read the crs of a grid from the cf 'grid_mapping' variable if defined
+pub fn read_grid_crs(reader: &Dataset)
+        -> Result<Option<String>, Box<dyn Error>> {
+    for variable in reader.variables() {
+        let grid_mapping = match variable.attribute("grid_mapping")? {
+            Some(AttrValue::Str(value)) => value,
+            _ => continue,
+        };
+
+        let mapping = match reader.variable(&grid_mapping) {
+            Some(mapping) => mapping,
+            None => return Err(format!(
+                "grid mapping variable '{}' not found", grid_mapping).into()),
+        };
+
+        // check common crs attributes in order of precedence
+        for name in ["crs_wkt", "spatial_ref", "proj4_params",
+                "proj4text", "proj4"].iter() {
+            if let Some(AttrValue::Str(value)) = mapping.attribute(name)? {
+                return Ok(Some(value));
+            }
+        }
+
+        match mapping.attribute("epsg_code")? {
+            Some(AttrValue::Str(value)) => return Ok(Some(value)),
+            Some(AttrValue::Int(value)) =>
+                return Ok(Some(format!("EPSG:{}", value))),
+            _ => {},
+        }
+
+        return Err(format!("unable to parse crs of grid mapping '{}'",
+            grid_mapping).into());
+    }
+
+    Ok(None)
+}
+
+// parse a numeric attribute coerced to f64
+pub fn attribute_f64(variable: &Variable, name: &str)
+        -> Result<Option<f64>, Box<dyn Error>> {
+    match variable.attribute(name)? {
+        Some(AttrValue::Double(value)) => Ok(Some(value)),
+        Some(AttrValue::Int(value)) => Ok(Some(value as f64)),
+        Some(x) => Err(format!("unsupported {} type '{:?}'", name, x).into()),
+        None => Ok(None),
+    }
+}
+
+// find the variable defining a grid mapping of the provided name
+pub fn find_grid_mapping<'a>(reader: &'a Dataset, name: &str)
+        -> Result<Option<Variable<'a>>, Box<dyn Error>> {
+    for variable in reader.variables() {
+        if let Some(AttrValue::Str(value)) =
+                variable.attribute("grid_mapping_name")? {
+            if value == name {
+                return Ok(Some(variable));
+            }
+        }
+    }
+
+    Ok(None)
+}
@@ -0,0 +1,344 @@
+// pure rust reader for the netcdf classic formats (cdf-1, cdf-2, cdf-5)
+use crate::dataset::AttrValue;
+
+use std::error::Error;
+use std::fs::File as FsFile;
+use std::io::{BufReader, Read, Seek, SeekFrom};
+use std::path::Path;
+use std::sync::Mutex;
+
+const NC_DIMENSION: u32 = 0x0A;
+const NC_VARIABLE: u32 = 0x0B;
+const NC_ATTRIBUTE: u32 = 0x0C;
+const STREAMING: u64 = 0xFFFFFFFF;
+
+pub struct File {
+    attributes: Vec<(String, AttrValue)>,
+    dimensions: Vec<(String, usize)>,
+    file: Mutex<FsFile>,
+    record_size: u64,
+    variables: Vec<Variable>,
+}
+
+pub struct Variable {
+    pub attributes: Vec<(String, AttrValue)>,
+    begin: u64,
+    dimension_ids: Vec<usize>,
+    pub name: String,
+    pub nc_type: u32,
+    record: bool,
+}
+
+// check for the classic format magic number
+pub fn is_classic(path: &Path) -> Result<bool, Box<dyn Error>> {
+    let mut magic = [0u8; 4];
+    match FsFile::open(path)?.read_exact(&mut magic) {
+        Ok(_) => Ok(&magic[..3] == b"CDF"
+            && [1, 2, 5].contains(&magic[3])),
+        Err(_) => Ok(false),
+    }
+}
+
+impl File {
+    pub fn open(path: &Path) -> Result<File, Box<dyn Error>> {
+        let mut reader = HeaderReader {
+            reader: BufReader::new(FsFile::open(path)?),
+            version: 1,
+        };
+
+        let mut magic = [0u8; 4];
+        reader.reader.read_exact(&mut magic)?;
+        reader.version = magic[3];
+
+        let mut record_count = reader.read_size()?;
+
+        // parse dimensions - the record dimension has zero length
+        let mut dimensions = Vec::new();
+        for _ in 0..reader.read_list_len(NC_DIMENSION)? {
+            let name = reader.read_name()?;
+            let len = reader.read_size()? as usize;
+            dimensions.push((name, len));
+        }
+
+        let attributes = reader.read_attributes()?;
+
+        // parse variables
+        let mut variables = Vec::new();
+        for _ in 0..reader.read_list_len(NC_VARIABLE)? {
+            let name = reader.read_name()?;
+
+            let mut dimension_ids = Vec::new();
+            for _ in 0..reader.read_size()? {
+                dimension_ids.push(reader.read_size()? as usize);
+            }
+
+            let attributes = reader.read_attributes()?;
+            let nc_type = reader.read_u32()?;
+            let _vsize = reader.read_size()?;
+            let begin = match reader.version {
+                1 => reader.read_u32()? as u64,
+                _ => reader.read_u64()?,
+            };
+
+            let record = dimension_ids.first()
+                .map(|x| dimensions[*x].1 == 0).unwrap_or(false);
+
+            variables.push(Variable {
+                attributes: attributes,
+                begin: begin,
+                dimension_ids: dimension_ids,
+                name: name,
+                nc_type: nc_type,
+                record: record,
+            });
+        }
+
+        // compute record size - a single record variable is unpadded
+        let record_variables: Vec<&Variable> =
+            variables.iter().filter(|x| x.record).collect();
+        let mut record_size = 0;
+        for variable in record_variables.iter() {
+            let mut size = type_size(variable.nc_type)? as u64;
+            for id in variable.dimension_ids[1..].iter() {
+                size *= dimensions[*id].1 as u64;
+            }
+
+            record_size += match record_variables.len() {
+                1 => size,
+                _ => (size + 3) / 4 * 4,
+            };
+        }
+
+        // compute record count of streamed files from the file length
+        let file = reader.reader.into_inner();
+        if record_count == STREAMING {
+            let begin = record_variables.iter()
+                .map(|x| x.begin).min().unwrap_or(0);
+            record_count = match record_size {
+                0 => 0,
+                x => (file.metadata()?.len().saturating_sub(begin)) / x,
+            };
+        }
+
+        for dimension in dimensions.iter_mut() {
+            if dimension.1 == 0 {
+                dimension.1 = record_count as usize;
+            }
+        }
+
+        Ok(File {
+            attributes: attributes,
+            dimensions: dimensions,
+            file: Mutex::new(file),
+            record_size: record_size,
+            variables: variables,
+        })
+    }
+
+    pub fn attributes(&self) -> &[(String, AttrValue)] {
+        &self.attributes
+    }
+
+    pub fn dimensions(&self) -> &[(String, usize)] {
+        &self.dimensions
+    }
+
+    pub fn variables(&self) -> &[Variable] {
+        &self.variables
+    }
+
+    // (name, length) of each variable dimension
+    pub fn variable_dimensions(&self, variable: &Variable)
+            -> Vec<(String, usize)> {
+        variable.dimension_ids.iter()
+            .map(|x| self.dimensions[*x].clone()).collect()
+    }
+
+    // read a hyperslab of values in row-major order
+    pub fn read(&self, variable: &Variable, start: &[usize],
+            count: &[usize]) -> Result<Vec<f64>, Box<dyn Error>> {
+        let shape: Vec<usize> = self.variable_dimensions(variable)
+            .iter().map(|x| x.1).collect();
+        if start.len() != shape.len() || count.len() != shape.len() {
+            return Err(format!("invalid hyperslab rank for variable '{}'",
+                variable.name).into());
+        }
+
+        for (k, len) in shape.iter().enumerate() {
+            if start[k] + count[k] > *len {
+                return Err(format!("hyperslab exceeds variable '{}' \
+                    dimension {}", variable.name, k).into());
+            }
+        }
+
+        let element_size = type_size(variable.nc_type)?;
+        let len: usize = count.iter().product();
+        let mut values = Vec::with_capacity(len);
+        if len == 0 {
+            return Ok(values);
+        }
+
+        // read contiguous runs along the last dimension
+        let run_len = count.last().cloned().unwrap_or(1);
+        let mut buf = vec![0u8; run_len * element_size];
+        let mut index = start.to_vec();
+        let mut file = self.file.lock().unwrap();
+        loop {
+            file.seek(SeekFrom::Start(self.offset(variable,
+                &shape, &index, element_size)))?;
+            file.read_exact(&mut buf)?;
+
+            for bytes in buf.chunks(element_size) {
+                values.push(decode(variable.nc_type, bytes)?);
+            }
+
+            // advance outer dimension indices
+            let mut k = shape.len().saturating_sub(1);
+            loop {
+                if k == 0 {
+                    return Ok(values);
+                }
+
+                k -= 1;
+                index[k] += 1;
+                if index[k] < start[k] + count[k] {
+                    break;
+                }
+
+                index[k] = start[k];
+            }
+        }
+    }
+
+    // compute the file offset of the element at index
+    fn offset(&self, variable: &Variable, shape: &[usize],
+            index: &[usize], element_size: usize) -> u64 {
+        let skip = match variable.record { true => 1, false => 0 };
+
+        let mut linear = 0u64;
+        for (len, i) in shape.iter().zip(index.iter()).skip(skip) {
+            linear = linear * *len as u64 + *i as u64;
+        }
+
+        let mut offset = variable.begin + linear * element_size as u64;
+        if variable.record {
+            offset += index[0] as u64 * self.record_size;
+        }
+
+        offset
+    }
+}
+
+struct HeaderReader<R: Read> {
+    reader: R,
+    version: u8,
+}
+
+impl<R: Read> HeaderReader<R> {
+    fn read_u32(&mut self) -> Result<u32, Box<dyn Error>> {
+        let mut buf = [0u8; 4];
+        self.reader.read_exact(&mut buf)?;
+        Ok(u32::from_be_bytes(buf))
+    }
+
+    fn read_u64(&mut self) -> Result<u64, Box<dyn Error>> {
+        let mut buf = [0u8; 8];
+        self.reader.read_exact(&mut buf)?;
+        Ok(u64::from_be_bytes(buf))
+    }
+
+    // sizes and counts are 64 bits in cdf-5
+    fn read_size(&mut self) -> Result<u64, Box<dyn Error>> {
+        match self.version {
+            5 => self.read_u64(),
+            _ => self.read_u32().map(|x| x as u64),
+        }
+    }
+
+    // read the length of a tagged list (absent lists have zero length)
+    fn read_list_len(&mut self, tag: u32) -> Result<u64, Box<dyn Error>> {
+        let list_tag = self.read_u32()?;
+        let len = self.read_size()?;
+        match list_tag {
+            0 => Ok(0),
+            x if x == tag => Ok(len),
+            x => Err(format!("unexpected header tag {:#x}", x).into()),
+        }
+    }
+
+    fn read_padded(&mut self, len: usize) -> Result<Vec<u8>, Box<dyn Error>> {
+        let mut buf = vec![0u8; (len + 3) / 4 * 4];
+        self.reader.read_exact(&mut buf)?;
+        buf.truncate(len);
+        Ok(buf)
+    }
+
+    fn read_name(&mut self) -> Result<String, Box<dyn Error>> {
+        let len = self.read_size()? as usize;
+        Ok(String::from_utf8(self.read_padded(len)?)?)
+    }
+
+    fn read_attributes(&mut self)
+            -> Result<Vec<(String, AttrValue)>, Box<dyn Error>> {
+        let mut attributes = Vec::new();
+        for _ in 0..self.read_list_len(NC_ATTRIBUTE)? {
+            let name = self.read_name()?;
+            let nc_type = self.read_u32()?;
+            let len = self.read_size()? as usize;
+            let bytes = self.read_padded(len * type_size(nc_type)?)?;
+
+            let value = match nc_type {
+                2 => AttrValue::Str(String::from_utf8_lossy(&bytes)
+                    .trim_end_matches('\0').to_string()),
+                _ => {
+                    let mut values = Vec::new();
+                    for chunk in bytes.chunks(type_size(nc_type)?) {
+                        values.push(decode(nc_type, chunk)?);
+                    }
+
+                    match (values.len(), nc_type) {
+                        (1, 5) | (1, 6) => AttrValue::Double(values[0]),
+                        (1, _) => AttrValue::Int(values[0] as i64),
+                        _ => AttrValue::Array(values),
+                    }
+                },
+            };
+
+            attributes.push((name, value));
+        }
+
+        Ok(attributes)
+    }
+}
+
+fn type_size(nc_type: u32) -> Result<usize, Box<dyn Error>> {
+    match nc_type {
+        1 | 2 | 7 => Ok(1),
+        3 | 8 => Ok(2),
+        4 | 5 | 9 => Ok(4),
+        6 | 10 | 11 => Ok(8),
+        x => Err(format!("unsupported netcdf type {}", x).into()),
+    }
+}
+
+// decode a big-endian value as f64
+fn decode(nc_type: u32, bytes: &[u8]) -> Result<f64, Box<dyn Error>> {
+    let mut buf = [0u8; 8];
+    buf[..bytes.len()].copy_from_slice(bytes);
+
+    let value = match nc_type {
+        1 => bytes[0] as i8 as f64,
+        2 | 7 => bytes[0] as f64,
+        3 => i16::from_be_bytes([buf[0], buf[1]]) as f64,
+        4 => i32::from_be_bytes([buf[0], buf[1], buf[2], buf[3]]) as f64,
+        5 => f32::from_be_bytes([buf[0], buf[1], buf[2], buf[3]]) as f64,
+        6 => f64::from_be_bytes(buf),
+        8 => u16::from_be_bytes([buf[0], buf[1]]) as f64,
+        9 => u32::from_be_bytes([buf[0], buf[1], buf[2], buf[3]]) as f64,
+        10 => i64::from_be_bytes(buf) as f64,
+        11 => u64::from_be_bytes(buf) as f64,
+        x => return Err(format!("unsupported netcdf type {}", x).into()),
+    };
+
+    Ok(value)
+}
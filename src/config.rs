@@ -0,0 +1,121 @@
+use structopt::clap::{App, ErrorKind};
+use toml::Value;
+
+use std::collections::HashMap;
+use std::error::Error;
+
+// expand '--config <file>' into the subcommand arguments it specifies
+//  - keys are long flag names (e.g. 'buffer_size' or 'buffer-size') and
+//  'files' lists positional arguments preceding any on the command line
+//  - a table named by the subcommand (e.g. '[dump]') is used when present
+//  - flags on the command line (long or short) override the config
+pub fn expand_args(mut args: Vec<String>, app: App)
+        -> Result<Vec<String>, Box<dyn Error>> {
+    // remove the config flag and its value
+    let position = match args.iter().position(|x| x == "--config"
+            || x.starts_with("--config=")) {
+        Some(position) if position > 1 => position,
+        _ => return Ok(args),
+    };
+
+    let path = match args.remove(position) {
+        x if x == "--config" && position < args.len() =>
+            args.remove(position),
+        x if x == "--config" => return Err("config requires a file".into()),
+        x => x["--config=".len()..].to_string(),
+    };
+
+    let value: Value = std::fs::read_to_string(&path)?.parse()
+        .map_err(|e| format!("invalid config '{}': {}", path, e))?;
+
+    // use the subcommand table if present
+    let subcommand = args[1].clone();
+    let table = match value.get(&subcommand) {
+        Some(Value::Table(table)) => table.clone(),
+        _ => value.as_table().cloned().unwrap_or_default(),
+    };
+
+    let shorts = short_flags(app, &args[0], &subcommand);
+
+    let (mut flags, mut files) = (Vec::new(), Vec::new());
+    for (key, value) in table.iter() {
+        if key == "files" {
+            for file in value.as_array().cloned().unwrap_or_default() {
+                files.push(config_string(&file)
+                    .ok_or_else(|| format!("invalid config file '{}'", file))?);
+            }
+
+            continue;
+        }
+
+        // skip flags given on the command line - short flags may have
+        //  attached values (e.g. '-b10')
+        let flag = format!("--{}", key.replace('_', "-"));
+        let short = shorts.get(&flag);
+        if args[2..].iter().any(|x| x == &flag
+                || x.starts_with(&format!("{}=", flag))
+                || short.map(|y| !x.starts_with("--") && x.starts_with(y))
+                    .unwrap_or(false)) {
+            continue;
+        }
+
+        match value {
+            Value::Boolean(true) => flags.push(flag),
+            Value::Boolean(false) | Value::Table(_) => {},
+            Value::Array(values) => {
+                let values = values.iter().map(config_string)
+                    .collect::<Option<Vec<String>>>()
+                    .ok_or_else(|| format!("invalid config '{}'", key))?;
+
+                flags.push(flag);
+                flags.push(values.join(","));
+            },
+            value => {
+                flags.push(flag);
+                flags.push(config_string(value)
+                    .ok_or_else(|| format!("invalid config '{}'", key))?);
+            },
+        }
+    }
+
+    // insert config arguments after the subcommand
+    let mut expanded = args[..2].to_vec();
+    expanded.extend(flags);
+    expanded.extend(files);
+    expanded.extend(args[2..].iter().cloned());
+    Ok(expanded)
+}
+
+// map long flags of a subcommand to their short aliases (e.g. '--output'
+//  to '-o') - clap exposes them only through the help message
+fn short_flags(app: App, binary: &str, subcommand: &str)
+        -> HashMap<String, String> {
+    let help = match app.get_matches_from_safe(
+            vec![binary, subcommand, "--help"]) {
+        Err(e) if e.kind == ErrorKind::HelpDisplayed => e.message,
+        _ => return HashMap::new(),
+    };
+
+    // help lines list flags as '-o, --output <output>'
+    help.lines().filter_map(|line| {
+        let mut fields = line.split_whitespace();
+        match (fields.next(), fields.next()) {
+            (Some(short), Some(long)) if short.len() == 3
+                    && short.starts_with('-') && short.ends_with(',')
+                    && long.starts_with("--") =>
+                Some((long.to_string(), short[..2].to_string())),
+            _ => None,
+        }
+    }).collect()
+}
+
+fn config_string(value: &Value) -> Option<String> {
+    match value {
+        Value::Boolean(x) => Some(x.to_string()),
+        Value::Datetime(x) => Some(x.to_string()),
+        Value::Float(x) => Some(x.to_string()),
+        Value::Integer(x) => Some(x.to_string()),
+        Value::String(x) => Some(x.to_string()),
+        _ => None,
+    }
+}
@@ -0,0 +1,293 @@
+use chrono::{DateTime, Utc};
+use ndarray::ArrayD;
+use structopt::StructOpt;
+
+use ncproj_rs::dataset::Dataset;
+use ncproj_rs::dumper::Encoding;
+use ncproj_rs::sink::{Columns, OutputFormat, TextFormat, TimeFormat};
+use ncproj_rs::zarr::Store;
+
+use std::collections::HashSet;
+use std::error::Error;
+use std::path::PathBuf;
+use std::str::FromStr;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ConvertFormat {
+    Table(OutputFormat),
+    Zarr,
+}
+
+impl FromStr for ConvertFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "zarr" => Ok(ConvertFormat::Zarr),
+            x => Ok(ConvertFormat::Table(OutputFormat::from_str(x)?)),
+        }
+    }
+}
+
+#[derive(StructOpt)]
+pub struct Convert {
+    // number of rows in each output batch (arrow, parquet)
+    #[structopt(long = "batch-size", default_value = "8192")]
+    batch_size: usize,
+
+    // zarr chunk lengths along 'time,lat,lon' - time chunks are also
+    //  the number of time intervals read at once
+    #[structopt(short = "c", long = "chunks", use_delimiter = true,
+        default_value = "100,256,256")]
+    chunks: Vec<usize>,
+
+    #[structopt(parse(from_os_str), index = 1)]
+    data_file: PathBuf,
+
+    // add year, month, and day columns after the timestamp (csv, ndjson)
+    #[structopt(long = "date-columns")]
+    date_columns: bool,
+
+    // csv field delimiter - 'comma', 'tab', 'pipe', or a character
+    #[structopt(short = "d", long = "delimiter", default_value = "comma",
+        parse(try_from_str = ncproj_rs::sink::parse_delimiter))]
+    delimiter: u8,
+
+    // only convert timesteps at or before this datetime
+    #[structopt(long = "end", parse(try_from_str = ncproj_rs::cf::parse_datetime))]
+    end: Option<DateTime<Utc>>,
+
+    // csv representation of cells without values - 'empty', 'nan', or
+    //  a literal string
+    #[structopt(long = "null-as", default_value = "nan",
+        parse(try_from_str = ncproj_rs::sink::parse_null))]
+    null_as: String,
+
+    // output path (a directory for zarr)
+    #[structopt(short = "o", long = "output", parse(from_os_str))]
+    output: Option<PathBuf>,
+
+    // 'zarr' or a tabular format ('csv', 'ndjson', 'arrow', or 'parquet')
+    //  - tabular rows are keyed by the '{y}_{x}' cell index
+    #[structopt(short = "f", long = "output-format", default_value = "csv")]
+    output_format: ConvertFormat,
+
+    // number of decimal places of tabular values
+    #[structopt(long = "precision", default_value = "3")]
+    precision: usize,
+
+    // suppress progress reporting
+    #[structopt(short = "q", long = "quiet")]
+    quiet: bool,
+
+    // skip tabular rows of cells without values
+    #[structopt(long = "skip-empty")]
+    skip_empty: bool,
+
+    // only convert timesteps at or after this datetime
+    #[structopt(long = "start",
+        parse(try_from_str = ncproj_rs::cf::parse_datetime))]
+    start: Option<DateTime<Utc>>,
+
+    // timestamp format of csv and ndjson rows - 'epoch' (seconds),
+    //  'iso8601', 'date' (e.g. '2020-01-31'), or a strftime pattern
+    //  (e.g. '%Y%m%d')
+    #[structopt(long = "time-format", default_value = "epoch")]
+    time_format: TimeFormat,
+
+    // comma-separated list of variables to convert (default all)
+    #[structopt(short = "v", long = "variables", use_delimiter = true)]
+    variables: Vec<String>,
+}
+
+impl Convert {
+    pub fn execute(&self) -> Result<(), Box<dyn Error>> {
+        if self.chunks.len() != 3 || self.chunks.contains(&0) {
+            return Err("chunks requires nonzero 'time,lat,lon'".into());
+        }
+
+        let reader = Dataset::open(&self.data_file)?;
+        let times = ncproj_rs::cf::parse_times(&reader)?;
+        let (y_name, x_name) =
+            ncproj_rs::grid::read_dimension_names(&reader)?;
+        let (y_len, x_len) = ncproj_rs::grid::read_dimensions(&reader)?;
+        let (start_index, end_index) =
+            ncproj_rs::cf::time_range(&times, self.start, self.end);
+
+        // identify (time, lat, lon) variables
+        let dimensions: HashSet<String> =
+            reader.dimensions().iter().map(|x| x.name()).collect();
+
+        let mut features = Vec::new();
+        for variable in reader.variables() {
+            let names: Vec<String> = variable.dimensions().iter()
+                .map(|x| x.name()).collect();
+            if dimensions.contains(&variable.name())
+                    || names.len() != 3 || names[1] != y_name
+                    || names[2] != x_name || (!self.variables.is_empty()
+                        && !self.variables.contains(&variable.name())) {
+                continue;
+            }
+
+            features.push(variable.name());
+        }
+
+        for variable in self.variables.iter() {
+            if !features.contains(variable) {
+                return Err(format!(
+                    "gridded variable '{}' not found", variable).into());
+            }
+        }
+
+        let encodings = features.iter()
+            .map(|x| Encoding::read(&reader.variable(x).unwrap()))
+            .collect::<Result<Vec<Encoding>, Box<dyn Error>>>()?;
+
+        // open output
+        let time_len = end_index.saturating_sub(start_index);
+        let mut output = match self.output_format {
+            ConvertFormat::Table(format) => {
+                let mut columns = vec!["lat".to_string(), "lon".to_string()];
+                columns.extend(features.iter().cloned());
+
+                let text_format = TextFormat {
+                    date_columns: self.date_columns,
+                    delimiter: self.delimiter,
+                    null: self.null_as.clone(),
+                    precision: self.precision,
+                    time_format: self.time_format.clone(),
+                };
+
+                Output::Table(ncproj_rs::sink::open(format, &self.output,
+                    Columns::new(columns), self.batch_size, &text_format,
+                    false)?)
+            },
+            ConvertFormat::Zarr => {
+                let path = self.output.as_ref()
+                    .ok_or("zarr output requires an output directory")?;
+                let store = Store::create(path, &reader.attributes())?;
+
+                let mut arrays = Vec::new();
+                for feature in features.iter() {
+                    let variable = reader.variable(feature).unwrap();
+                    let names: Vec<String> = variable.dimensions().iter()
+                        .map(|x| x.name()).collect();
+
+                    arrays.push(store.add_array(feature, "<f4", &names,
+                        &[time_len, y_len, x_len], &self.chunks,
+                        &variable.attributes())?);
+                }
+
+                // write coordinate variables as single chunks
+                let coordinates = reader.variables().into_iter()
+                    .filter(|x| dimensions.contains(&x.name()));
+                for variable in coordinates {
+                    let names: Vec<String> = variable.dimensions().iter()
+                        .map(|x| x.name()).collect();
+                    let mut start: Vec<usize> = vec![0; names.len()];
+                    let mut count: Vec<usize> = variable.dimensions()
+                        .iter().map(|x| x.len()).collect();
+                    if names.first().map(|x| x == "time").unwrap_or(false) {
+                        start[0] = start_index;
+                        count[0] = time_len;
+                    }
+
+                    let values: Vec<f64> = variable
+                        .values_at(&start, &count)?.iter().cloned().collect();
+                    let chunks: Vec<usize> =
+                        count.iter().map(|x| std::cmp::max(*x, 1)).collect();
+                    store.add_array(&variable.name(), "<f8", &names,
+                        &count, &chunks, &variable.attributes())?
+                        .write_block(&values, &vec![0; count.len()], &count)?;
+                }
+
+                Output::Zarr(arrays)
+            },
+        };
+
+        // cell center coordinates of tabular rows indexed by y * x_len + x
+        let (latitudes, longitudes) = match &output {
+            Output::Table(_) => (
+                cell_coordinates(ncproj_rs::get_netcdf_values(&reader,
+                    "lat")?, 0, y_len, x_len),
+                cell_coordinates(ncproj_rs::get_netcdf_values(&reader,
+                    "lon")?, 1, y_len, x_len)),
+            Output::Zarr(_) => (Vec::new(), Vec::new()),
+        };
+
+        // convert a buffer of time intervals at a time
+        let progress_bar =
+            ncproj_rs::progress_bar(time_len as u64, self.quiet);
+        let mut buffers = vec![vec![0f32; self.chunks[0] * y_len * x_len];
+            features.len()];
+        for i in (start_index..end_index).step_by(self.chunks[0]) {
+            let time_slice_len = std::cmp::min(self.chunks[0], end_index - i);
+            let buffer_size = time_slice_len * y_len * x_len;
+
+            for (k, feature) in features.iter().enumerate() {
+                ncproj_rs::dumper::read_slab(&reader, feature,
+                    &encodings[k], &mut buffers[k][..buffer_size],
+                    &[i, 0, 0], &[time_slice_len, y_len, x_len])?;
+            }
+
+            match &mut output {
+                Output::Table(sink) => {
+                    let mut row = vec![0f32; features.len() + 2];
+                    for t in 0..time_slice_len {
+                        for y in 0..y_len {
+                            for x in 0..x_len {
+                                let offset = (t * y_len + y) * x_len + x;
+                                for (k, buffer) in buffers.iter().enumerate() {
+                                    row[k + 2] = buffer[offset];
+                                }
+
+                                if self.skip_empty
+                                        && row[2..].iter().all(|x| x.is_nan()) {
+                                    continue;
+                                }
+
+                                row[0] = latitudes[y * x_len + x];
+                                row[1] = longitudes[y * x_len + x];
+                                sink.write_row(&format!("{}_{}", y, x),
+                                    times[i + t], &row)?;
+                            }
+                        }
+                    }
+                },
+                Output::Zarr(arrays) => {
+                    for (k, array) in arrays.iter().enumerate() {
+                        let values: Vec<f64> = buffers[k][..buffer_size]
+                            .iter().map(|x| *x as f64).collect();
+                        array.write_block(&values, &[i - start_index, 0, 0],
+                            &[time_slice_len, y_len, x_len])?;
+                    }
+                },
+            }
+
+            progress_bar.inc(time_slice_len as u64);
+        }
+
+        progress_bar.finish();
+        if let Output::Table(sink) = &mut output {
+            sink.close()?;
+        }
+
+        Ok(())
+    }
+}
+
+// expand 1d coordinates along the axis (0 for y, 1 for x) to all cells
+fn cell_coordinates(values: ArrayD<f64>, axis: usize, y_len: usize,
+        x_len: usize) -> Vec<f32> {
+    let values: Vec<f32> = values.iter().map(|x| *x as f32).collect();
+    match values.len() == y_len * x_len {
+        true => values,
+        false => (0..y_len * x_len)
+            .map(|x| values[[x / x_len, x % x_len][axis]]).collect(),
+    }
+}
+
+enum Output {
+    Table(Box<dyn ncproj_rs::sink::Sink + Send>),
+    Zarr(Vec<ncproj_rs::zarr::Array>),
+}
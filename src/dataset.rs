@@ -0,0 +1,476 @@
+// netcdf file access independent of the underlying reader
+//  - classic format files use the pure rust reader when the 'netcdf3'
+//  feature is enabled and all other files require 'netcdf-c'
+//  - opendap urls are opened with netcdf-c which issues constrained
+//  requests for each hyperslab read
+//  - s3 objects are read with netcdf-c byte-range requests
+//  - kerchunk reference files ('.json') are read lazily when the
+//  'kerchunk' feature is enabled
+use ndarray::ArrayD;
+
+use crate::error::NcprojError;
+
+use std::error::Error;
+use std::path::Path;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum AttrValue {
+    Array(Vec<f64>),
+    Double(f64),
+    Int(i64),
+    Str(String),
+}
+
+// storage type of variable values
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum StorageType {
+    Byte,
+    Char,
+    Double,
+    Float,
+    Int,
+    Int64,
+    Short,
+    String,
+    UByte,
+    UInt,
+    UInt64,
+    UShort,
+}
+
+impl StorageType {
+    // parse a netcdf type code
+    pub fn from_nc_type(nc_type: u32) -> Result<StorageType, Box<dyn Error>> {
+        match nc_type {
+            1 => Ok(StorageType::Byte),
+            2 => Ok(StorageType::Char),
+            3 => Ok(StorageType::Short),
+            4 => Ok(StorageType::Int),
+            5 => Ok(StorageType::Float),
+            6 => Ok(StorageType::Double),
+            7 => Ok(StorageType::UByte),
+            8 => Ok(StorageType::UShort),
+            9 => Ok(StorageType::UInt),
+            10 => Ok(StorageType::Int64),
+            11 => Ok(StorageType::UInt64),
+            12 => Ok(StorageType::String),
+            x => Err(format!("unsupported netcdf type {}", x).into()),
+        }
+    }
+
+    // bits of signed integer types which may be flagged '_Unsigned'
+    pub fn signed_bits(&self) -> Option<u32> {
+        match self {
+            StorageType::Byte => Some(8),
+            StorageType::Int => Some(32),
+            StorageType::Int64 => Some(64),
+            StorageType::Short => Some(16),
+            _ => None,
+        }
+    }
+
+    // netcdf default fill value - bytes have no default fill by
+    //  convention and text types are not numeric
+    pub fn default_fill(&self) -> Option<f64> {
+        match self {
+            StorageType::Double | StorageType::Float =>
+                Some(9.969_209_968_386_869e36),
+            StorageType::Int => Some(-2_147_483_647.0),
+            StorageType::Int64 => Some(-9_223_372_036_854_775_806.0),
+            StorageType::Short => Some(-32_767.0),
+            StorageType::UInt => Some(4_294_967_295.0),
+            StorageType::UInt64 => Some(18_446_744_073_709_551_614.0),
+            StorageType::UShort => Some(65_535.0),
+            _ => None,
+        }
+    }
+
+    // integer types - values are read as f64 to compare exactly
+    pub fn is_integer(&self) -> bool {
+        match self {
+            StorageType::Byte | StorageType::Int | StorageType::Int64
+                | StorageType::Short | StorageType::UByte | StorageType::UInt
+                | StorageType::UInt64 | StorageType::UShort => true,
+            _ => false,
+        }
+    }
+
+    pub fn is_numeric(&self) -> bool {
+        match self {
+            StorageType::Char | StorageType::String => false,
+            _ => true,
+        }
+    }
+}
+
+pub struct Dimension {
+    len: usize,
+    name: String,
+}
+
+impl Dimension {
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn name(&self) -> String {
+        self.name.to_string()
+    }
+}
+
+pub struct Dataset {
+    inner: Inner,
+}
+
+enum Inner {
+    #[cfg(feature = "netcdf3")]
+    Classic(crate::classic::File),
+    #[cfg(feature = "kerchunk")]
+    Kerchunk(crate::kerchunk::File),
+    #[cfg(feature = "netcdf-c")]
+    Netcdf(netcdf::File),
+}
+
+impl Dataset {
+    // open a file or url selecting the reader by file magic
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Dataset, Box<dyn Error>> {
+        let path = path.as_ref();
+        Dataset::open_inner(path).map_err(|e| NcprojError::Netcdf {
+            message: e.to_string(),
+            path: path.display().to_string(),
+        }.into())
+    }
+
+    // open with the reader for the path - errors are classified by open
+    fn open_inner(path: &Path) -> Result<Dataset, Box<dyn Error>> {
+        #[cfg(feature = "kerchunk")]
+        {
+            if path.extension().and_then(|x| x.to_str()) == Some("json") {
+                let file = crate::kerchunk::File::open(path)?;
+                return Ok(Dataset { inner: Inner::Kerchunk(file) });
+            }
+        }
+
+        #[cfg(feature = "netcdf3")]
+        {
+            if !is_remote(path) && crate::classic::is_classic(path)? {
+                let file = crate::classic::File::open(path)?;
+                return Ok(Dataset { inner: Inner::Classic(file) });
+            }
+        }
+
+        open_netcdf(path)
+    }
+
+    // global attributes - unsupported types are skipped
+    pub fn attributes(&self) -> Vec<(String, AttrValue)> {
+        match &self.inner {
+            #[cfg(feature = "netcdf3")]
+            Inner::Classic(file) => file.attributes().to_vec(),
+            #[cfg(feature = "kerchunk")]
+            Inner::Kerchunk(file) => file.attributes().to_vec(),
+            #[cfg(feature = "netcdf-c")]
+            Inner::Netcdf(file) => file.attributes()
+                .filter_map(|x| match x.value() {
+                    Ok(value) => Some((x.name().to_string(), convert(value))),
+                    Err(_) => None,
+                }).collect(),
+        }
+    }
+
+    pub fn dimensions(&self) -> Vec<Dimension> {
+        match &self.inner {
+            #[cfg(feature = "netcdf3")]
+            Inner::Classic(file) => file.dimensions().iter()
+                .map(|(name, len)| Dimension {
+                    len: *len,
+                    name: name.to_string(),
+                }).collect(),
+            #[cfg(feature = "kerchunk")]
+            Inner::Kerchunk(file) => file.dimensions().iter()
+                .map(|(name, len)| Dimension {
+                    len: *len,
+                    name: name.to_string(),
+                }).collect(),
+            #[cfg(feature = "netcdf-c")]
+            Inner::Netcdf(file) => file.dimensions()
+                .map(|x| Dimension { len: x.len(), name: x.name() })
+                .collect(),
+        }
+    }
+
+    pub fn variables(&self) -> Vec<Variable<'_>> {
+        match &self.inner {
+            #[cfg(feature = "netcdf3")]
+            Inner::Classic(file) => file.variables().iter()
+                .map(|x| Variable::Classic(file, x)).collect(),
+            #[cfg(feature = "kerchunk")]
+            Inner::Kerchunk(file) => file.variables().iter()
+                .map(|x| Variable::Kerchunk(file, x)).collect(),
+            #[cfg(feature = "netcdf-c")]
+            Inner::Netcdf(file) => file.variables()
+                .map(Variable::Netcdf).collect(),
+        }
+    }
+
+    pub fn variable(&self, name: &str) -> Option<Variable<'_>> {
+        match &self.inner {
+            #[cfg(feature = "netcdf3")]
+            Inner::Classic(file) => file.variables().iter()
+                .find(|x| x.name == name)
+                .map(|x| Variable::Classic(file, x)),
+            #[cfg(feature = "kerchunk")]
+            Inner::Kerchunk(file) => file.variables().iter()
+                .find(|x| x.name == name)
+                .map(|x| Variable::Kerchunk(file, x)),
+            #[cfg(feature = "netcdf-c")]
+            Inner::Netcdf(file) => file.variable(name).map(Variable::Netcdf),
+        }
+    }
+}
+
+pub enum Variable<'a> {
+    #[cfg(feature = "netcdf3")]
+    Classic(&'a crate::classic::File, &'a crate::classic::Variable),
+    #[cfg(feature = "kerchunk")]
+    Kerchunk(&'a crate::kerchunk::File, &'a crate::kerchunk::Variable),
+    #[cfg(feature = "netcdf-c")]
+    Netcdf(netcdf::Variable<'a>),
+}
+
+impl<'a> Variable<'a> {
+    pub fn name(&self) -> String {
+        match self {
+            #[cfg(feature = "netcdf3")]
+            Variable::Classic(_, variable) => variable.name.to_string(),
+            #[cfg(feature = "kerchunk")]
+            Variable::Kerchunk(_, variable) => variable.name.to_string(),
+            #[cfg(feature = "netcdf-c")]
+            Variable::Netcdf(variable) => variable.name(),
+        }
+    }
+
+    pub fn dimensions(&self) -> Vec<Dimension> {
+        match self {
+            #[cfg(feature = "netcdf3")]
+            Variable::Classic(file, variable) => file
+                .variable_dimensions(variable).into_iter()
+                .map(|(name, len)| Dimension { len: len, name: name })
+                .collect(),
+            #[cfg(feature = "kerchunk")]
+            Variable::Kerchunk(file, variable) => file
+                .variable_dimensions(variable).into_iter()
+                .map(|(name, len)| Dimension { len: len, name: name })
+                .collect(),
+            #[cfg(feature = "netcdf-c")]
+            Variable::Netcdf(variable) => variable.dimensions().iter()
+                .map(|x| Dimension { len: x.len(), name: x.name() })
+                .collect(),
+        }
+    }
+
+    pub fn attribute(&self, name: &str)
+            -> Result<Option<AttrValue>, Box<dyn Error>> {
+        match self {
+            #[cfg(feature = "netcdf3")]
+            Variable::Classic(_, variable) => Ok(variable.attributes.iter()
+                .find(|x| x.0 == name).map(|x| x.1.clone())),
+            #[cfg(feature = "kerchunk")]
+            Variable::Kerchunk(_, variable) => Ok(variable.attributes.iter()
+                .find(|x| x.0 == name).map(|x| x.1.clone())),
+            #[cfg(feature = "netcdf-c")]
+            Variable::Netcdf(variable) => match variable.attribute(name) {
+                Some(attribute) => Ok(Some(convert(attribute.value()?))),
+                None => Ok(None),
+            },
+        }
+    }
+
+    pub fn storage_type(&self) -> Result<StorageType, Box<dyn Error>> {
+        match self {
+            #[cfg(feature = "netcdf3")]
+            Variable::Classic(_, variable) =>
+                StorageType::from_nc_type(variable.nc_type),
+            #[cfg(feature = "kerchunk")]
+            Variable::Kerchunk(_, variable) =>
+                StorageType::from_nc_type(variable.nc_type()?),
+            #[cfg(feature = "netcdf-c")]
+            Variable::Netcdf(variable) =>
+                StorageType::from_nc_type(variable.vartype() as u32),
+        }
+    }
+
+    // all attributes - unsupported types are skipped
+    pub fn attributes(&self) -> Vec<(String, AttrValue)> {
+        match self {
+            #[cfg(feature = "netcdf3")]
+            Variable::Classic(_, variable) => variable.attributes.clone(),
+            #[cfg(feature = "kerchunk")]
+            Variable::Kerchunk(_, variable) => variable.attributes.clone(),
+            #[cfg(feature = "netcdf-c")]
+            Variable::Netcdf(variable) => variable.attributes()
+                .filter_map(|x| match x.value() {
+                    Ok(value) => Some((x.name().to_string(), convert(value))),
+                    Err(_) => None,
+                }).collect(),
+        }
+    }
+
+    // chunk lengths of each dimension (None if stored contiguously)
+    pub fn chunking(&self) -> Result<Option<Vec<usize>>, Box<dyn Error>> {
+        match self {
+            #[cfg(feature = "netcdf3")]
+            Variable::Classic(_, _) => Ok(None),
+            #[cfg(feature = "kerchunk")]
+            Variable::Kerchunk(_, variable) =>
+                Ok(Some(variable.chunks.clone())),
+            #[cfg(feature = "netcdf-c")]
+            Variable::Netcdf(variable) => Ok(variable.chunking()?),
+        }
+    }
+
+    // read all values
+    pub fn values(&self) -> Result<ArrayD<f64>, Box<dyn Error>> {
+        match self {
+            #[cfg(feature = "netcdf3")]
+            Variable::Classic(file, variable) => {
+                let shape: Vec<usize> = self.dimensions()
+                    .iter().map(|x| x.len()).collect();
+                let values = file.read(variable,
+                    &vec![0; shape.len()], &shape)?;
+                Ok(ArrayD::from_shape_vec(shape, values)?)
+            },
+            #[cfg(feature = "kerchunk")]
+            Variable::Kerchunk(file, variable) => {
+                let shape: Vec<usize> = self.dimensions()
+                    .iter().map(|x| x.len()).collect();
+                let values = file.read(variable,
+                    &vec![0; shape.len()], &shape)?;
+                Ok(ArrayD::from_shape_vec(shape, values)?)
+            },
+            #[cfg(feature = "netcdf-c")]
+            Variable::Netcdf(variable) =>
+                Ok(variable.values::<f64>(None, None)?),
+        }
+    }
+
+    // read the hyperslab at start with count length
+    pub fn values_at(&self, start: &[usize], count: &[usize])
+            -> Result<ArrayD<f64>, Box<dyn Error>> {
+        match self {
+            #[cfg(feature = "netcdf3")]
+            Variable::Classic(file, variable) => Ok(ArrayD::from_shape_vec(
+                count.to_vec(), file.read(variable, start, count)?)?),
+            #[cfg(feature = "kerchunk")]
+            Variable::Kerchunk(file, variable) => Ok(ArrayD::from_shape_vec(
+                count.to_vec(), file.read(variable, start, count)?)?),
+            #[cfg(feature = "netcdf-c")]
+            Variable::Netcdf(variable) =>
+                Ok(variable.values::<f64>(Some(start), Some(count))?),
+        }
+    }
+
+    // read the hyperslab at start with count length into buffer
+    pub fn values_to(&self, buffer: &mut [f32], start: &[usize],
+            count: &[usize]) -> Result<(), Box<dyn Error>> {
+        match self {
+            #[cfg(feature = "netcdf3")]
+            Variable::Classic(file, variable) => {
+                let values = file.read(variable, start, count)?;
+                for (x, value) in buffer.iter_mut().zip(values) {
+                    *x = value as f32;
+                }
+
+                Ok(())
+            },
+            #[cfg(feature = "kerchunk")]
+            Variable::Kerchunk(file, variable) => {
+                let values = file.read(variable, start, count)?;
+                for (x, value) in buffer.iter_mut().zip(values) {
+                    *x = value as f32;
+                }
+
+                Ok(())
+            },
+            #[cfg(feature = "netcdf-c")]
+            Variable::Netcdf(variable) =>
+                Ok(variable.values_to(buffer, Some(start), Some(count))?),
+        }
+    }
+}
+
+// check if the path is an opendap or s3 url
+pub fn is_remote(path: &Path) -> bool {
+    match path.to_str() {
+        Some(path) => path.starts_with("http://")
+            || path.starts_with("https://") || path.starts_with("dap4://")
+            || path.starts_with("s3://"),
+        None => false,
+    }
+}
+
+// convert 's3://bucket/key' into a netcdf-c byte-range url - requests
+//  are signed with aws credentials unless AWS_NO_SIGN_REQUEST is set
+pub fn s3_url(url: &str) -> Result<String, Box<dyn Error>> {
+    Ok(format!("{}#mode=bytes", crate::s3::object_url(url)?))
+}
+
+#[cfg(feature = "netcdf-c")]
+fn open_netcdf(path: &Path) -> Result<Dataset, Box<dyn Error>> {
+    let file = match path.to_str() {
+        Some(url) if url.starts_with("s3://") => netcdf::open(s3_url(url)?)?,
+        _ => netcdf::open(path)?,
+    };
+
+    Ok(Dataset { inner: Inner::Netcdf(file) })
+}
+
+#[cfg(not(feature = "netcdf-c"))]
+fn open_netcdf(path: &Path) -> Result<Dataset, Box<dyn Error>> {
+    Err(format!("unsupported dataset '{}' - only local classic netcdf \
+        files are supported without 'netcdf-c'", path.display()).into())
+}
+
+// convert netcdf-c attribute values - numeric arrays of every storage
+//  type are coerced to f64
+#[cfg(feature = "netcdf-c")]
+fn convert(value: netcdf::attribute::AttrValue) -> AttrValue {
+    use netcdf::attribute::AttrValue as Value;
+
+    match value {
+        Value::Uchar(x) => AttrValue::Int(x as i64),
+        Value::Schar(x) => AttrValue::Int(x as i64),
+        Value::Ushort(x) => AttrValue::Int(x as i64),
+        Value::Short(x) => AttrValue::Int(x as i64),
+        Value::Uint(x) => AttrValue::Int(x as i64),
+        Value::Int(x) => AttrValue::Int(x as i64),
+        Value::Ulonglong(x) => AttrValue::Int(x as i64),
+        Value::Longlong(x) => AttrValue::Int(x),
+        Value::Float(x) => AttrValue::Double(x as f64),
+        Value::Double(x) => AttrValue::Double(x),
+        Value::Str(x) => AttrValue::Str(x),
+        Value::Uchars(x) => AttrValue::Array(
+            x.into_iter().map(|x| x as f64).collect()),
+        Value::Schars(x) => AttrValue::Array(
+            x.into_iter().map(|x| x as f64).collect()),
+        Value::Ushorts(x) => AttrValue::Array(
+            x.into_iter().map(|x| x as f64).collect()),
+        Value::Shorts(x) => AttrValue::Array(
+            x.into_iter().map(|x| x as f64).collect()),
+        Value::Uints(x) => AttrValue::Array(
+            x.into_iter().map(|x| x as f64).collect()),
+        Value::Ints(x) => AttrValue::Array(
+            x.into_iter().map(|x| x as f64).collect()),
+        Value::Ulonglongs(x) => AttrValue::Array(
+            x.into_iter().map(|x| x as f64).collect()),
+        Value::Longlongs(x) => AttrValue::Array(
+            x.into_iter().map(|x| x as f64).collect()),
+        Value::Floats(x) => AttrValue::Array(
+            x.into_iter().map(|x| x as f64).collect()),
+        Value::Doubles(x) => AttrValue::Array(x),
+    }
+}
@@ -1,293 +1,695 @@
-use chrono::Duration;
-use chrono::prelude::{TimeZone, Utc};
-use crossbeam_channel::{Receiver, Sender};
-use netcdf::attribute::AttrValue;
+use chrono::{DateTime, TimeZone, Utc};
 use structopt::StructOpt;
 
-use std::collections::{BTreeMap, HashSet};
+use ncproj_rs::dumper::{Dumper, Level, Member, Plan};
+use ncproj_rs::grid::{CellWeight, WeightRaster};
+use ncproj_rs::resample::{Period, Resampler};
+use ncproj_rs::sink::{Columns, OutputFormat, Partition, TextFormat,
+    TimeFormat};
+use ncproj_rs::stat::{Correlation, Precision, Stat};
+use ncproj_rs::units::Units;
+
+use std::collections::HashSet;
 use std::error::Error;
-use std::fs::File;
-use std::io::{BufRead, BufReader};
 use std::path::PathBuf;
-use std::sync::{Arc, RwLock};
-use std::sync::atomic::{AtomicUsize, Ordering};
 
 #[derive(StructOpt)]
 pub struct Dump {
+    // precision of statistic accumulation - 'f32' (vectorized) or 'f64'
+    //  for exact sums and means over large shapes
+    #[structopt(long = "accumulation", default_value = "f32")]
+    accumulation: Precision,
+
+    // comma-separated index files (e.g. states) aggregated alongside the
+    //  index file in one read of the data - rows lead with an
+    //  'index_name' column of the index file name up to its first '.'
+    #[structopt(long = "additional-index", parse(from_os_str),
+        use_delimiter = true)]
+    additional_index: Vec<PathBuf>,
+
+    // number of rows in each output batch (arrow, parquet, databases)
+    #[structopt(long = "batch-size", default_value = "8192")]
+    batch_size: usize,
+
+    // number of time intervals to include
+    //  larger is faster but uses more memory (two buffers are allocated)
+    //  - rounded to a multiple of the variables' time chunk length
+    #[structopt(short = "b", long = "buffer-size", default_value = "250")]
+    buffer_size: usize,
+
+    // scale cell weights by 'coslat' or spherical cell 'area' (km^2)
+    //  - corrects means over large shapes and area weights sums
+    #[structopt(long = "cell-weight", default_value = "none")]
+    cell_weight: CellWeight,
+
+    // record the next unprocessed time index and output file lengths
+    //  after each buffer - reruns truncate output to the recorded lengths
+    //  and resume from the recorded index (uncompressed csv and ndjson
+    //  output files - database sinks are unsupported)
+    #[structopt(long = "checkpoint", parse(from_os_str))]
+    checkpoint: Option<PathBuf>,
+
+    // comma-separated target units (e.g. 'degC,mm/day') - variables with
+    //  units of the same quantity are converted before computing
+    //  statistics ('K', 'degC', 'degF', 'kg m-2 s-1', 'mm/day', 'mm/h',
+    //  'm', 'mm', 'in', 'Pa', 'hPa', 'm/s', 'km/h', 'mph', etc.)
+    #[structopt(long = "convert-units", use_delimiter = true)]
+    convert_units: Vec<Units>,
+
+    // comma-separated per-timestep correlations between variables over
+    //  shape cells as 'method:variable:variable' where method is
+    //  'pearson' or 'spearman' (e.g. 'pearson:tmax:vpd')
+    #[structopt(long = "correlate", use_delimiter = true)]
+    correlate: Vec<Correlation>,
+
+    // netcdf files, opendap urls, or s3 urls ('s3://bucket/key')
+    //  - directories and quoted glob patterns are expanded in sorted order
     #[structopt(parse(from_os_str), index = 2)]
     data_files: Vec<PathBuf>,
 
+    // add year, month, and day columns after the timestamp (csv, ndjson)
+    #[structopt(long = "date-columns")]
+    date_columns: bool,
+
+    // csv field delimiter - 'comma', 'tab', 'pipe', or a character
+    #[structopt(short = "d", long = "delimiter", default_value = "comma",
+        parse(try_from_str = ncproj_rs::sink::parse_delimiter))]
+    delimiter: u8,
+
+    // validate files and report variables, timesteps, memory usage,
+    //  and output rows without reading data
+    #[structopt(long = "dry-run")]
+    dry_run: bool,
+
+    // only process timesteps at or before this datetime
+    #[structopt(long = "end", parse(try_from_str = ncproj_rs::cf::parse_datetime))]
+    end: Option<DateTime<Utc>>,
+
     #[structopt(parse(from_os_str), index = 1)]
     index_file: PathBuf,
 
-    #[structopt(short = "t", long = "thread-count", default_value = "8")]
-    thread_count: u8,
-
-    // number of time intervals to include
-    //  larger is faster but uses more memory
-    #[structopt(short = "b", long = "buffer-size", default_value = "250")]
-    buffer_size: usize,
+    // number of threads reading data files - variables are divided
+    //  among threads
+    #[structopt(long = "io-thread-count", default_value = "1")]
+    io_thread_count: u8,
+
+    // level of variables with vertical dimensions (e.g. pressure levels)
+    //  - 'index:<n>', 'value:<coordinate>' (e.g. 'value:850'), or 'mean'
+    //  to average over all levels
+    #[structopt(long = "level")]
+    level: Option<Level>,
+
+    // cap on buffer memory (e.g. '8G') - computes the largest buffer
+    //  size that fits rather than using '--buffer-size'
+    #[structopt(long = "max-memory",
+        parse(try_from_str = ncproj_rs::parse_size))]
+    max_memory: Option<usize>,
+
+    // ensemble member of variables with a member dimension (e.g.
+    //  'realization') - a member index or 'each' to compute statistics
+    //  of each member with a leading 'member' column
+    #[structopt(long = "member")]
+    member: Option<Member>,
+
+    // csv representation of undefined values (e.g. shapes without valid
+    //  cells) - 'empty', 'nan', or a literal string
+    #[structopt(long = "null-as", default_value = "nan",
+        parse(try_from_str = ncproj_rs::sink::parse_null))]
+    null_as: String,
+
+    #[structopt(short = "o", long = "output", parse(from_os_str))]
+    output: Option<PathBuf>,
+
+    // directory of partitioned output files (see '--partition-by')
+    #[structopt(long = "output-dir", parse(from_os_str))]
+    output_dir: Option<PathBuf>,
+
+    // 'csv', 'ndjson', 'arrow', 'parquet', or 'netcdf' - netcdf writes
+    //  one (shape, time) variable per column (rows are always sorted)
+    #[structopt(short = "f", long = "output-format", default_value = "csv")]
+    output_format: OutputFormat,
+
+    // write a file per 'shape' (named by gis_join), 'year', or 'month'
+    //  into the output directory rather than a single output
+    //  - periods are written to hive-style 'year=2020/month=01' paths
+    //  - 'manifest.json' in the output directory lists all partitions
+    //  - each file remains open for the duration of the run
+    #[structopt(long = "partition-by")]
+    partition_by: Option<Partition>,
+
+    // bulk-insert rows into postgres rather than writing output
+    #[structopt(long = "postgres-url")]
+    postgres_url: Option<String>,
+
+    // number of decimal places of csv and ndjson values
+    #[structopt(long = "precision", default_value = "3")]
+    precision: usize,
+
+    // comma-separated quality flag values of accepted cells
+    #[structopt(long = "qc-accept", use_delimiter = true,
+        allow_hyphen_values = true)]
+    qc_accept: Vec<f32>,
+
+    // quality flag variable co-located with the data - cells whose flag
+    //  is not in qc-accept are treated as missing
+    #[structopt(long = "qc-variable")]
+    qc_variable: Option<String>,
+
+    // suppress progress reporting
+    #[structopt(short = "q", long = "quiet")]
+    quiet: bool,
+
+    // comma-separated 'variable=name' mappings renaming variables in
+    //  output columns (e.g. 'air_temperature=tmax')
+    #[structopt(long = "rename", use_delimiter = true,
+        parse(try_from_str = ncproj_rs::parse_key_value))]
+    rename: Vec<(String, String)>,
+
+    // aggregate statistics over 'monthly', 'seasonal', or 'annual' periods
+    //  - min and max are preserved, counts, sums, and degree days are
+    //  totaled, and remaining statistics are averaged over the period
+    //  (median, mode, and percentiles can not be resampled)
+    #[structopt(short = "r", long = "resample")]
+    resample: Option<Period>,
+
+    // comma-separated gis_join ids of shapes to process (default all)
+    //  - may be repeated and combined with shapes-file
+    #[structopt(long = "shape", use_delimiter = true)]
+    shape: Vec<String>,
+
+    // file listing gis_join ids of shapes to process, one per line
+    #[structopt(long = "shapes", parse(from_os_str))]
+    shapes_file: Option<PathBuf>,
+
+    // skip rows of shapes without valid cells at the timestep - netcdf
+    //  output fills them (with NaN) regardless
+    #[structopt(long = "skip-empty")]
+    skip_empty: bool,
+
+    // log and skip data files which fail to open and variable slabs
+    //  which fail to read (their statistics are undefined) rather than
+    //  aborting the run
+    //  - skipping data files requires '--variables' so the columns of
+    //  their variables are kept
+    #[structopt(long = "skip-errors")]
+    skip_errors: bool,
+
+    // write a csv report of skipped files and slabs (time index ranges)
+    #[structopt(long = "skip-report", parse(from_os_str))]
+    skip_report: Option<PathBuf>,
+
+    // write rows ordered by (timestamp, gis_join) so output is
+    //  reproducible across runs
+    #[structopt(long = "sorted")]
+    sorted: bool,
+
+    // insert rows into a sqlite database rather than writing output
+    #[structopt(long = "sqlite", parse(from_os_str))]
+    sqlite: Option<PathBuf>,
+
+    // only process timesteps at or after this datetime
+    #[structopt(long = "start",
+        parse(try_from_str = ncproj_rs::cf::parse_datetime))]
+    start: Option<DateTime<Utc>>,
+
+    // comma-separated list of statistics to compute for each feature
+    //  - 'min', 'max', 'mean', 'std', 'sum' (coverage weighted), 'count'
+    //  (valid cells), 'median', and percentiles (e.g. 'p25', 'p75')
+    //  - categorical variables support the majority 'mode' and the
+    //  coverage fraction of a class (e.g. 'frac3')
+    //  - threshold statistics count cells or compute the coverage
+    //  fraction compared ('gt', 'ge', 'lt', 'le') to a value (e.g.
+    //  'count_gt:32.2', 'frac_lt:0')
+    //  - coverage weighted cooling, growing, and heating degree days
+    //  over a base temperature in the variable's units (e.g. 'gdd:10',
+    //  'hdd:18.3')
+    #[structopt(short = "s", long = "stats", use_delimiter = true,
+        default_value = "min,max,mean,std")]
+    stats: Vec<Stat>,
+
+    // table name for database sinks
+    #[structopt(long = "table", default_value = "ncproj")]
+    table: String,
+
+    // number of threads computing statistics (default logical cpus)
+    #[structopt(short = "t", long = "thread-count")]
+    thread_count: Option<u8>,
+
+    // timestamp format of csv and ndjson rows - 'epoch' (seconds),
+    //  'iso8601', 'date' (e.g. '2020-01-31'), or a strftime pattern
+    //  (e.g. '%Y%m%d')
+    #[structopt(long = "time-format", default_value = "epoch")]
+    time_format: TimeFormat,
+
+    // comma-separated list of variables to process (default all)
+    #[structopt(short = "v", long = "variables", use_delimiter = true)]
+    variables: Vec<String>,
+
+    // weight cells by a 2d variable on the data grid as 'path:variable'
+    //  (e.g. 'population.nc:pop') - applied with cell-weight to mean,
+    //  std, and sum statistics
+    #[structopt(long = "weights")]
+    weights: Option<WeightRaster>,
 }
 
 impl Dump {
     pub fn execute(&self) -> Result<(), Box<dyn Error>> {
-        // read shape indices from file
-        let mut shapes = BTreeMap::new();
-
-        {
-            // open index file
-            let file = File::open(&self.index_file)?;
-            let buf_reader = BufReader::new(file);
-
-            // iterate over index entries
-            for result in buf_reader.lines() {
-                let line = result?;
-                let fields: Vec<&str> = line.split(" ").collect();
-
-                let x = fields[0].parse::<usize>()?;
-                let y = fields[1].parse::<usize>()?;
-
-                // add index to shapes map
-                let indices = shapes.entry(fields[2].to_string())
-                    .or_insert(Vec::new());
-                indices.push((x, y));
+        // order statistics of timesteps are not recoverable from their
+        //  average over a period
+        if self.resample.is_some() {
+            if let Some(stat) = self.stats.iter().find(|x| match x {
+                    Stat::Median | Stat::Mode | Stat::Percentile(_) => true,
+                    _ => false,
+                }) {
+                return Err(format!("statistic '{}' can not be resampled",
+                    stat.name()).into());
             }
         }
 
-        let shapes: Vec<(String, Vec<(usize, usize)>)> =
-            shapes.into_iter().collect();
+        // resume from checkpoint - periods may span buffers so
+        //  resampled output can not be checkpointed
+        //  - database batches are committed between checkpoints and
+        //  tables can not be truncated back to a checkpoint
+        let mut resume_index = None;
+        if let Some(path) = &self.checkpoint {
+            if self.resample.is_some() {
+                return Err("checkpoints are unsupported when resampling"
+                    .into());
+            } else if self.postgres_url.is_some() || self.sqlite.is_some() {
+                return Err("checkpoints are unsupported for database output"
+                    .into());
+            } else if self.output_format == OutputFormat::Arrow
+                    || self.output_format == OutputFormat::Netcdf
+                    || self.output_format == OutputFormat::Parquet {
+                return Err(format!("checkpoints are unsupported for {:?} \
+                    output", self.output_format).into());
+            }
 
-        // parse times
-        let (times, latitudes_len, longitudes_len) = {
-            let reader = netcdf::open(&self.data_files[0])?;
-            let times = crate::get_netcdf_values::<i64>(&reader, "time")?;
+            // appended compressed streams and stdout can not be truncated
+            match (&self.output, self.partition_by) {
+                (Some(output), None) => match output.extension()
+                        .and_then(|x| x.to_str()) {
+                    Some("gz") | Some("zst") => return Err(
+                        "checkpoints are unsupported for compressed output"
+                            .into()),
+                    _ => {},
+                },
+                (None, None) => return Err(
+                    "checkpoints require an output file".into()),
+                _ => {},
+            }
 
-            let datetime = Utc.ymd(1900, 1, 1).and_hms(0, 0, 0);
-            let times: Vec<i64> = times.iter().map(
-                    |x| (datetime + Duration::days(*x)).timestamp()
-                ).collect();
+            if path.exists() {
+                let (index, offsets) = read_checkpoint(path)?;
+                self.truncate_output(&offsets)?;
+                resume_index = Some(index);
+            }
+        }
+
+        if self.qc_variable.is_some() && self.qc_accept.is_empty() {
+            return Err("qc-variable requires qc-accept values".into());
+        }
 
-            let latitudes = 
-                crate::get_netcdf_values::<f64>(&reader, "lat")?;
-            let longitudes = 
-                crate::get_netcdf_values::<f64>(&reader, "lon")?;
+        // read the shape allowlist - blank lines and '#' comments of the
+        //  shapes file are ignored
+        let mut shapes = self.shape.clone();
+        if let Some(path) = &self.shapes_file {
+            for line in std::fs::read_to_string(path)?.lines() {
+                let line = line.trim();
+                if !line.is_empty() && !line.starts_with('#') {
+                    shapes.push(line.to_string());
+                }
+            }
 
-            (times, latitudes.len(), longitudes.len())
+            if shapes.is_empty() {
+                return Err(format!("no shapes listed in '{}'",
+                    path.display()).into());
+            }
+        }
+
+        let mut index_files = vec![self.index_file.clone()];
+        index_files.extend(self.additional_index.iter().cloned());
+
+        // netcdf sinks write a timestep at a time so records must arrive
+        //  ordered by time
+        let netcdf = self.output_format == OutputFormat::Netcdf
+            && self.postgres_url.is_none() && self.sqlite.is_none()
+            && self.partition_by.is_none();
+
+        let dumper = Dumper::new(self.index_file.clone(),
+                self.data_files.clone())
+            .accumulation(self.accumulation)
+            .buffer_size(self.buffer_size)
+            .cell_weight(self.cell_weight)
+            .convert_units(self.convert_units.clone())
+            .correlations(self.correlate.clone())
+            .end(self.end)
+            .index_files(index_files)
+            .io_thread_count(self.io_thread_count)
+            .level(self.level)
+            .max_memory(self.max_memory)
+            .member(self.member)
+            .qc_accept(self.qc_accept.clone())
+            .qc_variable(self.qc_variable.clone())
+            .renames(self.rename.iter().cloned().collect())
+            .resume_index(resume_index.unwrap_or(0))
+            .shapes(shapes)
+            .skip_errors(self.skip_errors)
+            .sorted(self.sorted || netcdf)
+            .start(self.start)
+            .stats(self.stats.clone())
+            .thread_count(self.thread_count
+                .unwrap_or_else(ncproj_rs::default_thread_count))
+            .variables(self.variables.clone())
+            .weights(self.weights.clone());
+
+        if self.dry_run {
+            return self.report(&dumper.plan()?);
+        }
+
+        let mut records = dumper.build()?;
+
+        // open output sink - appending to output of the checkpointed run
+        //  - rows of each member lead with the member index
+        //  - rows of multiple indexes are keyed by index name and shape
+        let members = records.members();
+        let mut values = records.columns().to_vec();
+        if members > 1 {
+            values.insert(0, "member".to_string());
+        }
+
+        let indexed = !self.additional_index.is_empty();
+        let columns = match indexed {
+            true => Columns::new(values)
+                .keys(vec!["index_name".to_string(), "gis_join".to_string()]),
+            false => Columns::new(values),
         };
 
-        // parse data
-        let mut features: Vec<Vec<String>> = Vec::new();
-        let buffers: Arc<RwLock<Vec<Vec<f32>>>> =
-            Arc::new(RwLock::new(Vec::new()));
-        let mut fill_values: Vec<f32> = Vec::new();
+        let text_format = TextFormat {
+            date_columns: self.date_columns,
+            delimiter: self.delimiter,
+            null: self.null_as.clone(),
+            precision: self.precision,
+            time_format: self.time_format.clone(),
+        };
 
-        for data_file in self.data_files.iter() {
-            // open data file
-            let reader = netcdf::open(data_file)?;
+        let mut sink = match (&self.postgres_url, &self.sqlite,
+                self.partition_by) {
+            (Some(_), Some(_), _) => return Err(
+                "postgres and sqlite sinks are mutually exclusive".into()),
+            (Some(_), _, Some(_)) | (_, Some(_), Some(_)) => return Err(
+                "database sinks do not support partitioning".into()),
+            (Some(url), None, None) => ncproj_rs::sink::open_postgres(url,
+                &self.table, columns, self.batch_size)?,
+            (None, Some(path), None) => ncproj_rs::sink::open_sqlite(path,
+                &self.table, columns, self.batch_size)?,
+            (None, None, Some(partition)) => {
+                let directory = self.output_dir.as_ref()
+                    .ok_or("partitioning requires an output directory")?;
+                ncproj_rs::sink::open_partitioned(self.output_format,
+                    partition, directory, columns, self.batch_size,
+                    &text_format, resume_index.is_some())?
+            },
+            (None, None, None) if netcdf && members > 1 => return Err(
+                "netcdf output does not support per-member statistics".into()),
+            (None, None, None) if netcdf && indexed => return Err(
+                "netcdf output does not support additional indexes".into()),
+            (None, None, None) if netcdf =>
+                ncproj_rs::sink::open_netcdf(&self.output,
+                    records.gis_joins(), columns.values)?,
+            (None, None, None) => ncproj_rs::sink::open(self.output_format,
+                &self.output, columns, self.batch_size, &text_format,
+                resume_index.is_some())?,
+        };
 
-            // compile set of dimension names
-            let mut dimensions = HashSet::new();
-            for dimension in reader.dimensions() {
-                dimensions.insert(dimension.name());
+        // initialize progress reporting over timesteps x shapes
+        let (start_index, end_index) = records.time_range();
+        let gis_joins = records.gis_joins().to_vec();
+        let index_names = records.index_names().to_vec();
+        let buffer_size = records.buffer_size();
+        let progress_bar = ncproj_rs::progress_bar(
+            (end_index.saturating_sub(start_index) * members
+                * gis_joins.len()) as u64,
+            self.quiet);
+
+        // resample feature statistics by statistic - correlations are
+        //  averaged over the period and members are resampled separately
+        let feature_column_count =
+            records.columns().len() - self.correlate.len();
+        let column_stats: Vec<Stat> = std::iter::repeat(Stat::Min)
+            .take(match members { 1 => 0, _ => 1 })
+            .chain(self.stats.iter().cloned().cycle()
+                .take(feature_column_count))
+            .chain(self.correlate.iter().map(|_| Stat::Mean)).collect();
+        let mut resampler = self.resample
+            .map(|x| Resampler::new(x, column_stats));
+
+        // netcdf sinks write empty rows as fill values rather than
+        //  skipping them
+        let skip_empty = self.skip_empty && !netcdf;
+
+        let (mut checkpoint_index, mut empty_count) = (start_index, 0);
+        for record in records.by_ref() {
+            let record = record?;
+
+            // records arrive a buffer at a time - the first record of a
+            //  buffer completes all previous buffers
+            if let Some(path) = &self.checkpoint {
+                let buffer_index = std::cmp::max(start_index,
+                    record.time_index / buffer_size * buffer_size);
+
+                if buffer_index > checkpoint_index {
+                    sink.flush()?;
+                    write_checkpoint(path, buffer_index,
+                        &self.output_offsets()?)?;
+                    checkpoint_index = buffer_index;
+                }
             }
 
-            // iterate over variables
-            let mut file_features = Vec::new();
-            for variable in reader.variables() {
-                // skip dimension variables 
-                if dimensions.contains(&variable.name()) {
+            // flag shapes without coverage at this timestep
+            if record.counts.iter().all(|x| *x == 0) {
+                empty_count += 1;
+                if skip_empty {
+                    progress_bar.inc(1);
                     continue;
                 }
+            }
+
+            let mut values = record.values;
+            if members > 1 {
+                values.insert(0, record.member as f32);
+            }
 
-                // add feature to features
-                file_features.push(variable.name());
-
-                // parse fill value
-                let fill_value = match variable.attribute("_FillValue") {
-                    Some(attribute) => match attribute.value()? {
-                        AttrValue::Float(value) => value as f32,
-                        AttrValue::Ushort(value) => value as f32,
-                        x => return Err(format!(
-                            "unsupported fill value type '{:?}'", x).into()),
-                    },
-                    None => return Err("fill value not found".into()),
-                };
-
-                fill_values.push(fill_value);
-
-                // add buffer to buffers
-                let mut buffers = buffers.write().unwrap();
-                buffers.push(
-                    vec![0f32; self.buffer_size * latitudes_len * longitudes_len]
-                );
+            match resampler.as_mut() {
+                Some(resampler) => resampler.update(
+                    record.shape_index * members + record.member,
+                    record.timestamp, &values),
+                None if indexed => sink.write_keyed_row(
+                    &[&index_names[record.shape_index], &record.gis_join],
+                    record.timestamp, &values)?,
+                None => sink.write_row(&record.gis_join,
+                    record.timestamp, &values)?,
             }
 
-            features.push(file_features);
+            progress_bar.inc(1);
         }
 
-        // print csv header
-        print!("gis_join,timestamp");
-        for file_features in features.iter() {
-            for feature in file_features.iter() {
-                print!(",min_{},max_{}", feature, feature);
-            }
+        progress_bar.finish();
+
+        if empty_count > 0 && !self.quiet {
+            eprintln!("{} shape timesteps had no valid cells{}", empty_count,
+                match skip_empty { true => " (skipped)", false => "" });
         }
-        println!();
 
-        // initailize thread channels
-        let (index_tx, index_rx): (Sender<(usize, usize)>,
-            Receiver<(usize, usize)>) = crossbeam_channel::unbounded();
-        let (data_tx, data_rx): (Sender<(usize, usize, Vec<f32>)>,
-            Receiver<(usize, usize, Vec<f32>)>) = crossbeam_channel::unbounded();
+        // report data files and slabs skipped due to errors
+        let skipped = records.skipped();
+        if !skipped.is_empty() {
+            eprintln!("skipped {} data files or slabs after errors",
+                skipped.len());
+        }
 
-        // initialize print thread
-        let completed_count = Arc::new(AtomicUsize::new(0));
-        let time_index_offset = Arc::new(AtomicUsize::new(0));
+        if let Some(path) = &self.skip_report {
+            let mut writer = csv::Writer::from_path(path)?;
+            writer.write_record(&["data_file", "variable", "start_index",
+                "end_index", "error"])?;
+            for skip in skipped.iter() {
+                writer.write_record(&[
+                    skip.data_file.display().to_string(),
+                    skip.variable.clone().unwrap_or_default(),
+                    skip.start_index.to_string(),
+                    skip.end_index.to_string(),
+                    skip.error.clone(),
+                ])?;
+            }
 
-        let handle = {
-            let (completed_count, time_index_offset) =
-                (completed_count.clone(), time_index_offset.clone());  
+            writer.flush()?;
+        }
 
-            let (shapes, times) = (shapes.clone(), times.clone());  
-            std::thread::spawn(move || {
-                for (i, j, data) in data_rx.iter() {
-                    let time_index_offset = time_index_offset
-                        .load(Ordering::Relaxed);
+        // write resampled periods
+        if let Some(resampler) = resampler {
+            for (timestamp, j, data) in resampler.finish() {
+                let (index_name, gis_join) =
+                    (&index_names[j / members], &gis_joins[j / members]);
+                match indexed {
+                    true => sink.write_keyed_row(&[index_name, gis_join],
+                        timestamp, &data)?,
+                    false => sink.write_row(gis_join, timestamp, &data)?,
+                }
+            }
+        }
 
-                    print!("{},{}", shapes[j].0,
-                        times[time_index_offset + i]);
+        sink.close()?;
+        if let Some(path) = &self.checkpoint {
+            write_checkpoint(path, end_index, &self.output_offsets()?)?;
+        }
 
-                    for k in 0..data.len() {
-                        print!(",{:.3}", data[k]);
-                    }
-                    println!("");
+        Ok(())
+    }
+}
 
-                    completed_count.fetch_add(1, Ordering::SeqCst);
-                }
-            })
-        };
+impl Dump {
+    // list the lengths of output files - the output file or partition
+    //  files of the output directory
+    fn output_offsets(&self) -> Result<Vec<(u64, PathBuf)>, Box<dyn Error>> {
+        let mut paths = Vec::new();
+        match (&self.output, &self.output_dir, self.partition_by) {
+            (_, Some(directory), Some(_)) =>
+                list_files(directory, &mut paths)?,
+            (Some(output), _, None) if output.exists() =>
+                paths.push(output.clone()),
+            _ => {},
+        }
 
-        // start worker threads
-        let (fill_values, shapes) =
-            (Arc::new(fill_values), Arc::new(shapes.clone()));
-
-        let mut worker_handles = Vec::new();
-        for _ in 0..self.thread_count {
-            let (latitudes_len, longitudes_len) =
-                (latitudes_len.clone(), longitudes_len.clone());
-
-            let (buffers, data_tx, fill_values, index_rx, shapes) =
-                (buffers.clone(), data_tx.clone(), fill_values.clone(), 
-                    index_rx.clone(), shapes.clone());
-
-            let handle = std::thread::spawn(move || {
-                // compute feature values for each shape
-                for (i, j) in index_rx.iter() {
-                    let mut data = Vec::new();
-
-                    // get shape indices - <x, y> coordinates in file
-                    let (shape_id, indices) = &shapes[j];
-
-                    let buffers = buffers.read().unwrap();
-                    for k in 0..buffers.len() {
-                        let buffer = &buffers[k];
-                        let fill_value = fill_values[k];
-
-                        let (mut min, mut max) = (f32::MAX, f32::MIN);
-                        for (x, y) in indices.iter() {
-                            let buffer_index = 
-                                i * (latitudes_len * longitudes_len) 
-                                + y * longitudes_len + x;
-
-                            let value = buffer[buffer_index];
-                            if value == fill_value {
-                                continue;
-                            }
-                            
-                            if value < min {
-                                min = value;
-                            }
-
-                            if value > max {
-                                max = value;
-                            }
-                        }
-
-                        data.push(min);
-                        data.push(max);
-                    }
-
-                    if let Err(e) = data_tx.send((i, j, data)) {
-                        println!("failed to write data: {}", e);
-                    }
-                }
-            });
+        let mut offsets = Vec::new();
+        for path in paths {
+            offsets.push((std::fs::metadata(&path)?.len(), path));
+        }
 
-            worker_handles.push(handle);
+        Ok(offsets)
+    }
+
+    // truncate output files to their checkpointed lengths - partition
+    //  files created after the checkpoint are removed
+    fn truncate_output(&self, offsets: &[(u64, PathBuf)])
+            -> Result<(), Box<dyn Error>> {
+        if let (Some(directory), Some(_)) =
+                (&self.output_dir, self.partition_by) {
+            let mut paths = Vec::new();
+            list_files(directory, &mut paths)?;
+            for path in paths {
+                if !offsets.iter().any(|x| x.1 == path) {
+                    std::fs::remove_file(&path)?;
+                }
+            }
         }
 
-        // iterate over time values
-        let mut count = 0;
-        let sleep_duration = std::time::Duration::from_millis(50);
-        for i in (0..times.len()).step_by(self.buffer_size) {
-            time_index_offset.store(i, Ordering::SeqCst);
+        for (offset, path) in offsets.iter() {
+            std::fs::OpenOptions::new().write(true).open(path)
+                .map_err(|e| format!("failed to open checkpointed output \
+                    '{}': {}", path.display(), e))?
+                .set_len(*offset)?;
+        }
 
-            let time_slice_len =
-                std::cmp::min(self.buffer_size, times.len() - i);
+        Ok(())
+    }
 
-            let slice_len = [time_slice_len,
-                latitudes_len, longitudes_len];
+    // print the plan of a run and its estimated output
+    fn report(&self, plan: &Plan) -> Result<(), Box<dyn Error>> {
+        // resampled rows are written for each period
+        let period_count = match self.resample {
+            Some(period) => plan.timestamps.iter()
+                .map(|x| period.start(*x)).collect::<HashSet<i64>>().len(),
+            None => plan.timestamps.len(),
+        };
 
-            // read data into buffers
-            let mut buffer_index = 0;
-            for (j, data_file) in self.data_files.iter().enumerate() {
-                // open data file
-                let reader = netcdf::open(data_file)?;
+        println!("data files: {}", plan.data_files.len());
+        for skip in plan.skipped.iter() {
+            println!("skipped data file: '{}' ({})",
+                skip.data_file.display(), skip.error);
+        }
+        println!("variables: {}", plan.features.join(","));
+        println!("columns: {}", plan.columns.len());
+        println!("shapes: {}", plan.shape_count);
+        if plan.members > 1 {
+            println!("members: {}", plan.members);
+        }
+        match (plan.timestamps.first(), plan.timestamps.last()) {
+            (Some(first), Some(last)) => println!("timesteps: {} ({} - {})",
+                plan.timestamps.len(), Utc.timestamp(*first, 0),
+                Utc.timestamp(*last, 0)),
+            _ => println!("timesteps: 0"),
+        }
+        println!("window: {}x{} cells", plan.window.0, plan.window.1);
+        println!("buffer size: {} timesteps", plan.buffer_size);
+        println!("buffer memory: {:.1} MiB",
+            plan.memory as f64 / (1u64 << 20) as f64);
+        println!("output rows: {}", period_count * plan.members
+            * plan.shape_count);
 
-                // iterate over identified variables
-                for feature in features[j].iter() {
-                    let variable = reader.variable(feature).unwrap();
+        Ok(())
+    }
+}
 
-                    // copy variable to buffer
-                    let buffer_size = time_slice_len 
-                        * latitudes_len * longitudes_len;
-                    let mut buffers = buffers.write().unwrap();
+// read the next time index and the '<length>\t<path>' lines of output
+//  files from a checkpoint
+fn read_checkpoint(path: &PathBuf)
+        -> Result<(usize, Vec<(u64, PathBuf)>), Box<dyn Error>> {
+    let contents = std::fs::read_to_string(path)?;
+    let invalid = |e: String| format!("invalid checkpoint '{}': {}",
+        path.display(), e);
+
+    let mut lines = contents.lines();
+    let index = lines.next().unwrap_or("").trim().parse::<usize>()
+        .map_err(|e| invalid(e.to_string()))?;
+
+    let mut offsets = Vec::new();
+    for line in lines.filter(|x| !x.trim().is_empty()) {
+        let mut fields = line.splitn(2, '\t');
+        match (fields.next().map(|x| x.parse::<u64>()), fields.next()) {
+            (Some(Ok(offset)), Some(output)) =>
+                offsets.push((offset, PathBuf::from(output))),
+            _ => return Err(invalid(format!("malformed line '{}'", line))
+                .into()),
+        }
+    }
 
-                    variable.values_to(
-                        &mut buffers[buffer_index][..buffer_size],
-                        Some(&[i, 0, 0]), Some(&slice_len))?;
+    Ok((index, offsets))
+}
 
-                    buffer_index += 1;
-                }
-            }
+// atomically replace the checkpoint with the next time index and the
+//  lengths of output files
+fn write_checkpoint(path: &PathBuf, index: usize,
+        offsets: &[(u64, PathBuf)]) -> Result<(), Box<dyn Error>> {
+    let mut tmp_path = path.clone().into_os_string();
+    tmp_path.push(".tmp");
 
-            // send indices down channel
-            count += time_slice_len * shapes.len();
-            for j in 0..time_slice_len {
-                for k in 0..shapes.len() {
-                    index_tx.send((j, k))?;
-                }
-            }
+    let mut contents = format!("{}\n", index);
+    for (offset, output) in offsets.iter() {
+        contents.push_str(&format!("{}\t{}\n", offset, output.display()));
+    }
 
-            // wait for all indices to be computed
-            while completed_count.load(Ordering::SeqCst) != count {
-                std::thread::sleep(sleep_duration);
-            }
-        }
+    std::fs::write(&tmp_path, contents)?;
+    std::fs::rename(&tmp_path, path)?;
+    Ok(())
+}
 
-        // wait until all threads have finished
-        drop(index_tx);
-        for handle in worker_handles {
-            if let Err(e) = handle.join() {
-                return Err(format!("failed to join handle: {:?}", e).into());
-            }
-        }
+// recursively list partition files of a directory - manifests are
+//  rewritten on resume and excluded
+fn list_files(directory: &PathBuf, paths: &mut Vec<PathBuf>)
+        -> Result<(), Box<dyn Error>> {
+    if !directory.exists() {
+        return Ok(());
+    }
 
-        drop(data_tx);
-        if let Err(e) = handle.join() {
-            return Err(format!("failed to join handle: {:?}", e).into());
+    for entry in std::fs::read_dir(directory)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            list_files(&path, paths)?;
+        } else if !path.file_name().and_then(|x| x.to_str())
+                .map(|x| x.starts_with("manifest.json")).unwrap_or(false) {
+            paths.push(path);
         }
-
-        Ok(())
     }
+
+    Ok(())
 }
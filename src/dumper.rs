@@ -0,0 +1,1613 @@
+use chrono::{DateTime, Utc};
+use crossbeam_channel::{Receiver, Sender};
+use rayon::prelude::*;
+
+use crate::dataset::{AttrValue, Dataset, Variable};
+use crate::grid::{CellWeight, Grid, LonConvention, WeightRaster};
+use crate::stat::{Accumulator, Correlation, CorrelationMethod, Precision,
+    Stat};
+use crate::units::Units;
+
+use std::collections::{HashMap, HashSet};
+use std::error::Error;
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::sync::{Arc, Condvar, Mutex, RwLock};
+use std::thread::JoinHandle;
+
+// statistics computed over a shape at a single timestep
+#[derive(Clone, Debug, PartialEq)]
+pub struct Record {
+    pub counts: Vec<usize>, // valid cells of each feature
+    pub gis_join: String,
+    pub member: usize, // ensemble member when computed per member
+    pub shape_index: usize,
+    pub time_index: usize,
+    pub timestamp: i64,
+    pub values: Vec<f32>,
+}
+
+// builder aggregating data file variables over indexed shapes
+pub struct Dumper {
+    accumulation: Precision,
+    buffer_size: usize,
+    cell_weight: CellWeight,
+    convert_units: Vec<Units>,
+    correlations: Vec<Correlation>,
+    data_files: Vec<PathBuf>,
+    end: Option<DateTime<Utc>>,
+    index_files: Vec<PathBuf>,
+    io_thread_count: u8,
+    level: Option<Level>,
+    max_memory: Option<usize>,
+    member: Option<Member>,
+    qc_accept: Vec<f32>,
+    qc_variable: Option<String>,
+    renames: HashMap<String, String>,
+    resume_index: usize,
+    shapes: Vec<String>,
+    skip_errors: bool,
+    sorted: bool,
+    start: Option<DateTime<Utc>>,
+    stats: Vec<Stat>,
+    thread_count: u8,
+    variables: Vec<String>,
+    weights: Option<WeightRaster>,
+}
+
+impl Dumper {
+    pub fn new(index_file: PathBuf, data_files: Vec<PathBuf>) -> Dumper {
+        Dumper {
+            accumulation: Precision::F32,
+            buffer_size: 250,
+            cell_weight: CellWeight::None,
+            convert_units: Vec::new(),
+            correlations: Vec::new(),
+            data_files: data_files,
+            end: None,
+            index_files: vec![index_file],
+            io_thread_count: 1,
+            level: None,
+            max_memory: None,
+            member: None,
+            qc_accept: Vec::new(),
+            qc_variable: None,
+            renames: HashMap::new(),
+            resume_index: 0,
+            shapes: Vec::new(),
+            skip_errors: false,
+            sorted: false,
+            start: None,
+            stats: vec![Stat::Min, Stat::Max, Stat::Mean, Stat::Std],
+            thread_count: crate::default_thread_count(),
+            variables: Vec::new(),
+            weights: None,
+        }
+    }
+
+    // precision of statistic accumulation - f64 avoids drift of sums
+    //  and means over large shapes at the cost of vectorization
+    pub fn accumulation(mut self, accumulation: Precision) -> Dumper {
+        self.accumulation = accumulation;
+        self
+    }
+
+    // number of time intervals to read at once
+    //  larger is faster but uses more memory (two buffers are allocated)
+    //  - rounded to a multiple of the variables' time chunk length
+    pub fn buffer_size(mut self, buffer_size: usize) -> Dumper {
+        self.buffer_size = buffer_size;
+        self
+    }
+
+    // scale cell coverage weights by latitude or cell area
+    //  - affects mean, std, and sum statistics
+    pub fn cell_weight(mut self, cell_weight: CellWeight) -> Dumper {
+        self.cell_weight = cell_weight;
+        self
+    }
+
+    // target units - values of variables with units of the same quantity
+    //  are converted when read so statistics are in these units
+    pub fn convert_units(mut self, convert_units: Vec<Units>) -> Dumper {
+        self.convert_units = convert_units;
+        self
+    }
+
+    // correlations between feature pairs over shape cells - appended
+    //  to record values after all feature statistics
+    pub fn correlations(mut self, correlations: Vec<Correlation>) -> Dumper {
+        self.correlations = correlations;
+        self
+    }
+
+    // only process timesteps at or before this datetime
+    pub fn end(mut self, end: Option<DateTime<Utc>>) -> Dumper {
+        self.end = end;
+        self
+    }
+
+    // index files whose shapes are all aggregated in one read of the
+    //  data - replaces the index file of the dumper
+    pub fn index_files(mut self, index_files: Vec<PathBuf>) -> Dumper {
+        self.index_files = index_files;
+        self
+    }
+
+    // number of threads reading slabs - variables are divided
+    //  among threads which each open their own readers
+    pub fn io_thread_count(mut self, io_thread_count: u8) -> Dumper {
+        self.io_thread_count = io_thread_count;
+        self
+    }
+
+    // selection of levels of variables with dimensions between time and
+    //  the grid (e.g. pressure levels) - required unless singleton
+    pub fn level(mut self, level: Option<Level>) -> Dumper {
+        self.level = level;
+        self
+    }
+
+    // cap on slab buffer bytes - overrides buffer_size with the largest
+    //  number of timesteps that fit
+    pub fn max_memory(mut self, max_memory: Option<usize>) -> Dumper {
+        self.max_memory = max_memory;
+        self
+    }
+
+    // selection of ensemble members of variables with a member dimension
+    //  - required unless singleton
+    pub fn member(mut self, member: Option<Member>) -> Dumper {
+        self.member = member;
+        self
+    }
+
+    // accepted values of the quality flag variable
+    pub fn qc_accept(mut self, qc_accept: Vec<f32>) -> Dumper {
+        self.qc_accept = qc_accept;
+        self
+    }
+
+    // quality flag variable co-located with features - cells with flags
+    //  not in qc_accept are treated as missing
+    pub fn qc_variable(mut self, qc_variable: Option<String>) -> Dumper {
+        self.qc_variable = qc_variable;
+        self
+    }
+
+    // output names of variables used in column names (variable -> name)
+    pub fn renames(mut self, renames: HashMap<String, String>) -> Dumper {
+        self.renames = renames;
+        self
+    }
+
+    // skip time indices before this index (in addition to start)
+    pub fn resume_index(mut self, resume_index: usize) -> Dumper {
+        self.resume_index = resume_index;
+        self
+    }
+
+    // gis_join ids of shapes to process (default all) - the read
+    //  window is reduced to the bounding box of the listed shapes
+    pub fn shapes(mut self, shapes: Vec<String>) -> Dumper {
+        self.shapes = shapes;
+        self
+    }
+
+    // fill slabs which fail to read with NaN rather than failing - see
+    //  DumpIter::skipped
+    //  - variables of data files which fail to open are filled with NaN
+    //  when listed in variables (the files are otherwise required)
+    pub fn skip_errors(mut self, skip_errors: bool) -> Dumper {
+        self.skip_errors = skip_errors;
+        self
+    }
+
+    // return records of each timestep ordered by gis_join rather than
+    //  as computed - each slab is held until aggregation completes
+    pub fn sorted(mut self, sorted: bool) -> Dumper {
+        self.sorted = sorted;
+        self
+    }
+
+    // only process timesteps at or after this datetime
+    pub fn start(mut self, start: Option<DateTime<Utc>>) -> Dumper {
+        self.start = start;
+        self
+    }
+
+    // statistics to compute for each feature
+    pub fn stats(mut self, stats: Vec<Stat>) -> Dumper {
+        self.stats = stats;
+        self
+    }
+
+    // number of threads computing statistics (default logical cpus)
+    pub fn thread_count(mut self, thread_count: u8) -> Dumper {
+        self.thread_count = thread_count;
+        self
+    }
+
+    // variables to process (default all)
+    pub fn variables(mut self, variables: Vec<String>) -> Dumper {
+        self.variables = variables;
+        self
+    }
+
+    // scale cell weights by an auxiliary weight raster on the data grid
+    //  (e.g. population weighted means)
+    pub fn weights(mut self, weights: Option<WeightRaster>) -> Dumper {
+        self.weights = weights;
+        self
+    }
+
+    // validate inputs and compute the run layout without reading data
+    pub fn plan(&self) -> Result<Plan, Box<dyn Error>> {
+        let layout = self.layout()?;
+        let (start_index, end_index) = (layout.start_index, layout.end_index);
+
+        Ok(Plan {
+            buffer_size: layout.buffer_size,
+            columns: layout.columns,
+            data_files: layout.data_files,
+            features: layout.features.into_iter().flatten().collect(),
+            members: layout.members,
+            memory: 2 * layout.encodings.len() * layout.members
+                * layout.buffer_size * layout.window.0 * layout.window.1
+                * std::mem::size_of::<f32>(),
+            shape_count: layout.shapes.len(),
+            skipped: layout.skipped,
+            timestamps: layout.times[start_index..end_index].to_vec(),
+            window: layout.window,
+        })
+    }
+
+    // read shapes and data file metadata into the layout of a run
+    fn layout(&self) -> Result<Layout, Box<dyn Error>> {
+        // read shape indices from files - each shape records the name of
+        //  its index (the file name up to the first '.')
+        let mut shapes: Vec<(String, Vec<(usize, usize, f32)>)> = Vec::new();
+        let (mut index_names, mut names) = (Vec::new(), HashSet::new());
+        for index_file in self.index_files.iter() {
+            let name = index_file.file_name()
+                .map(|x| x.to_string_lossy().split('.').next()
+                    .unwrap_or_default().to_string())
+                .unwrap_or_default();
+            if !names.insert(name.clone()) {
+                return Err(format!("duplicate index name '{}' of '{}'",
+                    name, index_file.display()).into());
+            }
+
+            for shape in crate::format::read_index(index_file)? {
+                shapes.push(shape);
+                index_names.push(name.clone());
+            }
+        }
+
+        // retain only listed shapes
+        if !self.shapes.is_empty() {
+            let ids: HashSet<&str> = shapes.iter()
+                .map(|x| x.0.as_str()).collect();
+            if let Some(id) = self.shapes.iter()
+                    .find(|x| !ids.contains(x.as_str())) {
+                return Err(format!("shape '{}' not found in index", id)
+                    .into());
+            }
+
+            let listed: HashSet<&String> = self.shapes.iter().collect();
+            let (retained_shapes, retained_names): (Vec<_>, Vec<_>) = shapes
+                .into_iter()
+                .zip(index_names.into_iter())
+                .filter(|(shape, _)| listed.contains(&shape.0)).unzip();
+            shapes = retained_shapes;
+            index_names = retained_names;
+        }
+
+        // expand directories and glob patterns
+        let data_files = crate::expand_paths(&self.data_files)?;
+        if data_files.is_empty() {
+            return Err("no data files found".into());
+        }
+
+        // parse times of the first data file that opens - files failing
+        //  to open before it are skipped below when skipping errors
+        let mut failures = Vec::new();
+        let (times, latitudes_len, longitudes_len) = loop {
+            let data_file = match data_files.get(failures.len()) {
+                Some(data_file) => data_file,
+                None => return Err(format!("failed to open any data file: \
+                    {}", failures.join(", ")).into()),
+            };
+
+            let reader = match Dataset::open(data_file) {
+                Ok(reader) => reader,
+                Err(e) if self.skip_errors => {
+                    failures.push(e.to_string());
+                    continue;
+                },
+                Err(e) => return Err(e),
+            };
+
+            let times = crate::cf::parse_times(&reader)?;
+
+            let (latitudes_len, longitudes_len) =
+                crate::grid::read_dimensions(&reader)?;
+
+            break (times, latitudes_len, longitudes_len);
+        };
+
+        // compute the bounding box of indexed cells - validated against
+        //  the grid before cells index into grid sized weights
+        let (x_offset, y_offset, x_max, y_max) = shapes.iter()
+            .flat_map(|x| x.1.iter())
+            .fold((usize::MAX, usize::MAX, 0, 0), |b, (x, y, _)|
+                (b.0.min(*x), b.1.min(*y), b.2.max(*x), b.3.max(*y)));
+
+        if x_offset <= x_max
+                && (x_max >= longitudes_len || y_max >= latitudes_len) {
+            return Err(format!("index cell ({}, {}) outside of {}x{} grid",
+                x_max, y_max, longitudes_len, latitudes_len).into());
+        }
+
+        // scale cell weights by latitude or area
+        if self.cell_weight != CellWeight::None {
+            let reader = Dataset::open(&data_files[failures.len()])?;
+            let grid = Grid::open(&reader, LonConvention::Auto)?;
+
+            for (_, indices) in shapes.iter_mut() {
+                for (x, y, weight) in indices.iter_mut() {
+                    *weight *= self.cell_weight.weight(&grid, *x, *y) as f32;
+                }
+            }
+        }
+
+        // scale cell weights by auxiliary weights
+        if let Some(weights) = &self.weights {
+            let weights = weights.read(latitudes_len, longitudes_len)?;
+            for (_, indices) in shapes.iter_mut() {
+                for (x, y, weight) in indices.iter_mut() {
+                    *weight *= weights[*y * longitudes_len + *x];
+                }
+            }
+        }
+
+        // read only the bounding box of indexed cells - cell indices
+        //  are remapped relative to the bounding box origin
+        let (x_offset, y_offset, latitudes_len, longitudes_len) =
+                match x_offset <= x_max {
+            true => (x_offset, y_offset,
+                y_max - y_offset + 1, x_max - x_offset + 1),
+            false => (0, 0, 0, 0),
+        };
+
+        for (_, indices) in shapes.iter_mut() {
+            for (x, y, _) in indices.iter_mut() {
+                *x -= x_offset;
+                *y -= y_offset;
+            }
+        }
+
+        // parse data
+        let mut features: Vec<Vec<String>> = Vec::new();
+        let mut encodings: Vec<Encoding> = Vec::new();
+        let mut axes: Vec<Vec<Axis>> = Vec::new();
+        let mut members = 1;
+        let mut qc = None;
+        let mut time_chunks: Vec<usize> = Vec::new();
+
+        let (mut opened_files, mut skipped) = (Vec::new(), Vec::new());
+        for (i, data_file) in data_files.iter().enumerate() {
+            // open data file - files which failed while parsing times are
+            //  not reopened
+            //  - variables of skipped files are only known when requested
+            //  so their columns are kept (and filled with NaN)
+            let reader = match failures.get(i) {
+                Some(e) => Err(e.to_string().into()),
+                None => Dataset::open(data_file),
+            };
+
+            let reader = match reader {
+                Ok(reader) => reader,
+                Err(e) if self.skip_errors && self.variables.is_empty() =>
+                    return Err(format!("failed to open '{}' - skipping data \
+                        files requires listing variables so their columns \
+                        are kept: {}", data_file.display(), e).into()),
+                Err(e) if self.skip_errors => {
+                    eprintln!("skipping data file: {}", e);
+                    skipped.push(Skipped {
+                        data_file: data_file.clone(),
+                        end_index: times.len(),
+                        error: e.to_string(),
+                        start_index: 0,
+                        variable: None,
+                    });
+
+                    continue;
+                },
+                Err(e) => return Err(e),
+            };
+
+            opened_files.push(data_file.clone());
+
+            // compile set of dimension names
+            let mut dimensions = HashSet::new();
+            for dimension in reader.dimensions() {
+                dimensions.insert(dimension.name());
+            }
+
+            // iterate over variables
+            let mut file_features = Vec::new();
+            for variable in reader.variables() {
+                // the quality flag variable is read after all features
+                if self.qc_variable.as_ref() == Some(&variable.name()) {
+                    let (qc_axes, _) = slab_axes(&reader, &variable,
+                        self.level, self.member)?;
+                    qc = Some((opened_files.len() - 1, variable.name(),
+                        Encoding::read(&variable)?, qc_axes));
+                    continue;
+                }
+
+                // skip dimension and unrequested variables
+                if dimensions.contains(&variable.name()) || (!self
+                        .variables.is_empty()
+                        && !self.variables.contains(&variable.name())) {
+                    continue;
+                }
+
+                // add feature to features
+                file_features.push(variable.name());
+
+                // convert values to the first target of the same quantity
+                //  - variables with unrecognized units are not converted
+                let mut encoding = Encoding::read(&variable)?;
+                match Units::read(&variable) {
+                    _ if self.convert_units.is_empty() => {},
+                    Ok(Some(units)) => {
+                        if let Some((scale, offset)) = self.convert_units
+                                .iter().find_map(|x| units.conversion(x)) {
+                            encoding.convert(scale, offset);
+                        }
+                    },
+                    Ok(None) => {},
+                    Err(e) => eprintln!("not converting variable '{}': {}",
+                        variable.name(), e),
+                }
+
+                encodings.push(encoding);
+
+                // all variables computed per member share the member count
+                let (variable_axes, member_len) = slab_axes(&reader,
+                    &variable, self.level, self.member)?;
+                if member_len > 1 {
+                    if members > 1 && members != member_len {
+                        return Err(format!("variable '{}' has {} members - \
+                            expected {}", variable.name(), member_len,
+                            members).into());
+                    }
+
+                    members = member_len;
+                }
+
+                // record time chunk length of chunked variables
+                let time_position = variable_axes.iter()
+                    .position(|x| *x == Axis::Time).unwrap_or(0);
+                if let Some(chunk_len) = variable.chunking()?
+                        .and_then(|x| x.get(time_position).cloned()) {
+                    time_chunks.push(chunk_len);
+                }
+
+                axes.push(variable_axes);
+            }
+
+            features.push(file_features);
+        }
+
+        // requested variables not found are assumed to belong to skipped
+        //  data files - their buffers are never read and remain undefined
+        let missing: Vec<String> = self.variables.iter()
+            .filter(|x| !features.iter().any(|y| y.contains(x)))
+            .cloned().collect();
+        if !skipped.is_empty() && !missing.is_empty() {
+            for variable in missing.iter() {
+                eprintln!("variable '{}' not found in opened data files - \
+                    filling with NaN", variable);
+                encodings.push(Encoding::undefined());
+                axes.push(vec![Axis::Time, Axis::Y, Axis::X]);
+            }
+
+            features.push(missing);
+        }
+
+        let data_files = opened_files;
+
+        // append quality flags to feature buffers
+        let qc = match (&self.qc_variable, qc) {
+            (Some(_), Some((j, name, encoding, qc_axes))) => {
+                encodings.push(encoding);
+                axes.push(qc_axes);
+                Some((j, name))
+            },
+            (Some(name), None) => return Err(format!(
+                "quality flag variable '{}' not found", name).into()),
+            (None, _) => None,
+        };
+
+        // ensure all requested variables were found
+        for variable in self.variables.iter() {
+            if !features.iter().any(|x| x.contains(variable)) {
+                return Err(format!(
+                    "variable '{}' not found", variable).into());
+            }
+        }
+
+        // compile output columns - using renamed variables
+        let rename = |feature: &String| self.renames.get(feature)
+            .cloned().unwrap_or_else(|| feature.to_string());
+
+        let mut columns = Vec::new();
+        for file_features in features.iter() {
+            for feature in file_features.iter() {
+                for stat in self.stats.iter() {
+                    columns.push(format!("{}_{}",
+                        stat.name(), rename(feature)));
+                }
+            }
+        }
+
+        // resolve correlated features to buffer indices
+        let flat_features: Vec<&String> =
+            features.iter().flat_map(|x| x.iter()).collect();
+        let mut correlations = Vec::new();
+        for correlation in self.correlations.iter() {
+            let (a, b) = &correlation.features;
+            let position = |feature: &String| flat_features.iter()
+                .position(|x| *x == feature)
+                .ok_or_else(|| format!("correlated variable '{}' not found",
+                    feature));
+
+            correlations.push((correlation.method, position(a)?, position(b)?));
+            columns.push(Correlation {
+                features: (rename(a), rename(b)),
+                method: correlation.method,
+            }.name());
+        }
+
+        // renaming may map distinct variables onto the same column
+        let mut names = HashSet::new();
+        if let Some(column) = columns.iter().find(|x| !names.insert(*x)) {
+            return Err(format!("duplicate output column '{}'",
+                column).into());
+        }
+
+        // compute time index range
+        let (start_index, end_index) =
+            crate::cf::time_range(&times, self.start, self.end);
+        let start_index = std::cmp::min(
+            std::cmp::max(start_index, self.resume_index), end_index);
+
+        // use the largest buffer fitting within max_memory - both slab
+        //  buffers hold every feature and member over the bounding box
+        let buffer_size = match self.max_memory {
+            Some(max_memory) => {
+                let step_size = 2 * encodings.len() * members
+                    * latitudes_len * longitudes_len
+                    * std::mem::size_of::<f32>();
+                let buffer_size = max_memory / std::cmp::max(step_size, 1);
+                if buffer_size == 0 {
+                    return Err(format!("max memory of {} bytes is less \
+                        than a single timestep ({} bytes)",
+                        max_memory, step_size).into());
+                }
+
+                std::cmp::min(buffer_size,
+                    std::cmp::max(end_index.saturating_sub(start_index), 1))
+            },
+            None => self.buffer_size,
+        };
+
+        // align slabs with time chunks so each chunk is decompressed once
+        //  - unless the aligned buffer exceeds max_memory
+        let buffer_size = match align_buffer_size(buffer_size, &time_chunks) {
+            x if self.max_memory.is_some() && x > buffer_size => {
+                eprintln!("buffer of {} timesteps aligned with time chunks \
+                    exceeds max memory - capped at {} timesteps, chunks \
+                    may be decompressed more than once", x, buffer_size);
+                buffer_size
+            },
+            x => x,
+        };
+
+        Ok(Layout {
+            axes: axes,
+            buffer_size: buffer_size,
+            columns: columns,
+            correlations: correlations,
+            data_files: data_files,
+            encodings: encodings,
+            end_index: end_index,
+            features: features,
+            index_names: index_names,
+            members: members,
+            offset: (y_offset, x_offset),
+            qc: qc,
+            shapes: shapes,
+            skipped: skipped,
+            start_index: start_index,
+            times: times,
+            window: (latitudes_len, longitudes_len),
+        })
+    }
+
+    // start computing records - records for one buffer of timesteps are
+    //  all returned before any of the next buffer
+    pub fn build(&self) -> Result<DumpIter, Box<dyn Error>> {
+        let Layout {
+            axes, buffer_size, columns, correlations, data_files, encodings,
+            end_index, features, index_names, members,
+            offset: (y_offset, x_offset), qc, shapes, skipped, start_index,
+            times,
+            window: (latitudes_len, longitudes_len),
+        } = self.layout()?;
+
+        let skipped = Arc::new(Mutex::new(skipped));
+
+        // double buffer slabs so the next slab is read while the
+        //  current slab is aggregated - features are locked separately
+        //  so io threads read them concurrently
+        //  - buffers of each member are contiguous when computed per member
+        //  - buffers are initialized undefined for unread features
+        let buffer_count = encodings.len() * members;
+        let buffers: Arc<Vec<Vec<RwLock<Vec<f32>>>>> = Arc::new((0..2)
+            .map(|_| (0..buffer_count).map(|_| RwLock::new(vec![f32::NAN;
+                buffer_size * latitudes_len * longitudes_len])).collect())
+            .collect());
+
+        // assign features to io threads round-robin
+        let io_thread_count = std::cmp::max(std::cmp::min(
+            self.io_thread_count as usize, buffer_count), 1);
+        let mut assignments = vec![Vec::new(); io_thread_count];
+        let mut k = 0;
+        for member in 0..members {
+            for (j, file_features) in features.iter().enumerate() {
+                for feature in file_features.iter() {
+                    // features of skipped data files are not read
+                    if j < data_files.len() {
+                        assignments[k % io_thread_count]
+                            .push((k, j, feature.to_string(), member));
+                    }
+
+                    k += 1;
+                }
+            }
+
+            if let Some((j, name)) = &qc {
+                assignments[k % io_thread_count]
+                    .push((k, *j, name.to_string(), member));
+                k += 1;
+            }
+        }
+
+        let (axes, encodings) = (Arc::new(axes), Arc::new(encodings));
+
+        // initailize bounded record channel - aggregation blocks when
+        //  records are not consumed
+        let capacity = std::cmp::max(self.thread_count as usize, 1) * 256;
+        let (record_tx, record_rx): (Sender<Record>, Receiver<Record>) =
+            crossbeam_channel::bounded(capacity);
+
+        let completion = Arc::new((Mutex::new(Completion {
+            cancelled: false,
+            count: 0,
+        }), Condvar::new()));
+
+        let pool = rayon::ThreadPoolBuilder::new()
+            .num_threads(self.thread_count as usize).build()?;
+
+        let gis_joins: Vec<String> =
+            shapes.iter().map(|x| x.0.to_string()).collect();
+        let (shapes, times) = (Arc::new(shapes), Arc::new(times));
+
+        // start coordinator thread reading buffers of time values
+        let handle = {
+            let (completion, correlations, data_files, skip_errors, skipped,
+                    sorted, stats) = (completion.clone(),
+                Arc::new(correlations), data_files.clone(), self.skip_errors,
+                skipped.clone(), self.sorted, self.stats.clone());
+            let (accumulation, qc, qc_accept) = (self.accumulation,
+                qc.is_some(), self.qc_accept.clone());
+
+            std::thread::spawn(move || -> Result<(), String> {
+                // start io threads reading assigned features of each slab
+                let (result_tx, result_rx) = crossbeam_channel::unbounded();
+                let mut slab_txs = Vec::new();
+                for features in assignments.into_iter() {
+                    let (slab_tx, slab_rx) = crossbeam_channel::unbounded();
+                    let slab_reader = SlabReader {
+                        axes: axes.clone(),
+                        buffers: buffers.clone(),
+                        encodings: encodings.clone(),
+                        features: features,
+                        offset: [y_offset, x_offset],
+                        shape: [latitudes_len, longitudes_len],
+                        skip_errors: skip_errors,
+                        skipped: skipped.clone(),
+                    };
+
+                    let (data_files, result_tx) =
+                        (data_files.clone(), result_tx.clone());
+                    std::thread::spawn(move || slab_reader
+                        .run(&data_files, slab_rx, result_tx));
+
+                    slab_txs.push(slab_tx);
+                }
+
+                drop(result_tx);
+
+                // slabs end on buffer_size boundaries - the first slab
+                //  is shortened when start_index is unaligned
+                let slabs = std::iter::once(start_index)
+                    .chain(((start_index / buffer_size + 1) * buffer_size
+                        ..end_index).step_by(buffer_size))
+                    .filter(|x| *x < end_index);
+
+                let mut count = 0;
+                for (n, i) in slabs.enumerate() {
+                    // the previous slab may still be aggregating while this
+                    //  slab is read into the alternate buffers
+                    let slot = n % 2;
+
+                    let time_slice_len = std::cmp::min(
+                        (i / buffer_size + 1) * buffer_size, end_index) - i;
+
+                    // read data into buffers
+                    for slab_tx in slab_txs.iter() {
+                        slab_tx.send((slot, i, time_slice_len))
+                            .map_err(|_| "io thread stopped".to_string())?;
+                    }
+
+                    for _ in 0..slab_txs.len() {
+                        result_rx.recv()
+                            .map_err(|_| "io thread stopped".to_string())??;
+                    }
+
+                    // treat cells with unaccepted quality flags as missing
+                    if qc {
+                        let slab_size =
+                            time_slice_len * latitudes_len * longitudes_len;
+                        mask_quality(&buffers[slot], members, &qc_accept,
+                            slab_size);
+                    }
+
+                    // wait for the previous slab so records are returned
+                    //  in slab order
+                    if !wait_for(&completion, count) {
+                        return Ok(());
+                    }
+
+                    // aggregate (time, member, shape) tuples of the slab
+                    //  in parallel
+                    count += time_slice_len * members * shapes.len();
+
+                    let (buffers, correlations, record_tx, shapes, stats,
+                            times) = (buffers.clone(), correlations.clone(),
+                        record_tx.clone(), shapes.clone(), stats.clone(),
+                        times.clone());
+                    pool.spawn(move || {
+                        let guards: Vec<_> = buffers[slot].iter()
+                            .map(|x| x.read().unwrap()).collect();
+                        let buffers: Vec<&[f32]> =
+                            guards.iter().map(|x| &x[..]).collect();
+                        let collect = stats.iter().any(|x| x.requires_values());
+
+                        let init = || Scratch {
+                            accumulator: Accumulator::new(collect,
+                                accumulation),
+                            others: Vec::new(),
+                            values: Vec::new(),
+                            weights: Vec::new(),
+                        };
+
+                        // quality flags follow the features of each member
+                        let feature_len = buffers.len() / members;
+                        let value_len = feature_len - qc as usize;
+                        let record = |scratch: &mut Scratch, index: usize| {
+                            let (t, m, j) = (index / (members * shapes.len()),
+                                index / shapes.len() % members,
+                                index % shapes.len());
+
+                            let (values, counts) = aggregate(
+                                &buffers[m * feature_len..
+                                    m * feature_len + value_len],
+                                &shapes[j].1, scratch, &stats, &correlations,
+                                t * latitudes_len * longitudes_len,
+                                longitudes_len);
+
+                            Record {
+                                counts: counts,
+                                gis_join: shapes[j].0.to_string(),
+                                member: m,
+                                shape_index: j,
+                                time_index: i + t,
+                                timestamp: times[i + t],
+                                values: values,
+                            }
+                        };
+
+                        // receiver is dropped when iteration stops
+                        let indices =
+                            0..time_slice_len * members * shapes.len();
+                        match sorted {
+                            true => {
+                                let records: Vec<Record> = indices
+                                    .into_par_iter()
+                                    .map_init(init, record).collect();
+                                for record in records {
+                                    if record_tx.send(record).is_err() {
+                                        break;
+                                    }
+                                }
+                            },
+                            false => indices.into_par_iter()
+                                .for_each_init(init, |scratch, index| {
+                                    let _ = record_tx
+                                        .send(record(scratch, index));
+                                }),
+                        }
+                    });
+                }
+
+                wait_for(&completion, count);
+                Ok(())
+            })
+        };
+
+        Ok(DumpIter {
+            buffer_size: buffer_size,
+            columns: columns,
+            completion: completion,
+            end_index: end_index,
+            gis_joins: gis_joins,
+            handle: Some(handle),
+            index_names: index_names,
+            members: members,
+            record_rx: record_rx,
+            skipped: skipped,
+            start_index: start_index,
+        })
+    }
+}
+
+// summary of a run computed without reading data
+pub struct Plan {
+    pub buffer_size: usize,
+    pub columns: Vec<String>,
+    pub data_files: Vec<PathBuf>,
+    pub features: Vec<String>,
+    pub members: usize, // ensemble members computed per member
+    pub memory: usize, // bytes of slab buffers
+    pub shape_count: usize,
+    pub skipped: Vec<Skipped>, // data files which failed to open
+    pub timestamps: Vec<i64>, // timestamps within the time range
+    pub window: (usize, usize), // (latitudes, longitudes) read per step
+}
+
+// shapes and data file metadata of a run
+struct Layout {
+    axes: Vec<Vec<Axis>>, // slab dimensions of each feature
+    buffer_size: usize,
+    columns: Vec<String>,
+    correlations: Vec<(CorrelationMethod, usize, usize)>,
+    data_files: Vec<PathBuf>,
+    encodings: Vec<Encoding>,
+    end_index: usize,
+    features: Vec<Vec<String>>,
+    index_names: Vec<String>, // index file name of each shape
+    members: usize,
+    offset: (usize, usize), // (y, x) of the bounding box origin
+    qc: Option<(usize, String)>, // (data file, variable) of quality flags
+    shapes: Vec<(String, Vec<(usize, usize, f32)>)>,
+    skipped: Vec<Skipped>,
+    start_index: usize,
+    times: Vec<i64>,
+    window: (usize, usize), // (latitudes, longitudes) of the bounding box
+}
+
+// records streamed from worker threads
+pub struct DumpIter {
+    buffer_size: usize,
+    columns: Vec<String>,
+    completion: Arc<(Mutex<Completion>, Condvar)>,
+    end_index: usize,
+    gis_joins: Vec<String>,
+    handle: Option<JoinHandle<Result<(), String>>>,
+    index_names: Vec<String>,
+    members: usize,
+    record_rx: Receiver<Record>,
+    skipped: Arc<Mutex<Vec<Skipped>>>,
+    start_index: usize,
+}
+
+// a data file (or variable slab) skipped after failing to open or read
+#[derive(Clone, Debug)]
+pub struct Skipped {
+    pub data_file: PathBuf,
+    pub end_index: usize, // exclusive time index
+    pub error: String,
+    pub start_index: usize,
+    pub variable: Option<String>, // none when the file failed to open
+}
+
+impl DumpIter {
+    // chunk aligned slab length - slabs start at multiples of this
+    //  (or start_index) and records are returned a slab at a time
+    pub fn buffer_size(&self) -> usize {
+        self.buffer_size
+    }
+
+    // "{stat}_{feature}" names of record values
+    pub fn columns(&self) -> &[String] {
+        &self.columns
+    }
+
+    // number of ensemble members of records - one unless computing
+    //  statistics per member
+    pub fn members(&self) -> usize {
+        self.members
+    }
+
+    // shape ids indexed by record shape_index
+    pub fn gis_joins(&self) -> &[String] {
+        &self.gis_joins
+    }
+
+    // index file names of shapes indexed by record shape_index
+    pub fn index_names(&self) -> &[String] {
+        &self.index_names
+    }
+
+    // processed [start, end) time indices
+    pub fn time_range(&self) -> (usize, usize) {
+        (self.start_index, self.end_index)
+    }
+
+    // data files and slabs skipped so far when skipping errors
+    pub fn skipped(&self) -> Vec<Skipped> {
+        self.skipped.lock().unwrap().clone()
+    }
+}
+
+impl Iterator for DumpIter {
+    type Item = Result<Record, Box<dyn Error>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Ok(record) = self.record_rx.recv() {
+            let (lock, condvar) = &*self.completion;
+            lock.lock().unwrap().count += 1;
+            condvar.notify_all();
+
+            return Some(Ok(record));
+        }
+
+        // surface coordinator failures
+        match self.handle.take().map(|x| x.join()) {
+            Some(Ok(Err(e))) => Some(Err(e.into())),
+            Some(Err(e)) => Some(Err(
+                format!("failed to join handle: {:?}", e).into())),
+            _ => None,
+        }
+    }
+}
+
+impl Drop for DumpIter {
+    fn drop(&mut self) {
+        let (lock, condvar) = &*self.completion;
+        lock.lock().unwrap().cancelled = true;
+        condvar.notify_all();
+    }
+}
+
+// per-thread aggregation state reused across shapes
+struct Scratch {
+    accumulator: Accumulator,
+    others: Vec<f32>,
+    values: Vec<f32>,
+    weights: Vec<f32>,
+}
+
+// compute statistics of each feature (followed by correlations of
+//  feature pairs) over shape cells <x, y, weight> within the time slice
+//  of buffers starting at offset - returns the values and the number of
+//  valid cells of each feature
+fn aggregate(buffers: &[&[f32]], indices: &[(usize, usize, f32)],
+        scratch: &mut Scratch, stats: &[Stat],
+        correlations: &[(CorrelationMethod, usize, usize)], offset: usize,
+        longitudes_len: usize) -> (Vec<f32>, Vec<usize>) {
+    let (mut values, mut counts) = (Vec::new(), Vec::new());
+    for buffer in buffers.iter() {
+        // gather defined cell values into contiguous vectors
+        scratch.values.clear();
+        scratch.weights.clear();
+        for (x, y, weight) in indices.iter() {
+            let value = buffer[offset + y * longitudes_len + x];
+            if !value.is_finite() {
+                continue;
+            }
+
+            scratch.values.push(value);
+            scratch.weights.push(*weight);
+        }
+
+        counts.push(scratch.values.len());
+
+        scratch.accumulator.reset();
+        scratch.accumulator.update_slice(&scratch.values, &scratch.weights);
+
+        // compute requested statistics
+        for stat in stats.iter() {
+            values.push(scratch.accumulator.compute(stat));
+        }
+    }
+
+    // correlate cells where both features are defined
+    for (method, a, b) in correlations.iter() {
+        scratch.values.clear();
+        scratch.others.clear();
+        scratch.weights.clear();
+        for (x, y, weight) in indices.iter() {
+            let cell = offset + y * longitudes_len + x;
+            let (a_value, b_value) = (buffers[*a][cell], buffers[*b][cell]);
+            if !a_value.is_finite() || !b_value.is_finite() {
+                continue;
+            }
+
+            scratch.values.push(a_value);
+            scratch.others.push(b_value);
+            scratch.weights.push(*weight);
+        }
+
+        values.push(crate::stat::correlate(*method, &scratch.values,
+            &scratch.others, &scratch.weights));
+    }
+
+    (values, counts)
+}
+
+// replace values of cells whose quality flag (the last buffer of each
+//  member) is not accepted with NaN
+fn mask_quality(buffers: &[RwLock<Vec<f32>>], members: usize,
+        accept: &[f32], slab_size: usize) {
+    let feature_len = buffers.len() / members;
+    for m in 0..members {
+        let flags = buffers[(m + 1) * feature_len - 1].read().unwrap();
+        for buffer in buffers[m * feature_len..(m + 1) * feature_len - 1]
+                .iter() {
+            let mut buffer = buffer.write().unwrap();
+            for (value, flag) in buffer[..slab_size].iter_mut()
+                    .zip(flags[..slab_size].iter()) {
+                if !accept.contains(flag) {
+                    *value = f32::NAN;
+                }
+            }
+        }
+    }
+}
+
+// count of records consumed by the iterator
+struct Completion {
+    cancelled: bool,
+    count: usize,
+}
+
+// wait until count records are consumed - false if iteration is cancelled
+fn wait_for(completion: &(Mutex<Completion>, Condvar), count: usize) -> bool {
+    let (lock, condvar) = completion;
+    let mut completion = lock.lock().unwrap();
+    while completion.count < count && !completion.cancelled {
+        completion = condvar.wait(completion).unwrap();
+    }
+
+    !completion.cancelled
+}
+
+// round buffer_size to a multiple of the time chunk lengths
+//  - reading less than a chunk decompresses it once per slab so the
+//  buffer is grown to at least the largest chunk
+fn align_buffer_size(buffer_size: usize, time_chunks: &[usize]) -> usize {
+    let chunk_lens: Vec<usize> =
+        time_chunks.iter().cloned().filter(|x| *x > 0).collect();
+    if chunk_lens.is_empty() {
+        return std::cmp::max(buffer_size, 1);
+    }
+
+    // least common multiple of all chunk lengths
+    let lcm = chunk_lens.iter().fold(1usize, |lcm, x| {
+        let (mut a, mut b) = (lcm, *x);
+        while b != 0 {
+            let t = b;
+            b = a % b;
+            a = t;
+        }
+
+        lcm.saturating_mul(*x / a)
+    });
+
+    // fall back to the largest chunk for incompatible chunk lengths
+    let alignment = match lcm <= buffer_size {
+        true => lcm,
+        false => *chunk_lens.iter().max().unwrap(),
+    };
+
+    std::cmp::max(buffer_size / alignment, 1) * alignment
+}
+
+// reads assigned features of requested slabs into slot buffers
+struct SlabReader {
+    axes: Arc<Vec<Vec<Axis>>>,
+    buffers: Arc<Vec<Vec<RwLock<Vec<f32>>>>>,
+    encodings: Arc<Vec<Encoding>>,
+    // (buffer, data file, variable, member)
+    features: Vec<(usize, usize, String, usize)>,
+    offset: [usize; 2],
+    shape: [usize; 2],
+    skip_errors: bool,
+    skipped: Arc<Mutex<Vec<Skipped>>>,
+}
+
+impl SlabReader {
+    // serve (slot, start, len) requests until the coordinator stops
+    fn run(&self, data_files: &[PathBuf],
+            slab_rx: Receiver<(usize, usize, usize)>,
+            result_tx: Sender<Result<(), String>>) {
+        // open assigned data files once - remote datasets are only
+        //  requested for the slabs read
+        let readers: Vec<Option<Result<Dataset, String>>> = data_files
+            .iter().enumerate().map(|(j, data_file)| {
+                match self.features.iter().any(|x| x.1 == j) {
+                    true => Some(Dataset::open(data_file)
+                        .map_err(|e| e.to_string())),
+                    false => None,
+                }
+            }).collect();
+
+        for (slot, start, len) in slab_rx.iter() {
+            let result = self.read(data_files, &readers, slot, start, len)
+                .map_err(|e| e.to_string());
+
+            if result_tx.send(result).is_err() {
+                break;
+            }
+        }
+    }
+
+    fn read(&self, data_files: &[PathBuf],
+            readers: &[Option<Result<Dataset, String>>], slot: usize,
+            start: usize, len: usize) -> Result<(), Box<dyn Error>> {
+        let cell_len = self.shape[0] * self.shape[1];
+        let buffer_size = len * cell_len;
+
+        for (k, j, feature, member) in self.features.iter() {
+            // buffers of each member share feature metadata
+            let f = *k % self.encodings.len();
+            let axes = &self.axes[f];
+
+            let (slab_start, slab_len): (Vec<usize>, Vec<usize>) = axes
+                .iter().map(|axis| match axis {
+                    Axis::Member => (*member, 1),
+                    Axis::Range(i, n) => (*i, *n),
+                    Axis::Time => (start, len),
+                    Axis::X => (self.offset[1], self.shape[1]),
+                    Axis::Y => (self.offset[0], self.shape[0]),
+                }).unzip();
+
+            let level_len: usize = axes.iter().map(|x| match x {
+                Axis::Range(_, n) => *n,
+                _ => 1,
+            }).product();
+
+            let mut buffer = self.buffers[slot][*k].write().unwrap();
+            let result = match level_len {
+                1 => read_variable(&readers[*j], feature, &self.encodings[f],
+                    &mut buffer[..buffer_size], &slab_start, &slab_len),
+                _ => {
+                    let mut values = vec![0f32; buffer_size * level_len];
+                    read_variable(&readers[*j], feature, &self.encodings[f],
+                            &mut values, &slab_start, &slab_len)
+                        .map(|_| mean_levels(&values,
+                            &mut buffer[..buffer_size], level_len, cell_len))
+                },
+            };
+
+            match result {
+                Ok(()) => {},
+                Err(e) if self.skip_errors => {
+                    // statistics of the skipped slab are undefined
+                    eprintln!("skipping '{}' variable '{}' time indices \
+                        [{}, {}): {}", data_files[*j].display(), feature,
+                        start, start + len, e);
+                    for value in buffer[..buffer_size].iter_mut() {
+                        *value = f32::NAN;
+                    }
+
+                    self.skipped.lock().unwrap().push(Skipped {
+                        data_file: data_files[*j].clone(),
+                        end_index: start + len,
+                        error: e.to_string(),
+                        start_index: start,
+                        variable: Some(feature.to_string()),
+                    });
+                },
+                Err(e) => return Err(e),
+            }
+        }
+
+        Ok(())
+    }
+}
+
+// read a decoded hyperslab of the variable from an opened data file
+fn read_variable(reader: &Option<Result<Dataset, String>>, feature: &str,
+        encoding: &Encoding, buffer: &mut [f32], start: &[usize],
+        count: &[usize]) -> Result<(), Box<dyn Error>> {
+    match reader {
+        Some(Ok(reader)) =>
+            read_slab(reader, feature, encoding, buffer, start, count),
+        Some(Err(e)) => Err(e.to_string().into()),
+        None => Err("data file not opened".into()),
+    }
+}
+
+// read a hyperslab of the variable into the buffer and decode values
+//  - integer variables are read as f64 so masking and categories are
+//  exact
+pub fn read_slab(reader: &Dataset, feature: &str, encoding: &Encoding,
+        buffer: &mut [f32], start: &[usize], count: &[usize])
+        -> Result<(), Box<dyn Error>> {
+    let variable = reader.variable(feature)
+        .ok_or_else(|| format!("variable '{}' not found", feature))?;
+
+    if encoding.integer {
+        let values = variable.values_at(start, count)?;
+        let values: Vec<f64> = values.iter().cloned().collect();
+        return encoding.decode_values(&values, buffer)
+            .map_err(|e| format!("variable '{}': {}", feature, e).into());
+    }
+
+    variable.values_to(buffer, start, count)?;
+    encoding.decode(buffer);
+    Ok(())
+}
+
+// average (time, level, cell) values over levels ignoring NaN
+fn mean_levels(values: &[f32], buffer: &mut [f32], level_len: usize,
+        cell_len: usize) {
+    for (i, value) in buffer.iter_mut().enumerate() {
+        let (t, c) = (i / cell_len, i % cell_len);
+        let (mut sum, mut count) = (0f32, 0);
+        for l in 0..level_len {
+            let x = values[(t * level_len + l) * cell_len + c];
+            if x.is_finite() {
+                sum += x;
+                count += 1;
+            }
+        }
+
+        *value = match count {
+            0 => f32::NAN,
+            _ => sum / count as f32,
+        };
+    }
+}
+
+// selection of levels of variables with dimensions between time and the
+//  grid - by index, coordinate value, or averaged over all levels
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Level {
+    Index(usize),
+    Mean,
+    Value(f64),
+}
+
+impl FromStr for Level {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (kind, value) = match s.find(':') {
+            Some(i) => (&s[..i], &s[i + 1..]),
+            None => (s, ""),
+        };
+
+        match (kind, value) {
+            ("index", x) => x.parse::<usize>().map(Level::Index)
+                .map_err(|_| format!("invalid level index '{}'", x)),
+            ("mean", "") => Ok(Level::Mean),
+            ("value", x) => x.parse::<f64>().map(Level::Value)
+                .map_err(|_| format!("invalid level value '{}'", x)),
+            _ => Err(format!("unsupported level '{}'", s)),
+        }
+    }
+}
+
+// selection of ensemble members - a single member by index or
+//  statistics of each member
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Member {
+    Each,
+    Index(usize),
+}
+
+impl FromStr for Member {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "each" => Ok(Member::Each),
+            x => x.parse::<usize>().map(Member::Index)
+                .map_err(|_| format!("unsupported member '{}'", x)),
+        }
+    }
+}
+
+// names of ensemble member dimensions
+const MEMBER_DIMENSIONS: [&str; 6] =
+    ["ens", "ensemble", "member", "member_id", "number", "realization"];
+
+// dimension of a variable slab
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Axis {
+    Member, // the member of the buffer read
+    Range(usize, usize), // (start, count) of a level or selected member
+    Time,
+    X,
+    Y,
+}
+
+// compute slab axes of variable dimensions - the last two dimensions are
+//  the grid, member dimensions are selected (or read per member), the
+//  dimension of the time coordinate variable is time, and remaining
+//  dimensions are levels
+//  - returns the axes and the member count when computed per member
+fn slab_axes(reader: &Dataset, variable: &Variable, level: Option<Level>,
+        member: Option<Member>) -> Result<(Vec<Axis>, usize), Box<dyn Error>> {
+    let dimensions = variable.dimensions();
+    if dimensions.len() < 3 {
+        return Ok((vec![Axis::Time, Axis::Y, Axis::X], 1));
+    }
+
+    // the time coordinate variable is indexed by the time dimension
+    let time_dimension = match reader.variable("time")
+            .map(|x| x.dimensions()) {
+        Some(time_dimensions) if time_dimensions.len() == 1 =>
+            time_dimensions[0].name(),
+        Some(_) => return Err("time variable must have one dimension".into()),
+        None => return Err("variable time not found".into()),
+    };
+
+    let grid_position = dimensions.len() - 2;
+    let (mut axes, mut member_len) = (Vec::new(), 1);
+    for (i, dimension) in dimensions.iter().enumerate() {
+        let name = dimension.name();
+        let axis = if i == grid_position {
+            Axis::Y
+        } else if i > grid_position {
+            Axis::X
+        } else if MEMBER_DIMENSIONS.contains(&name.as_str()) {
+            match (member, dimension.len()) {
+                (_, 1) => Axis::Range(0, 1),
+                (Some(Member::Each), len) => {
+                    member_len = len;
+                    Axis::Member
+                },
+                (Some(Member::Index(m)), len) if m < len => Axis::Range(m, 1),
+                (Some(Member::Index(m)), len) => return Err(format!(
+                    "member {} outside of '{}' dimension of length {}",
+                    m, name, len).into()),
+                (None, _) => return Err(format!("variable '{}' has \
+                    ensemble dimension '{}' - select a member",
+                    variable.name(), name).into()),
+            }
+        } else if name == time_dimension {
+            Axis::Time
+        } else {
+            match (level, dimension.len()) {
+                (_, 1) => Axis::Range(0, 1),
+                (Some(Level::Index(l)), len) if l < len => Axis::Range(l, 1),
+                (Some(Level::Index(l)), len) => return Err(format!(
+                    "level index {} outside of '{}' dimension of length {}",
+                    l, name, len).into()),
+                (Some(Level::Mean), len) => Axis::Range(0, len),
+                (Some(Level::Value(value)), _) => {
+                    let values = crate::get_netcdf_values(reader, &name)?;
+                    let tolerance = 1e-6 * value.abs().max(1.0);
+                    match values.iter()
+                            .position(|x| (x - value).abs() <= tolerance) {
+                        Some(l) => Axis::Range(l, 1),
+                        None => return Err(format!(
+                            "level {} not found in '{}' coordinates",
+                            value, name).into()),
+                    }
+                },
+                (None, _) => return Err(format!("variable '{}' has level \
+                    dimension '{}' - select a level",
+                    variable.name(), name).into()),
+            }
+        };
+
+        axes.push(axis);
+    }
+
+    let time_position = match axes.iter().position(|x| *x == Axis::Time) {
+        Some(time_position) => time_position,
+        None => return Err(format!("variable '{}' has no time dimension \
+            '{}'", variable.name(), time_dimension).into()),
+    };
+
+    // averaged levels are read as (time, level, cell) values
+    if axes.iter().take(time_position).any(|x| match x {
+            Axis::Range(_, n) => *n > 1,
+            _ => false,
+        }) {
+        return Err(format!("variable '{}' has levels before its time \
+            dimension - select a level", variable.name()).into());
+    }
+
+    Ok((axes, member_len))
+}
+
+// attributes used to decode raw variable values - held as f64 so
+//  integer values compare exactly
+pub struct Encoding {
+    add_offset: f64,
+    fill_value: Option<f64>,
+    integer: bool, // integer storage type
+    scale_factor: f64,
+    unsigned_bits: Option<u32>, // bits of signed integers read unsigned
+    valid_max: f64,
+    valid_min: f64,
+}
+
+impl Encoding {
+    pub fn read(variable: &Variable) -> Result<Encoding, Box<dyn Error>> {
+        let storage_type = variable.storage_type()?;
+        if !storage_type.is_numeric() {
+            return Err(format!("variable '{}' of type {:?} is not numeric",
+                variable.name(), storage_type).into());
+        }
+
+        // signed integers flagged '_Unsigned' store unsigned values
+        let unsigned_bits = match variable.attribute("_Unsigned")? {
+            Some(AttrValue::Str(x)) if x == "true" =>
+                storage_type.signed_bits(),
+            _ => None,
+        };
+
+        // parse fill value - falling back to missing_value and otherwise
+        //  the default fill value of the storage type
+        let attribute = |name: &str| crate::cf::attribute_f64(variable, name);
+        let fill_value = match attribute("_FillValue")? {
+            Some(value) => Some(value),
+            None => match attribute("missing_value")? {
+                Some(value) => Some(value),
+                None => storage_type.default_fill(),
+            },
+        };
+
+        // parse packing attributes
+        let scale_factor = attribute("scale_factor")?.unwrap_or(1.0);
+        let add_offset = attribute("add_offset")?.unwrap_or(0.0);
+
+        // parse valid range of packed values - valid_min and valid_max
+        //  override the bounds of valid_range
+        let (mut valid_min, mut valid_max) = match variable
+                .attribute("valid_range")? {
+            Some(AttrValue::Array(x)) if x.len() == 2 => (x[0], x[1]),
+            Some(x) => return Err(format!(
+                "unsupported valid_range '{:?}'", x).into()),
+            None => (f64::NEG_INFINITY, f64::INFINITY),
+        };
+
+        if let Some(value) = attribute("valid_min")? {
+            valid_min = value;
+        }
+
+        if let Some(value) = attribute("valid_max")? {
+            valid_max = value;
+        }
+
+        // fill values and valid ranges are compared to unsigned values
+        let unsigned = |x: f64| to_unsigned(x, unsigned_bits);
+        Ok(Encoding {
+            add_offset: add_offset,
+            fill_value: fill_value.map(unsigned),
+            integer: storage_type.is_integer(),
+            scale_factor: scale_factor,
+            unsigned_bits: unsigned_bits,
+            valid_max: unsigned(valid_max),
+            valid_min: unsigned(valid_min),
+        })
+    }
+
+    // encoding of features which are never read (e.g. variables of
+    //  skipped data files)
+    fn undefined() -> Encoding {
+        Encoding {
+            add_offset: 0.0,
+            fill_value: None,
+            integer: false,
+            scale_factor: 1.0,
+            unsigned_bits: None,
+            valid_max: f64::INFINITY,
+            valid_min: f64::NEG_INFINITY,
+        }
+    }
+
+    // apply a linear conversion after unpacking values
+    pub fn convert(&mut self, scale: f64, offset: f64) {
+        self.scale_factor *= scale;
+        self.add_offset = self.add_offset * scale + offset;
+    }
+
+    // unpack values in place - fill values and values outside of the
+    //  valid range are replaced with NaN
+    pub fn decode(&self, buffer: &mut [f32]) {
+        for value in buffer.iter_mut() {
+            *value = match self.unpack(*value as f64) {
+                Some(value) => value as f32,
+                None => f32::NAN,
+            };
+        }
+    }
+
+    // unpack values read as f64 into the buffer - unpacked integers
+    //  (e.g. categories) must be exactly representable as f32
+    pub fn decode_values(&self, values: &[f64], buffer: &mut [f32])
+            -> Result<(), Box<dyn Error>> {
+        let exact = self.integer
+            && self.scale_factor == 1.0 && self.add_offset == 0.0;
+        for (x, value) in buffer.iter_mut().zip(values.iter()) {
+            *x = match self.unpack(*value) {
+                Some(value) if !exact || value.abs() <= F32_EXACT_MAX =>
+                    value as f32,
+                Some(value) => return Err(format!("integer value {} is not \
+                    exactly representable as f32", value).into()),
+                None => f32::NAN,
+            };
+        }
+
+        Ok(())
+    }
+
+    // unpack a raw value - None for fill values and values outside of
+    //  the valid range
+    fn unpack(&self, value: f64) -> Option<f64> {
+        let raw = to_unsigned(value, self.unsigned_bits);
+        match self.is_fill(raw) || raw < self.valid_min
+                || raw > self.valid_max || raw.is_nan() {
+            true => None,
+            false => Some(raw * self.scale_factor + self.add_offset),
+        }
+    }
+
+    // compare with a relative tolerance - fill values of float variables
+    //  are often large doubles (e.g. 1e20) which are not exactly
+    //  representable as f32, integer values compare exactly
+    fn is_fill(&self, value: f64) -> bool {
+        match (self.fill_value, self.integer) {
+            (Some(fill_value), true) => value == fill_value,
+            (Some(fill_value), false) => value == fill_value
+                || (value - fill_value).abs()
+                    <= 4.0 * f32::EPSILON as f64 * fill_value.abs(),
+            (None, _) => false,
+        }
+    }
+}
+
+// largest magnitude below which every integer is exact as f32 (2^24)
+const F32_EXACT_MAX: f64 = 16_777_216.0;
+
+// reinterpret a negative signed integer of bits as unsigned
+fn to_unsigned(value: f64, bits: Option<u32>) -> f64 {
+    match bits {
+        Some(bits) if value < 0.0 => value + (bits as f64).exp2(),
+        _ => value,
+    }
+}
@@ -0,0 +1,42 @@
+use thiserror::Error;
+
+// failure classes reported by the command line with distinct exit codes
+//  - errors outside these classes exit with a generic failure code
+#[derive(Debug, Error)]
+pub enum NcprojError {
+    #[error("invalid index '{path}': {message}")]
+    IndexFormat { message: String, path: String },
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+    #[error("failed to open dataset '{path}': {message}")]
+    Netcdf { message: String, path: String },
+    #[error(transparent)]
+    Shapefile(#[from] shapefile::Error),
+}
+
+// process exit code of an error's failure class
+pub fn exit_code(error: &(dyn std::error::Error + 'static)) -> i32 {
+    if let Some(error) = error.downcast_ref::<NcprojError>() {
+        return match error {
+            NcprojError::Io(_) => 2,
+            NcprojError::Netcdf { .. } => 3,
+            NcprojError::Shapefile(_) => 4,
+            NcprojError::IndexFormat { .. } => 5,
+        };
+    }
+
+    #[cfg(feature = "netcdf-c")]
+    {
+        if error.is::<netcdf::error::Error>() {
+            return 3;
+        }
+    }
+
+    if error.is::<std::io::Error>() {
+        2
+    } else if error.is::<shapefile::Error>() {
+        4
+    } else {
+        1
+    }
+}
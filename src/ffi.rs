@@ -0,0 +1,223 @@
+// c api - see include/ncproj.h
+//  - pointer arguments must be null or valid for the call
+//  - functions return negative values on failure with the message
+//  available from ncproj_last_error on the calling thread
+//  - panics are caught at the boundary and reported as failures
+use crate::dumper::{DumpIter, Dumper};
+use crate::format::{Format, IndexWriter};
+use crate::indexer::Indexer;
+
+use std::cell::RefCell;
+use std::error::Error;
+use std::ffi::{CStr, CString};
+use std::os::raw::{c_char, c_int};
+use std::panic::AssertUnwindSafe;
+use std::path::PathBuf;
+use std::ptr;
+
+thread_local! {
+    static LAST_ERROR: RefCell<Option<CString>> = RefCell::new(None);
+}
+
+// dump iterator and storage backing pointers handed to the caller
+pub struct NcprojDumpIter {
+    columns: Vec<CString>,
+    gis_join: CString,
+    iter: DumpIter,
+    values: Vec<f32>,
+}
+
+// compute a text index for the shape file over the grid file
+//  - a zero thread count uses the number of logical cpus
+#[no_mangle]
+pub unsafe extern "C" fn ncproj_index_build(shape_file: *const c_char,
+        grid_file: *const c_char, output_file: *const c_char,
+        thread_count: u8) -> c_int {
+    let result = guard(|| -> Result<(), Box<dyn Error>> {
+        let entries = Indexer::new(to_path(shape_file)?,
+                to_path(grid_file)?)
+            .thread_count(thread_count_or_default(thread_count))
+            .build()?;
+
+        let output = Some(to_path(output_file)?);
+        let mut writer = IndexWriter::new(
+            crate::open_output(&output, false)?, Format::Text)?;
+        for entry in entries {
+            let entry = entry?;
+            writer.write(entry.x, entry.y, &entry.id, entry.weight)?;
+        }
+
+        writer.flush()?;
+        Ok(())
+    });
+
+    match result {
+        Ok(_) => 0,
+        Err(e) => fail(e),
+    }
+}
+
+// start dumping default statistics over all variables in the data files
+//  - returns null on failure and a zero thread count uses the
+//  number of logical cpus
+#[no_mangle]
+pub unsafe extern "C" fn ncproj_dump_iter_new(index_file: *const c_char,
+        data_files: *const *const c_char, data_files_len: usize,
+        thread_count: u8) -> *mut NcprojDumpIter {
+    let result = guard(|| -> Result<NcprojDumpIter, Box<dyn Error>> {
+        if data_files.is_null() {
+            return Err("data files must not be null".into());
+        }
+
+        let mut paths = Vec::new();
+        for i in 0..data_files_len {
+            paths.push(to_path(*data_files.add(i))?);
+        }
+
+        let iter = Dumper::new(to_path(index_file)?, paths)
+            .thread_count(thread_count_or_default(thread_count))
+            .build()?;
+
+        let mut columns = Vec::new();
+        for column in iter.columns() {
+            columns.push(CString::new(column.as_str())?);
+        }
+
+        Ok(NcprojDumpIter {
+            columns: columns,
+            gis_join: CString::default(),
+            iter: iter,
+            values: Vec::new(),
+        })
+    });
+
+    match result {
+        Ok(iter) => Box::into_raw(Box::new(iter)),
+        Err(e) => {
+            fail(e);
+            ptr::null_mut()
+        },
+    }
+}
+
+// number of values in each record
+#[no_mangle]
+pub unsafe extern "C" fn ncproj_dump_iter_columns_len(
+        iter: *const NcprojDumpIter) -> usize {
+    match iter.as_ref() {
+        Some(iter) => iter.columns.len(),
+        None => 0,
+    }
+}
+
+// name of the value at index - valid until the iterator is freed
+#[no_mangle]
+pub unsafe extern "C" fn ncproj_dump_iter_column(
+        iter: *const NcprojDumpIter, index: usize) -> *const c_char {
+    match iter.as_ref().and_then(|x| x.columns.get(index)) {
+        Some(column) => column.as_ptr(),
+        None => ptr::null(),
+    }
+}
+
+// advance to the next record - returns 1 when a record is available,
+//  0 when iteration is complete, and -1 on failure
+//  - gis_join and values remain valid until the next call
+#[no_mangle]
+pub unsafe extern "C" fn ncproj_dump_iter_next(iter: *mut NcprojDumpIter,
+        gis_join: *mut *const c_char, timestamp: *mut i64,
+        values: *mut *const f32) -> c_int {
+    let result = guard(|| -> Result<c_int, Box<dyn Error>> {
+        let iter = match iter.as_mut() {
+            Some(iter) => iter,
+            None => return Err("iterator must not be null".into()),
+        };
+
+        let record = match iter.iter.next() {
+            Some(record) => record?,
+            None => return Ok(0),
+        };
+
+        iter.gis_join = CString::new(record.gis_join)?;
+        iter.values = record.values;
+
+        if !gis_join.is_null() {
+            *gis_join = iter.gis_join.as_ptr();
+        }
+
+        if !timestamp.is_null() {
+            *timestamp = record.timestamp;
+        }
+
+        if !values.is_null() {
+            *values = iter.values.as_ptr();
+        }
+
+        Ok(1)
+    });
+
+    match result {
+        Ok(status) => status,
+        Err(e) => fail(e),
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn ncproj_dump_iter_free(iter: *mut NcprojDumpIter) {
+    if !iter.is_null() {
+        drop(Box::from_raw(iter));
+    }
+}
+
+// message of the last failure on this thread (null if none)
+#[no_mangle]
+pub extern "C" fn ncproj_last_error() -> *const c_char {
+    LAST_ERROR.with(|x| match x.borrow().as_ref() {
+        Some(message) => message.as_ptr(),
+        None => ptr::null(),
+    })
+}
+
+// run a call catching panics as errors - unwinding across the c
+//  boundary is undefined behavior
+fn guard<T, F>(f: F) -> Result<T, Box<dyn Error>>
+        where F: FnOnce() -> Result<T, Box<dyn Error>> {
+    match std::panic::catch_unwind(AssertUnwindSafe(f)) {
+        Ok(result) => result,
+        Err(payload) => {
+            let message = match (payload.downcast_ref::<&str>(),
+                    payload.downcast_ref::<String>()) {
+                (Some(message), _) => message.to_string(),
+                (_, Some(message)) => message.clone(),
+                _ => "unknown panic".to_string(),
+            };
+
+            Err(format!("panicked: {}", message).into())
+        },
+    }
+}
+
+// record an error message for ncproj_last_error
+fn fail(e: Box<dyn Error>) -> c_int {
+    let message = CString::new(e.to_string().replace('\0', ""))
+        .unwrap_or_default();
+    LAST_ERROR.with(|x| *x.borrow_mut() = Some(message));
+    -1
+}
+
+// convert a c string argument into a path
+fn to_path(s: *const c_char) -> Result<PathBuf, Box<dyn Error>> {
+    if s.is_null() {
+        return Err("path must not be null".into());
+    }
+
+    Ok(PathBuf::from(unsafe { CStr::from_ptr(s) }.to_str()?))
+}
+
+// zero selects the number of logical cpus
+fn thread_count_or_default(thread_count: u8) -> u8 {
+    match thread_count {
+        0 => crate::default_thread_count(),
+        x => x,
+    }
+}
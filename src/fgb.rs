@@ -0,0 +1,463 @@
+// reader for flatgeobuf polygon files - features intersecting an extent
+//  are located with the packed hilbert r-tree index when present
+use geo_types::{Coordinate, LineString, MultiPolygon, Polygon};
+
+use std::collections::HashMap;
+use std::convert::TryInto;
+use std::error::Error;
+use std::fs::File;
+use std::io::{BufReader, ErrorKind, Read, Seek, SeekFrom};
+use std::path::PathBuf;
+
+const MAGIC: &[u8; 3] = b"fgb";
+const NODE_ITEM_LEN: usize = 40; // min x, min y, max x, max y, offset
+
+pub struct Feature {
+    pub geometry: Option<MultiPolygon<f64>>,
+    pub properties: HashMap<String, String>,
+}
+
+pub struct Reader {
+    columns: Vec<(String, u8)>, // (name, column type)
+    crs: Option<String>,
+    features_count: u64,
+    features_offset: u64, // file offset of the first feature
+    geometry_type: u8,
+    index_node_size: u64,
+    reader: BufReader<File>,
+}
+
+impl Reader {
+    pub fn open(path: &PathBuf) -> Result<Reader, Box<dyn Error>> {
+        let mut reader = BufReader::new(File::open(path)?);
+
+        // validate magic bytes - 'fgb', major version, 'fgb', patch version
+        let mut magic = [0u8; 8];
+        reader.read_exact(&mut magic)?;
+        if &magic[0..3] != MAGIC || &magic[4..7] != MAGIC {
+            return Err(format!("'{}' is not a flatgeobuf file",
+                path.display()).into());
+        }
+
+        let mut header_len = [0u8; 4];
+        reader.read_exact(&mut header_len)?;
+        let header_len = u32::from_le_bytes(header_len) as usize;
+
+        let mut buffer = vec![0u8; header_len];
+        reader.read_exact(&mut buffer)?;
+        let header = Table::root(&buffer)?;
+
+        let mut columns = Vec::new();
+        for column in header.tables(7)? {
+            columns.push((column.string(0)?.unwrap_or_default().to_string(),
+                column.u8(1)?.unwrap_or(0)));
+        }
+
+        // identify the crs by authority code or wkt
+        let crs = match header.table(10)? {
+            Some(crs) => match (crs.string(0)?, crs.u32(1)?, crs.string(4)?) {
+                (organization, Some(code), _) if code != 0 => Some(format!(
+                    "{}:{}", organization.unwrap_or("EPSG"), code)),
+                (_, _, Some(wkt)) => Some(wkt.to_string()),
+                _ => None,
+            },
+            None => None,
+        };
+
+        // the index precedes features - its node size defaults to 16
+        let features_count = header.u64(8)?.unwrap_or(0);
+        let index_node_size = header.u16(9)?.unwrap_or(16) as u64;
+        let index_len = match (features_count, index_node_size) {
+            (0, _) | (_, 0) => 0,
+            (_, 1) => return Err("invalid flatgeobuf index node size".into()),
+            _ => level_bounds(features_count, index_node_size)[0].1
+                * NODE_ITEM_LEN as u64,
+        };
+
+        Ok(Reader {
+            columns: columns,
+            crs: crs,
+            features_count: features_count,
+            features_offset: 12 + header_len as u64 + index_len,
+            geometry_type: header.u8(2)?.unwrap_or(0),
+            index_node_size: index_node_size,
+            reader: reader,
+        })
+    }
+
+    // crs of feature coordinates (e.g. 'EPSG:4326') if recorded
+    pub fn crs(&self) -> Option<String> {
+        self.crs.clone()
+    }
+
+    // read features - only those whose bounds intersect the (min x,
+    //  min y, max x, max y) extent are read when the file is indexed
+    pub fn features(&mut self, extent: Option<&[f64; 4]>)
+            -> Result<Vec<Feature>, Box<dyn Error>> {
+        let mut features = Vec::new();
+        match extent {
+            Some(extent) if self.index_node_size != 0
+                    && self.features_count != 0 => {
+                for offset in self.search(extent)? {
+                    self.reader.seek(SeekFrom::Start(
+                        self.features_offset + offset))?;
+                    if let Some(feature) = self.read_feature()? {
+                        features.push(feature);
+                    }
+                }
+            },
+            _ => {
+                self.reader.seek(SeekFrom::Start(self.features_offset))?;
+                while let Some(feature) = self.read_feature()? {
+                    features.push(feature);
+                }
+            },
+        }
+
+        Ok(features)
+    }
+
+    // read the next size prefixed feature - None at the end of the file
+    fn read_feature(&mut self) -> Result<Option<Feature>, Box<dyn Error>> {
+        let mut len = [0u8; 4];
+        match self.reader.read_exact(&mut len) {
+            Ok(()) => {},
+            Err(ref e) if e.kind() == ErrorKind::UnexpectedEof =>
+                return Ok(None),
+            Err(e) => return Err(e.into()),
+        }
+
+        let mut buffer = vec![0u8; u32::from_le_bytes(len) as usize];
+        self.reader.read_exact(&mut buffer)?;
+        let feature = Table::root(&buffer)?;
+
+        let geometry = match feature.table(0)? {
+            Some(geometry) =>
+                Some(read_geometry(&geometry, self.geometry_type)?),
+            None => None,
+        };
+
+        let properties = read_properties(feature.bytes(1)?, &self.columns)?;
+        Ok(Some(Feature {
+            geometry: geometry,
+            properties: properties,
+        }))
+    }
+
+    // compute feature offsets of leaf nodes intersecting the extent
+    fn search(&mut self, extent: &[f64; 4])
+            -> Result<Vec<u64>, Box<dyn Error>> {
+        let level_bounds =
+            level_bounds(self.features_count, self.index_node_size);
+
+        let index_offset = self.features_offset
+            - level_bounds[0].1 * NODE_ITEM_LEN as u64;
+        let mut index = vec![0u8; level_bounds[0].1 as usize * NODE_ITEM_LEN];
+        self.reader.seek(SeekFrom::Start(index_offset))?;
+        self.reader.read_exact(&mut index)?;
+
+        // traverse from the root - internal node offsets are the index of
+        //  their first child and leaf offsets locate features
+        let mut offsets = Vec::new();
+        let mut queue = vec![(0, level_bounds.len() - 1)];
+        while let Some((node_index, level)) = queue.pop() {
+            let end = std::cmp::min(node_index + self.index_node_size,
+                level_bounds[level].1);
+            for position in node_index..end {
+                let item = read_bytes(&index,
+                    position as usize * NODE_ITEM_LEN, NODE_ITEM_LEN)?;
+                let value = |k: usize| f64::from_le_bytes(
+                    item[k * 8..k * 8 + 8].try_into().unwrap());
+                if value(2) < extent[0] || value(3) < extent[1]
+                        || value(0) > extent[2] || value(1) > extent[3] {
+                    continue;
+                }
+
+                let offset = u64::from_le_bytes(item[32..40].try_into()?);
+                match level {
+                    0 => offsets.push(offset),
+                    _ => queue.push((offset, level - 1)),
+                }
+            }
+        }
+
+        offsets.sort_unstable();
+        Ok(offsets)
+    }
+}
+
+// compute [start, end) node ranges of each packed r-tree level - level
+//  zero holds the leaves which are stored last
+fn level_bounds(item_count: u64, node_size: u64) -> Vec<(u64, u64)> {
+    let (mut n, mut node_count) = (item_count, item_count);
+    let mut level_node_counts = vec![n];
+    loop {
+        n = (n + node_size - 1) / node_size;
+        node_count += n;
+        level_node_counts.push(n);
+        if n == 1 {
+            break;
+        }
+    }
+
+    let mut bounds = Vec::new();
+    let mut end = node_count;
+    for count in level_node_counts {
+        bounds.push((end - count, end));
+        end -= count;
+    }
+
+    bounds
+}
+
+// read a polygonal geometry - the header type applies unless unknown
+fn read_geometry(geometry: &Table, geometry_type: u8)
+        -> Result<MultiPolygon<f64>, Box<dyn Error>> {
+    let geometry_type = match geometry_type {
+        0 => geometry.u8(6)?.unwrap_or(0),
+        x => x,
+    };
+
+    match geometry_type {
+        3 => Ok(MultiPolygon(vec![read_polygon(geometry)?])),
+        6 => {
+            let parts = geometry.tables(7)?;
+            match parts.is_empty() {
+                true => Ok(MultiPolygon(vec![read_polygon(geometry)?])),
+                false => Ok(MultiPolygon(parts.iter().map(read_polygon)
+                    .collect::<Result<Vec<Polygon<f64>>, Box<dyn Error>>>()?)),
+            }
+        },
+        x => Err(format!("unsupported flatgeobuf geometry type '{}'", x)
+            .into()),
+    }
+}
+
+// read a polygon of interleaved xy coordinates split into rings at ends
+fn read_polygon(geometry: &Table) -> Result<Polygon<f64>, Box<dyn Error>> {
+    let xy = geometry.vector(1, 8)?.unwrap_or(&[]);
+    let coordinates: Vec<Coordinate<f64>> = xy.chunks_exact(16)
+        .map(|x| Coordinate {
+            x: f64::from_le_bytes(x[0..8].try_into().unwrap()),
+            y: f64::from_le_bytes(x[8..16].try_into().unwrap()),
+        }).collect();
+
+    let mut ends: Vec<usize> = geometry.vector(0, 4)?.unwrap_or(&[])
+        .chunks_exact(4)
+        .map(|x| u32::from_le_bytes(x.try_into().unwrap()) as usize)
+        .collect();
+    if ends.is_empty() {
+        ends.push(coordinates.len());
+    }
+
+    let mut rings = Vec::new();
+    let mut start = 0;
+    for end in ends {
+        if end < start || end > coordinates.len() {
+            return Err("invalid flatgeobuf ring ends".into());
+        }
+
+        rings.push(LineString(coordinates[start..end].to_vec()));
+        start = end;
+    }
+
+    if coordinates.is_empty() {
+        return Err("invalid flatgeobuf polygon with no coordinates".into());
+    }
+
+    let exterior = rings.remove(0);
+    Ok(Polygon::new(exterior, rings))
+}
+
+// read (column index, value) encoded properties as strings - binary
+//  values are skipped
+fn read_properties(buffer: &[u8], columns: &[(String, u8)])
+        -> Result<HashMap<String, String>, Box<dyn Error>> {
+    let mut properties = HashMap::new();
+    let mut position = 0;
+    while position < buffer.len() {
+        let index = u16::from_le_bytes(
+            read_bytes(buffer, position, 2)?.try_into()?) as usize;
+        let (name, column_type) = columns.get(index)
+            .ok_or_else(|| format!("invalid flatgeobuf column '{}'", index))?;
+        position += 2;
+
+        let len = match column_type {
+            0 | 1 | 2 => 1,
+            3 | 4 => 2,
+            5 | 6 | 9 => 4,
+            7 | 8 | 10 => 8,
+            11 | 12 | 13 | 14 => 4 + u32::from_le_bytes(
+                read_bytes(buffer, position, 4)?.try_into()?) as usize,
+            x => return Err(format!("unsupported flatgeobuf column type \
+                '{}'", x).into()),
+        };
+
+        let bytes = read_bytes(buffer, position, len)?;
+        let value = match column_type {
+            0 => Some((bytes[0] as i8).to_string()),
+            1 | 2 => Some(bytes[0].to_string()),
+            3 => Some(i16::from_le_bytes(bytes.try_into()?).to_string()),
+            4 => Some(u16::from_le_bytes(bytes.try_into()?).to_string()),
+            5 => Some(i32::from_le_bytes(bytes.try_into()?).to_string()),
+            6 => Some(u32::from_le_bytes(bytes.try_into()?).to_string()),
+            7 => Some(i64::from_le_bytes(bytes.try_into()?).to_string()),
+            8 => Some(u64::from_le_bytes(bytes.try_into()?).to_string()),
+            9 => Some(f32::from_le_bytes(bytes.try_into()?).to_string()),
+            10 => Some(f64::from_le_bytes(bytes.try_into()?).to_string()),
+            11 | 12 | 13 =>
+                Some(String::from_utf8_lossy(&bytes[4..]).to_string()),
+            _ => None,
+        };
+
+        if let Some(value) = value {
+            properties.insert(name.to_string(), value);
+        }
+
+        position += len;
+    }
+
+    Ok(properties)
+}
+
+fn read_bytes(buffer: &[u8], position: usize, len: usize)
+        -> Result<&[u8], Box<dyn Error>> {
+    match buffer.get(position..position + len) {
+        Some(bytes) => Ok(bytes),
+        None => Err("unexpected end of flatgeobuf buffer".into()),
+    }
+}
+
+// minimal flatbuffers table accessor
+struct Table<'a> {
+    buffer: &'a [u8],
+    position: usize,
+}
+
+impl<'a> Table<'a> {
+    fn root(buffer: &'a [u8]) -> Result<Table<'a>, Box<dyn Error>> {
+        let position =
+            u32::from_le_bytes(read_bytes(buffer, 0, 4)?.try_into()?);
+        Ok(Table { buffer: buffer, position: position as usize })
+    }
+
+    // buffer position of field i - None when absent
+    fn field(&self, i: usize) -> Result<Option<usize>, Box<dyn Error>> {
+        let soffset = i32::from_le_bytes(
+            read_bytes(self.buffer, self.position, 4)?.try_into()?);
+        let vtable = self.position as i64 - soffset as i64;
+        if vtable < 0 {
+            return Err("invalid flatgeobuf vtable offset".into());
+        }
+
+        let vtable = vtable as usize;
+        let vtable_len = self.u16_at(vtable)? as usize;
+        if 4 + 2 * i + 2 > vtable_len {
+            return Ok(None);
+        }
+
+        match self.u16_at(vtable + 4 + 2 * i)? {
+            0 => Ok(None),
+            x => Ok(Some(self.position + x as usize)),
+        }
+    }
+
+    fn u16_at(&self, position: usize) -> Result<u16, Box<dyn Error>> {
+        Ok(u16::from_le_bytes(
+            read_bytes(self.buffer, position, 2)?.try_into()?))
+    }
+
+    fn u32_at(&self, position: usize) -> Result<u32, Box<dyn Error>> {
+        Ok(u32::from_le_bytes(
+            read_bytes(self.buffer, position, 4)?.try_into()?))
+    }
+
+    fn u8(&self, i: usize) -> Result<Option<u8>, Box<dyn Error>> {
+        match self.field(i)? {
+            Some(position) =>
+                Ok(Some(read_bytes(self.buffer, position, 1)?[0])),
+            None => Ok(None),
+        }
+    }
+
+    fn u16(&self, i: usize) -> Result<Option<u16>, Box<dyn Error>> {
+        match self.field(i)? {
+            Some(position) => Ok(Some(self.u16_at(position)?)),
+            None => Ok(None),
+        }
+    }
+
+    fn u32(&self, i: usize) -> Result<Option<u32>, Box<dyn Error>> {
+        match self.field(i)? {
+            Some(position) => Ok(Some(self.u32_at(position)?)),
+            None => Ok(None),
+        }
+    }
+
+    fn u64(&self, i: usize) -> Result<Option<u64>, Box<dyn Error>> {
+        match self.field(i)? {
+            Some(position) => Ok(Some(u64::from_le_bytes(
+                read_bytes(self.buffer, position, 8)?.try_into()?))),
+            None => Ok(None),
+        }
+    }
+
+    // follow the offset stored at field i
+    fn target(&self, i: usize) -> Result<Option<usize>, Box<dyn Error>> {
+        match self.field(i)? {
+            Some(position) =>
+                Ok(Some(position + self.u32_at(position)? as usize)),
+            None => Ok(None),
+        }
+    }
+
+    // bytes of a vector of ubytes - empty when absent
+    fn bytes(&self, i: usize) -> Result<&'a [u8], Box<dyn Error>> {
+        self.vector(i, 1).map(|x| x.unwrap_or(&[]))
+    }
+
+    fn string(&self, i: usize) -> Result<Option<&'a str>, Box<dyn Error>> {
+        match self.vector(i, 1)? {
+            Some(bytes) => Ok(Some(std::str::from_utf8(bytes)?)),
+            None => Ok(None),
+        }
+    }
+
+    fn table(&self, i: usize) -> Result<Option<Table<'a>>, Box<dyn Error>> {
+        match self.target(i)? {
+            Some(position) => Ok(Some(Table {
+                buffer: self.buffer,
+                position: position,
+            })),
+            None => Ok(None),
+        }
+    }
+
+    fn tables(&self, i: usize) -> Result<Vec<Table<'a>>, Box<dyn Error>> {
+        let start = match self.target(i)? {
+            Some(position) => position + 4,
+            None => return Ok(Vec::new()),
+        };
+
+        let len = self.u32_at(start - 4)? as usize;
+        (0..len).map(|k| {
+            let position = start + 4 * k;
+            Ok(Table {
+                buffer: self.buffer,
+                position: position + self.u32_at(position)? as usize,
+            })
+        }).collect()
+    }
+
+    // bytes of a vector of element_len scalars
+    fn vector(&self, i: usize, element_len: usize)
+            -> Result<Option<&'a [u8]>, Box<dyn Error>> {
+        match self.target(i)? {
+            Some(position) => {
+                let len = self.u32_at(position)? as usize * element_len;
+                Ok(Some(read_bytes(self.buffer, position + 4, len)?))
+            },
+            None => Ok(None),
+        }
+    }
+}
@@ -0,0 +1,378 @@
+use arrow::array::{ArrayRef, BinaryBuilder, Float64Builder, StringBuilder,
+    UInt32Builder};
+use arrow::datatypes::{DataType, Field, Schema};
+use arrow::record_batch::RecordBatch;
+use geo_types::Polygon;
+use parquet::arrow::ArrowWriter;
+use parquet::file::metadata::KeyValue;
+use parquet::file::properties::WriterProperties;
+
+use crate::error::NcprojError;
+
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fs::File;
+use std::io::{BufRead, BufReader, ErrorKind, Read, Write};
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::sync::Arc;
+
+// binary index files begin with a magic header followed by a version
+pub const MAGIC: &[u8; 4] = b"NCPI";
+pub const VERSION: u16 = 1;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Format {
+    Binary,
+    Text,
+}
+
+impl FromStr for Format {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "binary" => Ok(Format::Binary),
+            "text" => Ok(Format::Text),
+            x => Err(format!("unsupported index format '{}'", x)),
+        }
+    }
+}
+
+pub struct IndexWriter<W: Write> {
+    format: Format,
+    writer: W,
+}
+
+impl<W: Write> IndexWriter<W> {
+    pub fn new(mut writer: W, format: Format)
+            -> Result<IndexWriter<W>, Box<dyn Error>> {
+        // write binary header
+        if format == Format::Binary {
+            writer.write_all(MAGIC)?;
+            writer.write_all(&VERSION.to_le_bytes())?;
+        }
+
+        Ok(IndexWriter { format: format, writer: writer })
+    }
+
+    pub fn write(&mut self, x: usize, y: usize, id: &str, weight: f64)
+            -> Result<(), Box<dyn Error>> {
+        match self.format {
+            Format::Binary => {
+                // records are framed as <x, y, weight, id length, id>
+                self.writer.write_all(&(x as u32).to_le_bytes())?;
+                self.writer.write_all(&(y as u32).to_le_bytes())?;
+                self.writer.write_all(&(weight as f32).to_le_bytes())?;
+                self.writer.write_all(&(id.len() as u16).to_le_bytes())?;
+                self.writer.write_all(id.as_bytes())?;
+            },
+            Format::Text => writeln!(self.writer,
+                "{} {} {} {:.6}", x, y, id, weight)?,
+        }
+
+        Ok(())
+    }
+
+    pub fn flush(&mut self) -> Result<(), Box<dyn Error>> {
+        self.writer.flush()?;
+        Ok(())
+    }
+}
+
+// writes index entries with wkb cell polygons as geoparquet - cells
+//  are in grid longitude and latitude coordinates
+pub struct GeoParquetWriter {
+    batch_size: usize,
+    geometries: BinaryBuilder,
+    ids: StringBuilder,
+    len: usize,
+    schema: Arc<Schema>,
+    weights: Float64Builder,
+    writer: ArrowWriter<File>,
+    xs: UInt32Builder,
+    ys: UInt32Builder,
+}
+
+impl GeoParquetWriter {
+    pub fn new(file: File, batch_size: usize)
+            -> Result<GeoParquetWriter, Box<dyn Error>> {
+        let schema = Arc::new(Schema::new(vec![
+            Field::new("geometry", DataType::Binary, false),
+            Field::new("x", DataType::UInt32, false),
+            Field::new("y", DataType::UInt32, false),
+            Field::new("gis_join", DataType::Utf8, false),
+            Field::new("weight", DataType::Float64, false),
+        ]));
+
+        // describe the geometry column in the 'geo' file metadata
+        let geo = serde_json::json!({
+            "version": "0.4.0",
+            "primary_column": "geometry",
+            "columns": {
+                "geometry": {
+                    "encoding": "WKB",
+                    "geometry_types": ["Polygon"],
+                },
+            },
+        });
+
+        let properties = WriterProperties::builder()
+            .set_key_value_metadata(Some(vec![
+                KeyValue::new("geo".to_string(), geo.to_string())]))
+            .build();
+        let writer =
+            ArrowWriter::try_new(file, schema.clone(), Some(properties))?;
+
+        Ok(GeoParquetWriter {
+            batch_size: batch_size,
+            geometries: BinaryBuilder::new(batch_size),
+            ids: StringBuilder::new(batch_size),
+            len: 0,
+            schema: schema,
+            weights: Float64Builder::new(batch_size),
+            writer: writer,
+            xs: UInt32Builder::new(batch_size),
+            ys: UInt32Builder::new(batch_size),
+        })
+    }
+
+    pub fn write(&mut self, x: usize, y: usize, id: &str, weight: f64,
+            cell: &Polygon<f64>) -> Result<(), Box<dyn Error>> {
+        self.geometries.append_value(&polygon_wkb(cell))?;
+        self.xs.append_value(x as u32)?;
+        self.ys.append_value(y as u32)?;
+        self.ids.append_value(id)?;
+        self.weights.append_value(weight)?;
+
+        self.len += 1;
+        if self.len >= self.batch_size {
+            self.write_batch()?;
+        }
+
+        Ok(())
+    }
+
+    fn write_batch(&mut self) -> Result<(), Box<dyn Error>> {
+        let arrays: Vec<ArrayRef> = vec![
+            Arc::new(self.geometries.finish()),
+            Arc::new(self.xs.finish()),
+            Arc::new(self.ys.finish()),
+            Arc::new(self.ids.finish()),
+            Arc::new(self.weights.finish()),
+        ];
+
+        self.writer.write(&RecordBatch::try_new(self.schema.clone(), arrays)?)?;
+        self.len = 0;
+        Ok(())
+    }
+
+    pub fn close(&mut self) -> Result<(), Box<dyn Error>> {
+        if self.len != 0 {
+            self.write_batch()?;
+        }
+
+        self.writer.close()?;
+        Ok(())
+    }
+}
+
+// streams index entries as a geojson feature collection of cell polygons
+//  - cells are tagged with their shape id and filled with a color
+//  derived from it (simplestyle 'fill' with weight as 'fill-opacity')
+pub struct GeoJsonWriter<W: Write> {
+    count: usize,
+    writer: W,
+}
+
+impl<W: Write> GeoJsonWriter<W> {
+    pub fn new(mut writer: W) -> Result<GeoJsonWriter<W>, Box<dyn Error>> {
+        write!(writer, "{{\"type\":\"FeatureCollection\",\"features\":[")?;
+        Ok(GeoJsonWriter { count: 0, writer: writer })
+    }
+
+    pub fn write(&mut self, x: usize, y: usize, id: &str, weight: f64,
+            cell: &Polygon<f64>) -> Result<(), Box<dyn Error>> {
+        let rings: Vec<Vec<[f64; 2]>> = std::iter::once(cell.exterior())
+            .chain(cell.interiors().iter())
+            .map(|x| x.0.iter().map(|x| [x.x, x.y]).collect()).collect();
+
+        let feature = serde_json::json!({
+            "type": "Feature",
+            "geometry": {
+                "type": "Polygon",
+                "coordinates": rings,
+            },
+            "properties": {
+                "gis_join": id,
+                "x": x,
+                "y": y,
+                "weight": weight,
+                "fill": shape_color(id),
+                "fill-opacity": weight,
+            },
+        });
+
+        if self.count != 0 {
+            write!(self.writer, ",")?;
+        }
+
+        writeln!(self.writer)?;
+        serde_json::to_writer(&mut self.writer, &feature)?;
+        self.count += 1;
+        Ok(())
+    }
+
+    pub fn close(&mut self) -> Result<(), Box<dyn Error>> {
+        writeln!(self.writer, "\n]}}")?;
+        self.writer.flush()?;
+        Ok(())
+    }
+}
+
+// compute a stable '#rrggbb' color of a shape id (fnv-1a hash) - channels
+//  are kept above 64 so colors remain visible on dark basemaps
+fn shape_color(id: &str) -> String {
+    let hash = id.bytes().fold(0xcbf29ce484222325u64,
+        |hash, x| (hash ^ x as u64).wrapping_mul(0x100000001b3));
+    let channel = |shift: u32| 64 + ((hash >> shift) & 0xff) % 192;
+
+    format!("#{:02x}{:02x}{:02x}", channel(0), channel(8), channel(16))
+}
+
+// encode a polygon as little endian well-known binary
+fn polygon_wkb(polygon: &Polygon<f64>) -> Vec<u8> {
+    let rings: Vec<_> = std::iter::once(polygon.exterior())
+        .chain(polygon.interiors().iter()).collect();
+
+    let mut buffer = vec![1u8];
+    buffer.extend_from_slice(&3u32.to_le_bytes());
+    buffer.extend_from_slice(&(rings.len() as u32).to_le_bytes());
+    for ring in rings {
+        buffer.extend_from_slice(&(ring.0.len() as u32).to_le_bytes());
+        for coordinate in ring.0.iter() {
+            buffer.extend_from_slice(&coordinate.x.to_le_bytes());
+            buffer.extend_from_slice(&coordinate.y.to_le_bytes());
+        }
+    }
+
+    buffer
+}
+
+// read shape indices from an index file of either format
+pub fn read_index(path: &PathBuf)
+        -> Result<BTreeMap<String, Vec<(usize, usize, f32)>>, Box<dyn Error>> {
+    let reader = crate::open_input(path)?;
+    let mut buf_reader = BufReader::new(reader);
+
+    // check for binary magic header
+    let is_binary = buf_reader.fill_buf()?.starts_with(MAGIC);
+    let result = match is_binary {
+        true => read_binary(buf_reader),
+        false => read_text(buf_reader),
+    };
+
+    // classify malformed content (including truncation) as format errors
+    result.map_err(|e| match e.downcast::<std::io::Error>() {
+        Ok(e) if e.kind() != ErrorKind::UnexpectedEof
+            && e.kind() != ErrorKind::InvalidData => e as Box<dyn Error>,
+        Ok(e) => format_error(path, e.to_string()),
+        Err(e) => format_error(path, e.to_string()),
+    })
+}
+
+fn format_error(path: &PathBuf, message: String) -> Box<dyn Error> {
+    NcprojError::IndexFormat {
+        message: message,
+        path: path.display().to_string(),
+    }.into()
+}
+
+fn read_binary<R: Read>(mut reader: R)
+        -> Result<BTreeMap<String, Vec<(usize, usize, f32)>>, Box<dyn Error>> {
+    // read and validate header
+    let mut header = [0u8; 6];
+    reader.read_exact(&mut header)?;
+
+    let version = u16::from_le_bytes([header[4], header[5]]);
+    if version > VERSION {
+        return Err(format!("unsupported index version '{}'", version).into());
+    }
+
+    // iterate over index records
+    let mut shapes = BTreeMap::new();
+    let mut record = [0u8; 14];
+    loop {
+        // end of file ends the index at a record boundary only
+        let mut len = 0;
+        while len < record.len() {
+            match reader.read(&mut record[len..]) {
+                Ok(0) => break,
+                Ok(count) => len += count,
+                Err(ref e) if e.kind() == ErrorKind::Interrupted => {},
+                Err(e) => return Err(e.into()),
+            }
+        }
+
+        match len {
+            0 => break,
+            x if x < record.len() =>
+                return Err("truncated index record".into()),
+            _ => {},
+        }
+
+        let x = u32::from_le_bytes([record[0],
+            record[1], record[2], record[3]]) as usize;
+        let y = u32::from_le_bytes([record[4],
+            record[5], record[6], record[7]]) as usize;
+        let weight = f32::from_le_bytes([record[8],
+            record[9], record[10], record[11]]);
+        let id_len = u16::from_le_bytes([record[12], record[13]]) as usize;
+
+        let mut id = vec![0u8; id_len];
+        match reader.read_exact(&mut id) {
+            Ok(_) => {},
+            Err(ref e) if e.kind() == ErrorKind::UnexpectedEof =>
+                return Err("truncated index record".into()),
+            Err(e) => return Err(e.into()),
+        }
+
+        // add index to shapes map
+        let indices = shapes.entry(String::from_utf8(id)?)
+            .or_insert(Vec::new());
+        indices.push((x, y, weight));
+    }
+
+    Ok(shapes)
+}
+
+fn read_text<R: BufRead>(reader: R)
+        -> Result<BTreeMap<String, Vec<(usize, usize, f32)>>, Box<dyn Error>> {
+    let mut shapes = BTreeMap::new();
+
+    // iterate over index entries
+    for (i, result) in reader.lines().enumerate() {
+        let line = result?;
+        let fields: Vec<&str> = line.split(" ").collect();
+        if fields.len() < 3 {
+            return Err(format!("line {}: expected 'x y id [weight]'",
+                i + 1).into());
+        }
+
+        let x = fields[0].parse::<usize>()?;
+        let y = fields[1].parse::<usize>()?;
+
+        // parse fractional coverage weight if present
+        let weight = match fields.get(3) {
+            Some(field) => field.parse::<f32>()?,
+            None => 1f32,
+        };
+
+        // add index to shapes map
+        let indices = shapes.entry(fields[2].to_string())
+            .or_insert(Vec::new());
+        indices.push((x, y, weight));
+    }
+
+    Ok(shapes)
+}
@@ -0,0 +1,689 @@
+use geo::algorithm::bounding_rect::BoundingRect;
+use geo_types::{LineString, Polygon};
+use ndarray::{Array2, Ix2};
+
+use crate::dataset::{AttrValue, Dataset, Dimension};
+
+use std::error::Error;
+use std::path::PathBuf;
+use std::str::FromStr;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum LonConvention {
+    Auto,
+    Signed,
+    Unsigned,
+}
+
+impl LonConvention {
+    // normalize a longitude in this convention to -180..180
+    pub fn normalize(&self, longitude: f64) -> f64 {
+        match self {
+            LonConvention::Unsigned if longitude >= 180.0 => longitude - 360.0,
+            _ => longitude,
+        }
+    }
+}
+
+impl FromStr for LonConvention {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "auto" => Ok(LonConvention::Auto),
+            "180" | "±180" | "-180-180" => Ok(LonConvention::Signed),
+            "0-360" | "360" => Ok(LonConvention::Unsigned),
+            x => Err(format!("unsupported longitude convention '{}'", x)),
+        }
+    }
+}
+
+// assignment of shapes smaller than a grid cell
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Interp {
+    Bilinear,
+    Nearest,
+}
+
+impl FromStr for Interp {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "bilinear" => Ok(Interp::Bilinear),
+            "nearest" => Ok(Interp::Nearest),
+            x => Err(format!("unsupported interpolation '{}'", x)),
+        }
+    }
+}
+
+// measure ranking cells by the distance of their centers from a point
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Distance {
+    Haversine, // great circle distance of geographic coordinates
+    Planar, // euclidean distance (e.g. projected coordinates)
+}
+
+impl Distance {
+    // compute the distance between (x, y) points - haversine in km
+    pub fn measure(&self, a: (f64, f64), b: (f64, f64)) -> f64 {
+        match self {
+            Distance::Haversine => {
+                let (a_lat, b_lat) = (a.1.to_radians(), b.1.to_radians());
+                let h = ((b_lat - a_lat) / 2.0).sin().powi(2)
+                    + a_lat.cos() * b_lat.cos()
+                        * ((b.0 - a.0).to_radians() / 2.0).sin().powi(2);
+                2.0 * 6371.0 * h.sqrt().min(1.0).asin()
+            },
+            Distance::Planar =>
+                ((b.0 - a.0).powi(2) + (b.1 - a.1).powi(2)).sqrt(),
+        }
+    }
+}
+
+impl FromStr for Distance {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "haversine" | "geodesic" => Ok(Distance::Haversine),
+            "planar" => Ok(Distance::Planar),
+            x => Err(format!("unsupported distance '{}'", x)),
+        }
+    }
+}
+
+// scaling of cell coverage weights when aggregating
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum CellWeight {
+    Area,
+    CosLat,
+    None,
+}
+
+impl CellWeight {
+    // compute the weight of the cell at (x, y)
+    pub fn weight(&self, grid: &Grid, x: usize, y: usize) -> f64 {
+        match self {
+            CellWeight::Area => spherical_area(&grid.cell(x, y)),
+            CellWeight::CosLat => {
+                let cell = grid.cell(x, y);
+                let points = &cell.exterior().0[1..];
+                let latitude = points.iter().map(|x| x.y).sum::<f64>()
+                    / points.len() as f64;
+                latitude.to_radians().cos().max(0.0)
+            },
+            CellWeight::None => 1.0,
+        }
+    }
+}
+
+impl FromStr for CellWeight {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "area" => Ok(CellWeight::Area),
+            "coslat" => Ok(CellWeight::CosLat),
+            "none" => Ok(CellWeight::None),
+            x => Err(format!("unsupported cell weight '{}'", x)),
+        }
+    }
+}
+
+// auxiliary cell weights (e.g. population or land fraction) of a 2d
+//  variable on the data grid parsed from 'path:variable'
+#[derive(Clone, Debug, PartialEq)]
+pub struct WeightRaster {
+    pub path: PathBuf,
+    pub variable: String,
+}
+
+impl WeightRaster {
+    // read weights indexed by y * x_len + x - undefined and negative
+    //  weights are zero
+    pub fn read(&self, y_len: usize, x_len: usize)
+            -> Result<Vec<f32>, Box<dyn Error>> {
+        let reader = Dataset::open(&self.path)?;
+        let variable = reader.variable(&self.variable)
+            .ok_or_else(|| format!("weight variable '{}' not found in '{}'",
+                self.variable, self.path.display()))?;
+
+        // leading dimensions (e.g. time) must be singleton
+        let count: Vec<usize> =
+            variable.dimensions().iter().map(|x| x.len()).collect();
+        if count.len() < 2 || count[count.len() - 2..] != [y_len, x_len]
+                || count.iter().product::<usize>() != y_len * x_len {
+            return Err(format!("weight variable '{}' {:?} does not match \
+                the {}x{} data grid", self.variable, count,
+                y_len, x_len).into());
+        }
+
+        let mut weights = vec![0f32; y_len * x_len];
+        variable.values_to(&mut weights, &vec![0; count.len()], &count)?;
+        crate::dumper::Encoding::read(&variable)?.decode(&mut weights);
+
+        for weight in weights.iter_mut() {
+            if !weight.is_finite() || *weight < 0.0 {
+                *weight = 0.0;
+            }
+        }
+
+        Ok(weights)
+    }
+}
+
+impl FromStr for WeightRaster {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.rfind(':') {
+            Some(i) if i > 0 && i + 1 < s.len() => Ok(WeightRaster {
+                path: PathBuf::from(&s[..i]),
+                variable: s[i + 1..].to_string(),
+            }),
+            _ => Err(format!("invalid weights '{}' - expected \
+                'path:variable'", s)),
+        }
+    }
+}
+
+pub enum Grid {
+    // 2d 'lat(y, x)' and 'lon(y, x)' coordinates - stores cell corners
+    Curvilinear {
+        latitudes: Array2<f64>,
+        longitudes: Array2<f64>,
+    },
+    // 1d 'lat' and 'lon' coordinates - stores (low, high) cell bounds
+    //  which need not be uniformly spaced (e.g. gaussian grids)
+    Rectilinear {
+        latitude_bounds: Vec<(f64, f64)>,
+        latitudes: Vec<f64>,
+        longitude_bounds: Vec<(f64, f64)>,
+        longitudes: Vec<f64>,
+    },
+}
+
+impl Grid {
+    pub fn open(reader: &Dataset, lon_convention: LonConvention)
+            -> Result<Grid, Box<dyn Error>> {
+        // rotated pole grids are converted to curvilinear grids
+        if let Some(grid) = Grid::open_rotated(reader)? {
+            return Ok(grid);
+        }
+
+        let latitudes = crate::get_netcdf_values(reader, "lat")?;
+        let raw_longitudes = crate::get_netcdf_values(reader, "lon")?;
+
+        // identify longitude convention
+        let lon_convention = match lon_convention {
+            LonConvention::Auto =>
+                    match raw_longitudes.iter().any(|x| *x > 180.0) {
+                true => LonConvention::Unsigned,
+                false => LonConvention::Signed,
+            },
+            x => x,
+        };
+
+        let longitudes =
+            raw_longitudes.mapv(|x| lon_convention.normalize(x));
+
+        match (latitudes.ndim(), longitudes.ndim()) {
+            (1, 1) => {
+                let latitudes: Vec<f64> = latitudes.iter().cloned().collect();
+                let longitudes: Vec<f64> = longitudes.iter().cloned().collect();
+                let raw_longitudes: Vec<f64> =
+                    raw_longitudes.iter().cloned().collect();
+
+                // shift longitude bounds with their normalized centers
+                let longitude_bounds = read_bounds(reader, "lon",
+                        &raw_longitudes)?.into_iter()
+                    .zip(raw_longitudes.iter().zip(longitudes.iter()))
+                    .map(|((low, high), (raw, longitude))|
+                        (low + longitude - raw, high + longitude - raw))
+                    .collect();
+
+                Ok(Grid::Rectilinear {
+                    latitude_bounds: read_bounds(reader, "lat", &latitudes)?,
+                    latitudes: latitudes,
+                    longitude_bounds: longitude_bounds,
+                    longitudes: longitudes,
+                })
+            },
+            (2, 2) => Ok(Grid::Curvilinear {
+                latitudes: corners(&latitudes.into_dimensionality::<Ix2>()?),
+                longitudes: corners(&longitudes.into_dimensionality::<Ix2>()?),
+            }),
+            _ => Err("unsupported lat / lon coordinate dimensions".into()),
+        }
+    }
+
+    // open a grid of 'rlat' and 'rlon' coordinates on a rotated pole
+    fn open_rotated(reader: &Dataset)
+            -> Result<Option<Grid>, Box<dyn Error>> {
+        if reader.variable("rlat").is_none()
+                || reader.variable("rlon").is_none() {
+            return Ok(None);
+        }
+
+        let mapping = match crate::cf::find_grid_mapping(reader,
+                "rotated_latitude_longitude")? {
+            Some(mapping) => mapping,
+            None => return Err("rotated pole grid mapping not found".into()),
+        };
+
+        let pole_latitude = crate::cf::attribute_f64(&mapping,
+            "grid_north_pole_latitude")?.ok_or("pole latitude not found")?;
+        let pole_longitude = crate::cf::attribute_f64(&mapping,
+            "grid_north_pole_longitude")?.ok_or("pole longitude not found")?;
+        let pole_grid_longitude = crate::cf::attribute_f64(&mapping,
+            "north_pole_grid_longitude")?.unwrap_or(0.0);
+
+        // compute rotated cell edges
+        let rlats = edges(&crate::get_netcdf_values(reader, "rlat")?
+            .iter().cloned().collect::<Vec<f64>>());
+        let rlons = edges(&crate::get_netcdf_values(reader, "rlon")?
+            .iter().cloned().collect::<Vec<f64>>());
+
+        // unrotate cell corners into geographic coordinates
+        let mut latitudes = Array2::<f64>::zeros((rlats.len(), rlons.len()));
+        let mut longitudes = Array2::<f64>::zeros((rlats.len(), rlons.len()));
+        for (r, rlat) in rlats.iter().enumerate() {
+            for (c, rlon) in rlons.iter().enumerate() {
+                let (longitude, latitude) = unrotate(*rlon - pole_grid_longitude,
+                    *rlat, pole_longitude, pole_latitude);
+
+                latitudes[[r, c]] = latitude;
+                longitudes[[r, c]] = longitude;
+            }
+        }
+
+        Ok(Some(Grid::Curvilinear {
+            latitudes: latitudes,
+            longitudes: longitudes,
+        }))
+    }
+
+    // compute the grid (y, x) dimension lengths
+    pub fn dimensions(&self) -> (usize, usize) {
+        match self {
+            Grid::Curvilinear { latitudes, .. } =>
+                (latitudes.nrows() - 1, latitudes.ncols() - 1),
+            Grid::Rectilinear { latitudes, longitudes, .. } =>
+                (latitudes.len(), longitudes.len()),
+        }
+    }
+
+    // compute the (min x, min y, max x, max y) extent of all cells
+    pub fn extent(&self) -> (f64, f64, f64, f64) {
+        let (xs, ys): (Vec<f64>, Vec<f64>) = match self {
+            Grid::Curvilinear { latitudes, longitudes } =>
+                (longitudes.iter().cloned().collect(),
+                    latitudes.iter().cloned().collect()),
+            Grid::Rectilinear { latitude_bounds, longitude_bounds, .. } =>
+                (longitude_bounds.iter().flat_map(|x| vec![x.0, x.1])
+                        .collect(),
+                    latitude_bounds.iter().flat_map(|x| vec![x.0, x.1])
+                        .collect()),
+        };
+
+        let min = |values: &[f64]| values.iter().cloned().fold(f64::MAX, f64::min);
+        let max = |values: &[f64]| values.iter().cloned().fold(f64::MIN, f64::max);
+        (min(&xs), min(&ys), max(&xs), max(&ys))
+    }
+
+    // compute the [start, end) runs of x and y indices of cells
+    //  intersecting a (min lon, min lat, max lon, max lat) box - axes
+    //  normalized from 0..360 longitudes wrap so intersecting cells may
+    //  form two runs, and curvilinear cells within the runs may lie
+    //  outside of the box (see cell_intersects)
+    pub fn cell_ranges(&self, bbox: &[f64; 4])
+            -> (Vec<(usize, usize)>, Vec<(usize, usize)>) {
+        let intersects = |a: f64, b: f64, low: f64, high: f64|
+            a.max(b) >= low && a.min(b) <= high;
+
+        let (xs, ys) = match self {
+            Grid::Curvilinear { .. } => {
+                // test the bounds of every cell
+                let (y_len, x_len) = self.dimensions();
+                let (mut xs, mut ys) = (vec![false; x_len], vec![false; y_len]);
+                for y in 0..y_len {
+                    for x in 0..x_len {
+                        if self.cell_intersects(x, y, bbox) {
+                            xs[x] = true;
+                            ys[y] = true;
+                        }
+                    }
+                }
+
+                (xs, ys)
+            },
+            Grid::Rectilinear { latitude_bounds, longitude_bounds, .. } =>
+                (longitude_bounds.iter()
+                        .map(|x| intersects(x.0, x.1, bbox[0], bbox[2]))
+                        .collect(),
+                    latitude_bounds.iter()
+                        .map(|x| intersects(x.0, x.1, bbox[1], bbox[3]))
+                        .collect()),
+        };
+
+        (runs(&xs), runs(&ys))
+    }
+
+    // check if the bounds of the cell at (x, y) intersect a (min lon,
+    //  min lat, max lon, max lat) box
+    pub fn cell_intersects(&self, x: usize, y: usize, bbox: &[f64; 4])
+            -> bool {
+        match self.cell(x, y).bounding_rect() {
+            Some(rect) => rect.max().x >= bbox[0] && rect.min().x <= bbox[2]
+                && rect.max().y >= bbox[1] && rect.min().y <= bbox[3],
+            None => false,
+        }
+    }
+
+    // compute the mean area of a cell (None for curvilinear grids)
+    pub fn cell_area(&self) -> Option<f64> {
+        let mean_width = |bounds: &[(f64, f64)]| bounds.iter()
+            .map(|x| (x.1 - x.0).abs()).sum::<f64>() / bounds.len() as f64;
+
+        match self {
+            Grid::Curvilinear { .. } => None,
+            Grid::Rectilinear { latitude_bounds, longitude_bounds, .. } =>
+                Some(mean_width(latitude_bounds)
+                    * mean_width(longitude_bounds)),
+        }
+    }
+
+    // compute (x, y, weight) of the four cells surrounding a point
+    //  weighted by bilinear interpolation between cell centers
+    //  - unsupported (None) for curvilinear grids
+    pub fn bilinear(&self, longitude: f64, latitude: f64)
+            -> Option<Vec<(usize, usize, f64)>> {
+        let (latitudes, longitudes) = match self {
+            Grid::Curvilinear { .. } => return None,
+            Grid::Rectilinear { latitudes, longitudes, .. } =>
+                (latitudes, longitudes),
+        };
+
+        // locate the lower cell center and fractional offset on an axis
+        //  - values beyond the centers are clamped to the nearest end
+        let locate = |value: f64, centers: &[f64]| {
+            let len = centers.len();
+            let lower = (0..len.saturating_sub(1)).find(|i| {
+                let (a, b) = (centers[*i], centers[i + 1]);
+                value >= a.min(b) && value <= a.max(b)
+            });
+
+            match lower {
+                Some(i) => (i, ((value - centers[i])
+                    / (centers[i + 1] - centers[i])).max(0.0).min(1.0)),
+                None if (value - centers[0]).abs()
+                    <= (value - centers[len - 1]).abs() => (0, 0.0),
+                None => (len - 2, 1.0),
+            }
+        };
+
+        let (x, tx) = locate(longitude, longitudes);
+        let (y, ty) = locate(latitude, latitudes);
+
+        let x_max = longitudes.len() - 1;
+        let y_max = latitudes.len() - 1;
+        let weights = vec![
+            (x, y, (1.0 - tx) * (1.0 - ty)),
+            ((x + 1).min(x_max), y, tx * (1.0 - ty)),
+            (x, (y + 1).min(y_max), (1.0 - tx) * ty),
+            ((x + 1).min(x_max), (y + 1).min(y_max), tx * ty),
+        ];
+
+        Some(weights.into_iter().filter(|x| x.2 > 0.0).collect())
+    }
+
+    // compute the (x, y) cell with the center nearest a point
+    pub fn nearest(&self, longitude: f64, latitude: f64,
+            distance: Distance) -> Option<(usize, usize)> {
+        let nearest = |a: Option<(usize, f64)>, b: (usize, f64)| match a {
+            Some(a) if a.1 <= b.1 => Some(a),
+            _ => Some(b),
+        };
+
+        match self {
+            Grid::Curvilinear { .. } => {
+                // test the corner mean of every cell
+                let (y_len, x_len) = self.dimensions();
+                let mut cell = None;
+                for y in 0..y_len {
+                    for x in 0..x_len {
+                        let polygon = self.cell(x, y);
+                        let points = &polygon.exterior().0[1..];
+                        let (x_sum, y_sum) = points.iter()
+                            .fold((0.0, 0.0), |a, b| (a.0 + b.x, a.1 + b.y));
+                        let center = (x_sum / points.len() as f64,
+                            y_sum / points.len() as f64);
+
+                        cell = nearest(cell, (y * x_len + x, distance
+                            .measure(center, (longitude, latitude))));
+                    }
+                }
+
+                cell.map(|(i, _)| (i % x_len, i / x_len))
+            },
+            Grid::Rectilinear { latitudes, longitudes, .. } => {
+                // the nearest longitude is independent of latitude under
+                //  either measure - latitudes are then ranked along it
+                let point = (longitude, latitude);
+                let x = longitudes.iter().enumerate()
+                    .map(|(i, x)| (i, distance.measure((*x, latitude), point)))
+                    .fold(None, nearest).map(|(i, _)| i)?;
+                let y = latitudes.iter().enumerate()
+                    .map(|(i, y)| (i,
+                        distance.measure((longitudes[x], *y), point)))
+                    .fold(None, nearest).map(|(i, _)| i)?;
+
+                Some((x, y))
+            },
+        }
+    }
+
+    // compute the polygon bounding the cell at (x, y)
+    pub fn cell(&self, x: usize, y: usize) -> Polygon<f64> {
+        let mut coordinates = match self {
+            Grid::Curvilinear { latitudes, longitudes } => vec![
+                (longitudes[[y, x]], latitudes[[y, x]]),
+                (longitudes[[y, x + 1]], latitudes[[y, x + 1]]),
+                (longitudes[[y + 1, x + 1]], latitudes[[y + 1, x + 1]]),
+                (longitudes[[y + 1, x]], latitudes[[y + 1, x]]),
+                (longitudes[[y, x]], latitudes[[y, x]]),
+            ],
+            Grid::Rectilinear { latitude_bounds, longitude_bounds, .. } => {
+                let ((x_low, x_high), (y_low, y_high)) =
+                    (longitude_bounds[x], latitude_bounds[y]);
+                vec![(x_low, y_low), (x_high, y_low), (x_high, y_high),
+                    (x_low, y_high), (x_low, y_low)]
+            },
+        };
+
+        // orient rings counterclockwise regardless of axis direction
+        //  (e.g. latitudes descending from north to south)
+        let area: f64 = coordinates.windows(2)
+            .map(|x| (x[0].0 * x[1].1) - (x[1].0 * x[0].1)).sum();
+        if area < 0.0 {
+            coordinates.reverse();
+        }
+
+        Polygon::new(LineString::from(coordinates), vec![])
+    }
+}
+
+// read the (low, high) bounds of cells along a coordinate axis from the
+//  cf 'bounds' variable (e.g. 'lat_bnds') or midpoints between centers
+fn read_bounds(reader: &Dataset, name: &str, centers: &[f64])
+        -> Result<Vec<(f64, f64)>, Box<dyn Error>> {
+    let bounds_name = match reader.variable(name) {
+        Some(variable) => match variable.attribute("bounds")? {
+            Some(AttrValue::Str(value)) => Some(value),
+            _ => None,
+        },
+        None => None,
+    }.or_else(|| {
+        let bounds_name = format!("{}_bnds", name);
+        reader.variable(&bounds_name).map(|_| bounds_name)
+    });
+
+    if let Some(bounds_name) = bounds_name {
+        let values = crate::get_netcdf_values(reader, &bounds_name)?;
+        if values.shape() != [centers.len(), 2] {
+            return Err(format!("invalid shape {:?} of bounds variable \
+                '{}'", values.shape(), bounds_name).into());
+        }
+
+        return Ok(values.into_dimensionality::<Ix2>()?.outer_iter()
+            .map(|x| (x[0].min(x[1]), x[0].max(x[1]))).collect());
+    }
+
+    if centers.len() < 2 {
+        return Err(format!("cells of '{}' require a bounds variable or \
+            multiple coordinates", name).into());
+    }
+
+    // edges of descending coordinates are ordered per cell
+    Ok(edges(centers).windows(2)
+        .map(|x| (x[0].min(x[1]), x[0].max(x[1]))).collect())
+}
+
+// group the indices of set flags into [start, end) runs
+fn runs(flags: &[bool]) -> Vec<(usize, usize)> {
+    let mut runs: Vec<(usize, usize)> = Vec::new();
+    for (i, flag) in flags.iter().enumerate() {
+        if !flag {
+            continue;
+        }
+
+        match runs.last_mut() {
+            Some(run) if run.1 == i => run.1 = i + 1,
+            _ => runs.push((i, i + 1)),
+        }
+    }
+
+    runs
+}
+
+// read the grid (y, x) dimension lengths without computing cells
+pub fn read_dimensions(reader: &Dataset)
+        -> Result<(usize, usize), Box<dyn Error>> {
+    let (y, x) = read_grid_dimensions(reader)?;
+    Ok((y.len(), x.len()))
+}
+
+// read the grid (y, x) dimension names
+pub fn read_dimension_names(reader: &Dataset)
+        -> Result<(String, String), Box<dyn Error>> {
+    let (y, x) = read_grid_dimensions(reader)?;
+    Ok((y.name(), x.name()))
+}
+
+fn read_grid_dimensions(reader: &Dataset)
+        -> Result<(Dimension, Dimension), Box<dyn Error>> {
+    let (latitude, longitude) = match (reader.variable("rlat"),
+            reader.variable("rlon"), reader.variable("lat"),
+            reader.variable("lon")) {
+        (Some(latitude), Some(longitude), _, _) => (latitude, longitude),
+        (_, _, Some(latitude), Some(longitude)) => (latitude, longitude),
+        _ => return Err("lat / lon coordinate variables not found".into()),
+    };
+
+    let (mut latitude_dimensions, mut longitude_dimensions) =
+        (latitude.dimensions(), longitude.dimensions());
+    match (latitude_dimensions.len(), longitude_dimensions.len()) {
+        (1, 1) => Ok((latitude_dimensions.remove(0),
+            longitude_dimensions.remove(0))),
+        (2, 2) => {
+            let x = latitude_dimensions.remove(1);
+            Ok((latitude_dimensions.remove(0), x))
+        },
+        _ => Err("unsupported lat / lon coordinate dimensions".into()),
+    }
+}
+
+// compute the area (km^2) of a lon / lat polygon on a spherical earth
+//  (chamberlain and duquette)
+fn spherical_area(polygon: &Polygon<f64>) -> f64 {
+    let points = &polygon.exterior().0;
+
+    let mut sum = 0.0;
+    for k in 1..points.len() {
+        let (p1, p2) = (points[k - 1], points[k]);
+        sum += (p2.x - p1.x).to_radians() * (2.0
+            + p1.y.to_radians().sin() + p2.y.to_radians().sin());
+    }
+
+    (sum * 6371.0 * 6371.0 / 2.0).abs()
+}
+
+// compute cell edges from 1d cell centers - ends are linearly extrapolated
+fn edges(centers: &[f64]) -> Vec<f64> {
+    let len = centers.len();
+
+    let mut edges = vec![1.5 * centers[0] - 0.5 * centers[1]];
+    for k in 1..len {
+        edges.push((centers[k - 1] + centers[k]) / 2.0);
+    }
+
+    edges.push(1.5 * centers[len - 1] - 0.5 * centers[len - 2]);
+    edges
+}
+
+// convert rotated pole coordinates to geographic (longitude, latitude)
+fn unrotate(rlon: f64, rlat: f64, pole_longitude: f64, pole_latitude: f64)
+        -> (f64, f64) {
+    let (rlon, rlat) = (rlon.to_radians(), rlat.to_radians());
+    let theta = (90.0 - pole_latitude).to_radians();
+    let phi = (pole_longitude + 180.0).to_radians();
+
+    // convert to cartesian coordinates
+    let (x, y, z) = (rlat.cos() * rlon.cos(),
+        rlat.cos() * rlon.sin(), rlat.sin());
+
+    // rotate about the y axis by theta and then the z axis by phi
+    let (x, z) = (theta.cos() * x - theta.sin() * z,
+        theta.sin() * x + theta.cos() * z);
+    let (x, y) = (phi.cos() * x - phi.sin() * y,
+        phi.sin() * x + phi.cos() * y);
+
+    (y.atan2(x).to_degrees(), z.asin().to_degrees())
+}
+
+// compute cell corners from cell centers - edges are linearly extrapolated
+fn corners(centers: &Array2<f64>) -> Array2<f64> {
+    let (rows, cols) = centers.dim();
+
+    // pad centers by extrapolating one cell in each direction
+    let mut padded = Array2::<f64>::zeros((rows + 2, cols + 2));
+    for r in 0..rows {
+        for c in 0..cols {
+            padded[[r + 1, c + 1]] = centers[[r, c]];
+        }
+    }
+
+    for c in 1..cols + 1 {
+        padded[[0, c]] = 2.0 * padded[[1, c]] - padded[[2, c]];
+        padded[[rows + 1, c]] =
+            2.0 * padded[[rows, c]] - padded[[rows - 1, c]];
+    }
+
+    for r in 0..rows + 2 {
+        padded[[r, 0]] = 2.0 * padded[[r, 1]] - padded[[r, 2]];
+        padded[[r, cols + 1]] =
+            2.0 * padded[[r, cols]] - padded[[r, cols - 1]];
+    }
+
+    // average the four centers surrounding each corner
+    let mut corners = Array2::<f64>::zeros((rows + 1, cols + 1));
+    for r in 0..rows + 1 {
+        for c in 0..cols + 1 {
+            corners[[r, c]] = (padded[[r, c]] + padded[[r + 1, c]]
+                + padded[[r, c + 1]] + padded[[r + 1, c + 1]]) / 4.0;
+        }
+    }
+
+    corners
+}
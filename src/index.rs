@@ -1,176 +1,243 @@
-use crossbeam_channel::{Receiver, Sender};
-use dbase::FieldValue;
-use geo::algorithm::centroid::Centroid;
-use geo::algorithm::contains::Contains;
-use geo::algorithm::euclidean_distance::EuclideanDistance;
-use geo::algorithm::intersects::Intersects;
-use geo_types::{LineString, MultiPolygon, Point, Polygon};
-use shapefile::Reader;
 use structopt::StructOpt;
 
-use std::collections::{BTreeMap, HashMap};
+use ncproj_rs::dataset::Dataset;
+use ncproj_rs::format::{Format, GeoJsonWriter, GeoParquetWriter,
+    IndexWriter};
+use ncproj_rs::grid::{Distance, Grid, Interp, LonConvention};
+use ncproj_rs::indexer::{Fallback, Indexer};
+use ncproj_rs::shape::Filter;
+
+use std::collections::{BTreeMap, HashSet};
 use std::error::Error;
 use std::path::PathBuf;
-use std::sync::Arc;
 
 #[derive(StructOpt)]
 pub struct Index {
-    #[structopt(short = "b", long = "buffer-size", default_value = "5")]
-    buffer_size: usize,
-
+    // only index cells intersecting 'min_lon,min_lat,max_lon,max_lat'
+    //  and shapes within it - shapes crossing the box are only indexed
+    //  over cells within it
+    #[structopt(long = "bbox", use_delimiter = true,
+        allow_hyphen_values = true)]
+    bbox: Vec<f64>,
+
+    // distance in kilometers expanding shapes before intersection - points
+    //  and lines are buffered into polygons
+    #[structopt(long = "buffer-km")]
+    buffer: Option<f64>,
+
+    // attribute whose shapes sharing a value are dissolved into one shape
+    //  identified by the value (e.g. counties into states by 'STATEFP10')
+    #[structopt(long = "dissolve-by", conflicts_with = "id_field")]
+    dissolve_by: Option<String>,
+
+    // distance ranking cells nearest shape centroids - 'haversine' or
+    //  'planar' (e.g. projected grids)
+    #[structopt(long = "distance", default_value = "haversine")]
+    distance: Distance,
+
+    // assignment of shapes without intersecting cells - 'none' reports
+    //  them and 'nearest' assigns the cell nearest their centroid
+    #[structopt(long = "fallback", default_value = "none")]
+    fallback: Fallback,
+
+    // only index shapes whose attributes (shapefile dbase fields or
+    //  geojson and flatgeobuf properties) match a filter
+    //  (e.g. "STATEFP10 == '08'")
+    //  - repeated filters must all match
+    #[structopt(long = "where")]
+    filters: Vec<Filter>,
+
+    #[structopt(short = "f", long = "format", default_value = "text")]
+    format: Format,
+
+    // also write entries as a geojson feature collection of cell polygons
+    //  tagged and colored by shape id (e.g. for inspection in qgis)
+    #[structopt(long = "geojson", parse(from_os_str))]
+    geojson: Option<PathBuf>,
+
+    // write a geojson feature collection for each shape (named by shape
+    //  id) into the geojson path as a directory
+    #[structopt(long = "geojson-per-shape")]
+    geojson_per_shape: bool,
+
+    // also write entries with their cell polygons to a geoparquet file
+    //  for inspection (e.g. in geopandas or duckdb)
+    #[structopt(long = "geoparquet", parse(from_os_str))]
+    geoparquet: Option<PathBuf>,
+
+    // netcdf file, opendap url, or s3 url ('s3://bucket/key')
     #[structopt(parse(from_os_str), index = 2)]
     grid_file: PathBuf,
 
+    // field used as the shape id (shapefiles default to gis_join composed
+    //  from 'STATEFP10' and 'COUNTYFP10', geojson to the feature id,
+    //  geopackage to the primary key, and csv to the 'id' column -
+    //  required for flatgeobuf)
+    #[structopt(short = "i", long = "id-field")]
+    id_field: Option<String>,
+
+    // assignment of points and shapes smaller than a cell - 'nearest'
+    //  uses cell coverage (or containing cells) and 'bilinear' weights
+    //  the four cells around the point or shape centroid (rectilinear
+    //  grids)
+    #[structopt(long = "interp", default_value = "nearest")]
+    interp: Interp,
+
+    // layer to read from multi-layer inputs (geopackage)
+    #[structopt(short = "l", long = "layer")]
+    layer: Option<String>,
+
+    // grid longitude convention ('auto', '0-360', or '180') - longitudes
+    //  are normalized to -180..180 for comparison with shapes
+    #[structopt(long = "lon-convention", default_value = "auto")]
+    lon_convention: LonConvention,
+
+    #[structopt(short = "o", long = "output", parse(from_os_str))]
+    output: Option<PathBuf>,
+
+    // suppress progress reporting and reporting of shapes without cells
+    #[structopt(short = "q", long = "quiet")]
+    quiet: bool,
+
     #[structopt(parse(from_os_str), index = 1)]
     shape_file: PathBuf,
 
-    #[structopt(short = "t", long = "thread-count", default_value = "8")]
-    thread_count: u8,
+    // number of worker threads (default logical cpus)
+    #[structopt(short = "t", long = "thread-count")]
+    thread_count: Option<u8>,
 }
 
 impl Index {
     pub fn execute(&self) -> Result<(), Box<dyn Error>> {
-        // populate shapes map
-        let mut shapes: BTreeMap<String, (Point<f64>, Polygon<f64>)> =
-            BTreeMap::new();
+        if self.geojson_per_shape && self.geojson.is_none() {
+            return Err("geojson-per-shape requires a geojson path".into());
+        }
 
-        {
-            // open shapefile reader and iterator
-            let reader = Reader::from_path(&self.shape_file)?;
-            let iterator = reader.iter_shapes_and_records_as
-                    ::<shapefile::Polygon>()?;
+        let bbox = match self.bbox.as_slice() {
+            [] => None,
+            [min_x, min_y, max_x, max_y] if min_x <= max_x && min_y <= max_y
+                => Some([*min_x, *min_y, *max_x, *max_y]),
+            _ => return Err(
+                "bbox requires 'min_lon,min_lat,max_lon,max_lat'".into()),
+        };
+
+        if let Some(buffer) = self.buffer {
+            if !buffer.is_finite() || buffer <= 0.0 {
+                return Err(format!("invalid buffer distance '{}'",
+                    buffer).into());
+            }
+        }
 
-            // iterate over shapefile
-            for result in iterator {
-                let (shape, record) = result?;
+        let entries = Indexer::new(self.shape_file.clone(),
+                self.grid_file.clone())
+            .bbox(bbox)
+            .buffer(self.buffer)
+            .dissolve_by(self.dissolve_by.clone())
+            .distance(self.distance)
+            .fallback(self.fallback)
+            .filters(self.filters.clone())
+            .id_field(self.id_field.clone())
+            .interp(self.interp)
+            .layer(self.layer.clone())
+            .lon_convention(self.lon_convention)
+            .quiet(self.quiet)
+            .thread_count(self.thread_count
+                .unwrap_or_else(ncproj_rs::default_thread_count))
+            .build()?;
+
+        // open the grid computing cell polygons of geometry outputs
+        let grid = match self.geojson.is_some() || self.geoparquet.is_some() {
+            true => Some(Grid::open(&Dataset::open(&self.grid_file)?,
+                self.lon_convention)?),
+            false => None,
+        };
+
+        let mut geoparquet = match &self.geoparquet {
+            Some(path) => Some(GeoParquetWriter::new(
+                std::fs::File::create(path)?, 8192)?),
+            None => None,
+        };
+
+        let mut geojson = match (&self.geojson, self.geojson_per_shape) {
+            (Some(path), false) => Some(GeoJsonWriter::new(
+                ncproj_rs::open_output(&Some(path.clone()), false)?)?),
+            _ => None,
+        };
+
+        // entries of each shape are held to write per-shape collections
+        let mut shape_entries: BTreeMap<String, Vec<(usize, usize, f64)>> =
+            BTreeMap::new();
 
-                // parse shape bounds and centroid
-                let multipolygon: MultiPolygon<f64> = shape.into();
-                let polygon = multipolygon.into_iter().next().unwrap();
-                let point = polygon.centroid().unwrap();
+        // write index entries as they are computed
+        let mut writer = IndexWriter::new(
+            ncproj_rs::open_output(&self.output, false)?, self.format)?;
+        for entry in entries {
+            let entry = entry?;
+            writer.write(entry.x, entry.y, &entry.id, entry.weight)?;
+
+            if let Some(grid) = &grid {
+                let cell = grid.cell(entry.x, entry.y);
+                if let Some(geoparquet) = geoparquet.as_mut() {
+                    geoparquet.write(entry.x, entry.y, &entry.id,
+                        entry.weight, &cell)?;
+                }
 
-                // parse record metadata
-                let statefp = parse_field(&record, "STATEFP10")?;
-                let countyfp = parse_field(&record, "COUNTYFP10")?;
+                if let Some(geojson) = geojson.as_mut() {
+                    geojson.write(entry.x, entry.y, &entry.id,
+                        entry.weight, &cell)?;
+                }
+            }
 
-                shapes.insert(format!("G{}0{}0", statefp, countyfp),
-                    (point, polygon));
+            if self.geojson_per_shape {
+                shape_entries.entry(entry.id.to_string())
+                    .or_insert_with(Vec::new)
+                    .push((entry.x, entry.y, entry.weight));
             }
         }
-        
-        // open netcdf grid_file
-        let reader = netcdf::open(&self.grid_file)?;
-
-        // read netcdf dimension values
-        let longitudes = crate::get_netcdf_values::<f64>(&reader, "lon")?;
-        let latitudes = crate::get_netcdf_values::<f64>(&reader, "lat")?;
-
-        // label netcdf indices with corresponding shape
-        let latitude_delta = latitudes[1] - latitudes[0];
-        let longitude_delta = longitudes[1] - longitudes[0];
-
-        let mut shape_index = Vec::new();
-        for _ in 0..longitudes.len() {
-            let mut vec = Vec::new();
-            for _ in 0..latitudes.len() {
-                vec.push("".to_string());
-            }
 
-            shape_index.push(vec);
+        writer.flush()?;
+        if let Some(geoparquet) = geoparquet.as_mut() {
+            geoparquet.close()?;
         }
 
-        let (index_tx, index_rx):
-            (Sender<(usize, usize)>, Receiver<(usize, usize)>) =
-                crossbeam_channel::unbounded();
-        let (latitudes, longitudes, shapes) = 
-            (Arc::new(latitudes), Arc::new(longitudes), Arc::new(shapes));
-
-        let mut handles = Vec::new();
-        for _ in 0..self.thread_count {
-            let (buffer_size, index_rx, latitudes, longitudes, shapes) =
-                (self.buffer_size.clone(), index_rx.clone(),
-                    latitudes.clone(), longitudes.clone(), shapes.clone());
-
-            let handle = std::thread::spawn(move || {
-                let mut buffer: Vec<(f64, &str, &Polygon<f64>)> = Vec::new();
-                for (i, j) in index_rx.iter() {
-                    // identify longitude and latitude of index
-                    let (longitude, latitude) =
-                        (longitudes[i] - 360.0, latitudes[j]);
-                    //let index_point = Point::new(longitude, latitude);
-                    let index_polygon = Polygon::new(
-                        LineString::from(vec![(longitude, latitude),
-                            (longitude + longitude_delta, latitude),
-                            (longitude + longitude_delta,
-                                latitude + latitude_delta), 
-                            (longitude, latitude + latitude_delta),
-                            (longitude, latitude)]),
-                        vec![]);
-                    let index_point = index_polygon.centroid().unwrap();
-
-                    // identify closest shapes by centroid
-                    for (k, (point, polygon)) in shapes.iter() {
-                        // compute distance
-                        let distance = 
-                            point.euclidean_distance(&index_point);
-
-                        // identify ordered buffer location
-                        let mut index = buffer.len();
-                        while index != 0 && distance < buffer[index-1].0 {
-                            index -= 1;
-                        }
-
-                        // insert into buffer at index
-                        if index < buffer_size {
-                            buffer.insert(index, (distance, k, polygon));
-                        }
-
-                        if buffer.len() > buffer_size {
-                            buffer.pop();
-                        }
-                    }
-
-                    // compute 'contains'
-                    for (_, k, polygon) in buffer.iter() {
-                        if polygon.intersects(&index_polygon)
-                                || index_polygon.contains(*polygon)
-                                || polygon.contains(&index_polygon) {
-                            println!("{} {} {}", i, j, k);
-                        }
-                    }
-
-                    buffer.clear();
-                }
-            });
-
-            handles.push(handle);
+        if let Some(geojson) = geojson.as_mut() {
+            geojson.close()?;
         }
 
-        // send indices down channel
-        for i in 0..longitudes.len() {
-            for j in 0..latitudes.len() {
-                index_tx.send((i, j))?;
+        // write per-shape collections named by a file name safe shape id
+        //  - ids sanitized to a name in use (e.g. 'a/b' and 'a_b') are
+        //  suffixed with a counter in id order
+        if let (Some(directory), Some(grid)) = (&self.geojson, &grid) {
+            if self.geojson_per_shape {
+                std::fs::create_dir_all(directory)?;
             }
-        }
 
-        // wait until all threads have finished
-        drop(index_tx);
-        for handle in handles {
-            if let Err(e) = handle.join() {
-                return Err(format!("failed to join handle: {:?}", e).into());
+            let mut names = HashSet::new();
+            for (id, entries) in shape_entries.iter() {
+                let sanitized: String = id.chars()
+                    .map(|x| match x.is_ascii_alphanumeric() || x == '-' {
+                        true => x,
+                        false => '_',
+                    }).collect();
+
+                let mut name = sanitized.clone();
+                let mut count = 1;
+                while !names.insert(name.to_lowercase()) {
+                    count += 1;
+                    name = format!("{}_{}", sanitized, count);
+                }
+
+                let path = directory.join(format!("{}.geojson", name));
+                let mut geojson = GeoJsonWriter::new(
+                    ncproj_rs::open_output(&Some(path), false)?)?;
+                for (x, y, weight) in entries.iter() {
+                    geojson.write(*x, *y, id, *weight, &grid.cell(*x, *y))?;
+                }
+
+                geojson.close()?;
             }
         }
 
         Ok(())
     }
 }
-
-fn parse_field(record: &HashMap<String, FieldValue>, name: &str) -> Result<String, Box<dyn Error>> {
-    match record.get(name) {
-        Some(value) => match value {
-            FieldValue::Character(Some(id)) => Ok(id.to_string()),
-            x => Err(format!("unsupported field type: {}", x).into()),
-        },
-        None => Err("failed to identify shape id".into()),
-    }
-}
@@ -0,0 +1,114 @@
+use structopt::StructOpt;
+
+use ncproj_rs::dataset::Dataset;
+
+use std::collections::HashMap;
+use std::error::Error;
+use std::path::PathBuf;
+
+#[derive(StructOpt)]
+pub struct IndexStats {
+    // netcdf file used to report the fraction of grid cells covered
+    #[structopt(short = "g", long = "grid-file", parse(from_os_str))]
+    grid_file: Option<PathBuf>,
+
+    // field used as the shape id when a shape file is provided
+    #[structopt(short = "i", long = "id-field")]
+    id_field: Option<String>,
+
+    #[structopt(parse(from_os_str), index = 1)]
+    index_file: PathBuf,
+
+    // layer to read from multi-layer shape files (geopackage)
+    #[structopt(short = "l", long = "layer")]
+    layer: Option<String>,
+
+    // shape file used to report shapes with zero cells - which are
+    //  absent from the index
+    #[structopt(short = "s", long = "shape-file", parse(from_os_str))]
+    shape_file: Option<PathBuf>,
+}
+
+impl IndexStats {
+    pub fn execute(&self) -> Result<(), Box<dyn Error>> {
+        let shapes = ncproj_rs::format::read_index(&self.index_file)?;
+
+        // compute cells per shape and distinct cells over all shapes
+        let mut counts: Vec<usize> = shapes.values()
+            .map(|x| x.len()).collect();
+        counts.sort_unstable();
+
+        let mut cells: HashMap<(usize, usize), usize> = HashMap::new();
+        for (x, y, _) in shapes.values().flat_map(|x| x.iter()) {
+            *cells.entry((*x, *y)).or_insert(0) += 1;
+        }
+        let entry_count: usize = counts.iter().sum();
+
+        println!("shapes = {}", shapes.len());
+        println!("entries = {}", entry_count);
+
+        // print cells per shape distribution
+        if let (Some(min), Some(max)) = (counts.first(), counts.last()) {
+            let median = match counts.len() % 2 {
+                0 => (counts[counts.len() / 2 - 1]
+                    + counts[counts.len() / 2]) as f64 / 2.0,
+                _ => counts[counts.len() / 2] as f64,
+            };
+
+            println!("cells per shape:");
+            println!("  min = {}", min);
+            println!("  median = {}", median);
+            println!("  max = {}", max);
+            println!("  mean = {:.3}",
+                entry_count as f64 / counts.len() as f64);
+
+            // histogram over power of two bins (1, 2-3, 4-7, ..)
+            println!("histogram:");
+            let mut low = 1;
+            while low <= *max {
+                let high = low * 2 - 1;
+                let count = counts.iter()
+                    .filter(|x| **x >= low && **x <= high).count();
+                let label = match low == high {
+                    true => format!("{}", low),
+                    false => format!("{}-{}", low, high),
+                };
+
+                println!("  {:>12} = {}", label, count);
+                low *= 2;
+            }
+        }
+
+        // print cell coverage
+        println!("coverage:");
+        println!("  cells = {}", cells.len());
+        println!("  shared cells = {}",
+            cells.values().filter(|x| **x > 1).count());
+
+        if let Some(grid_file) = &self.grid_file {
+            let reader = Dataset::open(grid_file)?;
+            let (y_len, x_len) = ncproj_rs::grid::read_dimensions(&reader)?;
+            let fraction = match x_len * y_len {
+                0 => 0.0,
+                x => cells.len() as f64 / x as f64,
+            };
+
+            println!("  grid cells = {}", x_len * y_len);
+            println!("  fraction = {:.3}", fraction);
+        }
+
+        // print shapes with zero cells
+        if let Some(shape_file) = &self.shape_file {
+            let ids: Vec<String> = ncproj_rs::shape::read_shapes(shape_file,
+                    &self.id_field, &self.layer, &[], None)?.into_iter()
+                .map(|x| x.0).filter(|x| !shapes.contains_key(x)).collect();
+
+            println!("zero cell shapes = {}", ids.len());
+            for id in ids.iter() {
+                println!("  {}", id);
+            }
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,882 @@
+use crossbeam_channel::{Receiver, Sender};
+use geo::algorithm::area::Area;
+use geo::algorithm::bounding_rect::BoundingRect;
+use geo::algorithm::centroid::Centroid;
+use geo::algorithm::map_coords::MapCoords;
+use geo_types::{Coordinate, LineString, MultiLineString, MultiPoint,
+    MultiPolygon, Point, Polygon, Rect};
+use indicatif::ProgressBar;
+use proj::Proj;
+use rstar::{AABB, RTree, RTreeObject};
+
+use crate::dataset::Dataset;
+use crate::grid::{Distance, Grid, Interp, LonConvention};
+use crate::shape::{Filter, Shape};
+
+use std::collections::{BTreeMap, HashSet};
+use std::error::Error;
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::sync::Arc;
+use std::thread::JoinHandle;
+
+// grid cell <x, y> covering a fraction of the shape id - the fraction
+//  of the cell covered by polygons or of line length or points within it
+#[derive(Clone, Debug, PartialEq)]
+pub struct IndexEntry {
+    pub id: String,
+    pub weight: f64,
+    pub x: usize,
+    pub y: usize,
+}
+
+// assignment of shapes without intersecting cells
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Fallback {
+    Nearest,
+    None,
+}
+
+impl FromStr for Fallback {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "nearest" => Ok(Fallback::Nearest),
+            "none" => Ok(Fallback::None),
+            x => Err(format!("unsupported fallback '{}'", x)),
+        }
+    }
+}
+
+// builder computing the fractional coverage of grid cells by shapes
+pub struct Indexer {
+    bbox: Option<[f64; 4]>,
+    buffer: Option<f64>,
+    dissolve_by: Option<String>,
+    distance: Distance,
+    fallback: Fallback,
+    filters: Vec<Filter>,
+    grid_file: PathBuf,
+    id_field: Option<String>,
+    interp: Interp,
+    layer: Option<String>,
+    lon_convention: LonConvention,
+    quiet: bool,
+    shape_file: PathBuf,
+    thread_count: u8,
+}
+
+impl Indexer {
+    pub fn new(shape_file: PathBuf, grid_file: PathBuf) -> Indexer {
+        Indexer {
+            bbox: None,
+            buffer: None,
+            dissolve_by: None,
+            distance: Distance::Haversine,
+            fallback: Fallback::None,
+            filters: Vec::new(),
+            grid_file: grid_file,
+            id_field: None,
+            interp: Interp::Nearest,
+            layer: None,
+            lon_convention: LonConvention::Auto,
+            quiet: true,
+            shape_file: shape_file,
+            thread_count: crate::default_thread_count(),
+        }
+    }
+
+    // (min lon, min lat, max lon, max lat) box limiting indexed cells
+    //  - shapes crossing the box are only indexed over cells within it
+    pub fn bbox(mut self, bbox: Option<[f64; 4]>) -> Indexer {
+        self.bbox = bbox;
+        self
+    }
+
+    // distance in kilometers expanding shapes before intersection (e.g.
+    //  coastal shapes whose land cells sit offshore of coarse grids)
+    pub fn buffer(mut self, buffer: Option<f64>) -> Indexer {
+        self.buffer = buffer;
+        self
+    }
+
+    // attribute dissolving shapes - shapes sharing a value are indexed
+    //  as one shape identified by the value
+    pub fn dissolve_by(mut self, dissolve_by: Option<String>) -> Indexer {
+        self.dissolve_by = dissolve_by;
+        self
+    }
+
+    // measure ranking cells nearest shape centroids - planar distances
+    //  suit projected grids
+    pub fn distance(mut self, distance: Distance) -> Indexer {
+        self.distance = distance;
+        self
+    }
+
+    // assign shapes without intersecting cells (e.g. small shapes lost to
+    //  precision) the cell nearest their centroid rather than no cells
+    pub fn fallback(mut self, fallback: Fallback) -> Indexer {
+        self.fallback = fallback;
+        self
+    }
+
+    // attribute filters shapes must all match to be indexed
+    pub fn filters(mut self, filters: Vec<Filter>) -> Indexer {
+        self.filters = filters;
+        self
+    }
+
+    // field used as the shape id (defaults depend on the shape format)
+    pub fn id_field(mut self, id_field: Option<String>) -> Indexer {
+        self.id_field = id_field;
+        self
+    }
+
+    // assign shapes smaller than a cell to the cells around their
+    //  centroid by bilinear weights rather than by coverage
+    pub fn interp(mut self, interp: Interp) -> Indexer {
+        self.interp = interp;
+        self
+    }
+
+    // layer to read from multi-layer inputs (geopackage)
+    pub fn layer(mut self, layer: Option<String>) -> Indexer {
+        self.layer = layer;
+        self
+    }
+
+    pub fn lon_convention(mut self, lon_convention: LonConvention)
+            -> Indexer {
+        self.lon_convention = lon_convention;
+        self
+    }
+
+    // suppress progress reporting over grid cells and reporting of
+    //  shapes without cells on stderr
+    pub fn quiet(mut self, quiet: bool) -> Indexer {
+        self.quiet = quiet;
+        self
+    }
+
+    pub fn thread_count(mut self, thread_count: u8) -> Indexer {
+        self.thread_count = thread_count;
+        self
+    }
+
+    // start computing index entries - returned in no particular order
+    pub fn build(&self) -> Result<IndexIter, Box<dyn Error>> {
+        // open netcdf grid_file
+        let reader = Dataset::open(&self.grid_file)?;
+
+        // initialize reprojection from the shape crs into wgs84 - grid
+        //  cells are defined by their lat / lon coordinates regardless of
+        //  the grid mapping and shapes without a crs are assumed wgs84
+        let (projection, geographic) = match crate::shape::read_crs(
+                &self.shape_file, &self.layer)? {
+            Some(shape_crs) => wgs84_projection(&shape_crs)?,
+            None => (None, true),
+        };
+
+        // read netcdf grid
+        let grid = Grid::open(&reader, self.lon_convention)?;
+        let (y_len, x_len) = grid.dimensions();
+
+        // compute the [start, end) runs of cells to index
+        let (x_runs, y_runs) = match &self.bbox {
+            Some(bbox) => match grid.cell_ranges(bbox) {
+                (x_runs, y_runs) if !x_runs.is_empty()
+                        && !y_runs.is_empty() => (x_runs, y_runs),
+                _ => return Err(format!("bbox {:?} does not intersect \
+                    the grid", bbox).into()),
+            },
+            None => (vec![(0, x_len)], vec![(0, y_len)]),
+        };
+
+        // limit spatially indexed shape files to the bbox or the grid
+        //  extent - unless reprojected or in 0..360 longitudes
+        let extent = match (&projection, &self.bbox, grid.extent()) {
+            (Some(_), _, _) => None,
+            (None, Some(bbox), _) => Some(*bbox),
+            (None, None, (min_x, min_y, max_x, max_y)) if max_x <= 180.0 =>
+                Some([min_x, min_y, max_x, max_y]),
+            _ => None,
+        };
+
+        // populate shapes and spatial index over shape bounds
+        let mut interpolated = Vec::new();
+        let mut shapes: Vec<(String, Shape)> = Vec::new();
+        let mut envelopes = Vec::new();
+
+        let features = match &self.dissolve_by {
+            Some(field) => crate::shape::dissolve_shapes(&self.shape_file,
+                field, &self.layer, &self.filters, extent.as_ref())?,
+            None => crate::shape::read_shapes(&self.shape_file,
+                &self.id_field, &self.layer, &self.filters, extent.as_ref())?,
+        };
+
+        for (id, shape) in features {
+            // expand shapes into buffered polygons - before reprojection
+            //  so projected coordinates are buffered in their own units
+            let shape = match self.buffer {
+                Some(distance) =>
+                    crate::buffer::buffer(&shape, distance, geographic),
+                None => shape,
+            };
+
+            let shape = match &projection {
+                Some(proj) => shape.try_map_coords(|&(x, y)|
+                    Ok(proj.convert((x, y))?))?,
+                None => shape,
+            };
+
+            // shift parts defined in 0..360 longitudes into -180..180
+            let shift = |rect: Option<Rect<f64>>| match rect {
+                Some(rect) => rect.min().x >= 180.0,
+                None => false,
+            };
+
+            let shape = match shape {
+                Shape::Lines(lines) => Shape::Lines(MultiLineString(lines.0
+                    .into_iter().map(|line| match shift(line.bounding_rect()) {
+                        true => line.map_coords(|&(x, y)| (x - 360.0, y)),
+                        false => line,
+                    }).collect())),
+                Shape::Points(points) => Shape::Points(MultiPoint(points.0
+                    .into_iter().map(|point| match point.x() >= 180.0 {
+                        true => Point::new(point.x() - 360.0, point.y()),
+                        false => point,
+                    }).collect())),
+                Shape::Polygons(polygons) => Shape::Polygons(MultiPolygon(
+                    polygons.0.into_iter().map(|polygon|
+                            match shift(polygon.bounding_rect()) {
+                        true => polygon.map_coords(|&(x, y)| (x - 360.0, y)),
+                        false => polygon,
+                    }).collect())),
+            };
+
+            // split lines and polygons crossing the antimeridian
+            let shape = match shape {
+                Shape::Lines(lines) =>
+                    Shape::Lines(MultiLineString(lines.0.iter()
+                        .flat_map(split_line_antimeridian).collect())),
+                Shape::Polygons(polygons) =>
+                    Shape::Polygons(MultiPolygon(polygons.0.iter()
+                        .flat_map(split_antimeridian).collect())),
+                shape => shape,
+            };
+
+            // skip shapes outside of the bbox
+            if let Some(bbox) = &self.bbox {
+                match shape.bounding_rect() {
+                    Some(rect) if rect.max().x >= bbox[0]
+                        && rect.max().y >= bbox[1]
+                        && rect.min().x <= bbox[2]
+                        && rect.min().y <= bbox[3] => {},
+                    _ => continue,
+                }
+            }
+
+            // interpolate points and shapes smaller than a cell at their
+            //  centroid - points of multipoints are weighted equally
+            if self.interp == Interp::Bilinear {
+                let weights = match (&shape, grid.cell_area()) {
+                    (Shape::Points(points), _) =>
+                        bilinear_points(&grid, points),
+                    (Shape::Polygons(polygons), Some(cell_area))
+                            if polygons.unsigned_area() < cell_area =>
+                        match polygons.centroid() {
+                            Some(centroid) =>
+                                grid.bilinear(centroid.x(), centroid.y()),
+                            None => None,
+                        },
+                    _ => None,
+                };
+
+                if let Some(weights) = weights {
+                    for (x, y, weight) in weights {
+                        // cells outside of the bbox are not indexed
+                        if let Some(bbox) = &self.bbox {
+                            if !grid.cell_intersects(x, y, bbox) {
+                                continue;
+                            }
+                        }
+
+                        interpolated.push(IndexEntry {
+                            id: id.to_string(),
+                            weight: weight,
+                            x: x,
+                            y: y,
+                        });
+                    }
+
+                    continue;
+                }
+            }
+
+            // bound parts separately so split polygons on opposite sides
+            //  of the antimeridian are not bounded by the whole globe
+            let rects: Vec<Option<Rect<f64>>> = match &shape {
+                Shape::Lines(lines) =>
+                    lines.0.iter().map(|x| x.bounding_rect()).collect(),
+                Shape::Points(points) => points.0.iter()
+                    .map(|x| Some(Rect::new(x.0, x.0))).collect(),
+                Shape::Polygons(polygons) =>
+                    polygons.0.iter().map(|x| x.bounding_rect()).collect(),
+            };
+
+            for (k, rect) in rects.into_iter().enumerate() {
+                if let Some(rect) = rect {
+                    envelopes.push(ShapeEnvelope {
+                        envelope: AABB::from_corners(
+                            [rect.min().x, rect.min().y],
+                            [rect.max().x, rect.max().y]),
+                        index: shapes.len(),
+                        part: k,
+                    });
+                }
+            }
+
+            shapes.push((id, shape));
+        }
+
+        let rtree = RTree::bulk_load(envelopes);
+
+        let (index_tx, index_rx):
+            (Sender<(usize, usize)>, Receiver<(usize, usize)>) =
+                crossbeam_channel::unbounded();
+        let (result_tx, result_rx):
+            (Sender<IndexEntry>, Receiver<IndexEntry>) =
+                crossbeam_channel::unbounded();
+        let (grid, rtree, shapes) =
+            (Arc::new(grid), Arc::new(rtree), Arc::new(shapes));
+
+        // initialize progress reporting over grid cells
+        let run_len = |runs: &[(usize, usize)]|
+            runs.iter().map(|x| x.1 - x.0).sum::<usize>();
+        let progress_bar = crate::progress_bar(
+            (run_len(&x_runs) * run_len(&y_runs)) as u64, self.quiet);
+
+        let mut handles = Vec::new();
+        for _ in 0..self.thread_count {
+            let (bbox, grid, index_rx, progress_bar, result_tx, rtree,
+                    shapes) = (self.bbox, grid.clone(), index_rx.clone(),
+                progress_bar.clone(), result_tx.clone(), rtree.clone(),
+                shapes.clone());
+
+            let handle = std::thread::spawn(move || {
+                for (i, j) in index_rx.iter() {
+                    progress_bar.inc(1);
+
+                    // skip cells within the runs outside of the bbox
+                    if let Some(bbox) = &bbox {
+                        if !grid.cell_intersects(i, j, bbox) {
+                            continue;
+                        }
+                    }
+
+                    // compute polygon bounding the index cell
+                    let index_polygon = grid.cell(i, j);
+                    let cell_area = index_polygon.unsigned_area();
+
+                    // compute shape weights over cell parts on either side
+                    //  of the antimeridian
+                    let mut weights = BTreeMap::new();
+                    for part in split_antimeridian(&index_polygon) {
+                        let rect = match part.bounding_rect() {
+                            Some(rect) => rect,
+                            None => continue,
+                        };
+
+                        // identify candidate shape parts with intersecting
+                        //  bounds
+                        let cell_envelope = AABB::from_corners(
+                            [rect.min().x, rect.min().y],
+                            [rect.max().x, rect.max().y]);
+
+                        for shape_envelope in rtree
+                                .locate_in_envelope_intersecting(
+                                    &cell_envelope) {
+                            let shape = &shapes[shape_envelope.index].1;
+                            *weights.entry(shape_envelope.index)
+                                .or_insert(0.0) += part_weight(shape,
+                                    shape_envelope.part, &part, cell_area);
+                        }
+                    }
+
+                    // compute fractional coverage of index cell - parts
+                    //  may overlap (e.g. undissolved multipolygons)
+                    for (index, weight) in weights {
+                        if weight > 0.0 {
+                            // receiver is dropped when iteration stops
+                            let _ = result_tx.send(IndexEntry {
+                                id: shapes[index].0.to_string(),
+                                weight: weight.min(1.0),
+                                x: i,
+                                y: j,
+                            });
+                        }
+                    }
+                }
+            });
+
+            handles.push(handle);
+        }
+
+        for entry in interpolated {
+            result_tx.send(entry)?;
+        }
+
+        // send indices down channel
+        for (x_start, x_end) in x_runs.iter() {
+            for i in *x_start..*x_end {
+                for (y_start, y_end) in y_runs.iter() {
+                    for j in *y_start..*y_end {
+                        index_tx.send((i, j))?;
+                    }
+                }
+            }
+        }
+
+        Ok(IndexIter {
+            bbox: self.bbox,
+            distance: self.distance,
+            fallback: self.fallback,
+            grid: grid,
+            handles: handles,
+            ids: HashSet::new(),
+            pending: None,
+            progress_bar: progress_bar,
+            quiet: self.quiet,
+            result_rx: result_rx,
+            shapes: shapes,
+        })
+    }
+}
+
+// index entries streamed from worker threads - followed by fallback
+//  entries of shapes without cells once all threads have finished
+pub struct IndexIter {
+    bbox: Option<[f64; 4]>,
+    distance: Distance,
+    fallback: Fallback,
+    grid: Arc<Grid>,
+    handles: Vec<JoinHandle<()>>,
+    ids: HashSet<String>, // shapes with at least one entry
+    pending: Option<Vec<IndexEntry>>,
+    progress_bar: ProgressBar,
+    quiet: bool,
+    result_rx: Receiver<IndexEntry>,
+    shapes: Arc<Vec<(String, Shape)>>,
+}
+
+impl IndexIter {
+    // compute fallback entries and report shapes without cells
+    fn fallback_entries(&self) -> Vec<IndexEntry> {
+        let mut entries = Vec::new();
+        for (id, shape) in self.shapes.iter() {
+            if self.ids.contains(id) {
+                continue;
+            }
+
+            let cell = match (self.fallback, shape.centroid()) {
+                (Fallback::Nearest, Some(centroid)) =>
+                    self.grid.nearest(centroid.x(), centroid.y(),
+                        self.distance),
+                _ => None,
+            };
+
+            // nearest cells outside of the bbox are not indexed
+            let cell = match (cell, &self.bbox) {
+                (Some((x, y)), Some(bbox))
+                    if !self.grid.cell_intersects(x, y, bbox) => None,
+                (cell, _) => cell,
+            };
+
+            match cell {
+                Some((x, y)) => {
+                    if !self.quiet {
+                        eprintln!("assigned nearest cell ({}, {}) to shape \
+                            '{}' without cells", x, y, id);
+                    }
+
+                    entries.push(IndexEntry {
+                        id: id.to_string(),
+                        weight: 1.0,
+                        x: x,
+                        y: y,
+                    });
+                },
+                None if !self.quiet =>
+                    eprintln!("no assigned cells: shape '{}'", id),
+                None => {},
+            }
+        }
+
+        entries
+    }
+}
+
+impl Iterator for IndexIter {
+    type Item = Result<IndexEntry, Box<dyn Error>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Some(pending) = self.pending.as_mut() {
+            return pending.pop().map(Ok);
+        }
+
+        if let Ok(entry) = self.result_rx.recv() {
+            if !self.ids.contains(&entry.id) {
+                self.ids.insert(entry.id.to_string());
+            }
+
+            return Some(Ok(entry));
+        }
+
+        // wait until all threads have finished
+        self.progress_bar.finish();
+        for handle in self.handles.drain(..) {
+            if let Err(e) = handle.join() {
+                return Some(Err(format!(
+                    "failed to join handle: {:?}", e).into()));
+            }
+        }
+
+        let mut pending = self.fallback_entries();
+        pending.reverse();
+        self.pending = Some(pending);
+        self.next()
+    }
+}
+
+
+// bounding box of a shape part (polygon, line, or point) stored in the
+//  spatial index
+struct ShapeEnvelope {
+    envelope: AABB<[f64; 2]>,
+    index: usize,
+    part: usize,
+}
+
+impl RTreeObject for ShapeEnvelope {
+    type Envelope = AABB<[f64; 2]>;
+
+    fn envelope(&self) -> Self::Envelope {
+        self.envelope
+    }
+}
+
+// compute the weight of part k of a shape within a convex cell part
+fn part_weight(shape: &Shape, k: usize, cell: &Polygon<f64>,
+        cell_area: f64) -> f64 {
+    match shape {
+        Shape::Lines(lines) => {
+            let length: f64 = lines.0.iter().map(line_length).sum();
+            match length > 0.0 {
+                true => clip_length(&lines.0[k], cell) / length,
+                false => 0.0,
+            }
+        },
+        Shape::Points(points) => match contains(cell, &points.0[k]) {
+            true => 1.0 / points.0.len() as f64,
+            false => 0.0,
+        },
+        Shape::Polygons(polygons) =>
+            clip_area(&polygons.0[k], cell) / cell_area,
+    }
+}
+
+// compute (x, y, weight) bilinear weights of points weighted equally
+//  - unsupported (None) for curvilinear grids
+fn bilinear_points(grid: &Grid, points: &MultiPoint<f64>)
+        -> Option<Vec<(usize, usize, f64)>> {
+    let mut weights = BTreeMap::new();
+    for point in points.0.iter() {
+        for (x, y, weight) in grid.bilinear(point.x(), point.y())? {
+            *weights.entry((x, y)).or_insert(0.0) +=
+                weight / points.0.len() as f64;
+        }
+    }
+
+    Some(weights.into_iter().map(|((x, y), weight)| (x, y, weight))
+        .collect())
+}
+
+// initialize reprojection from a crs into wgs84 and determine whether
+//  the crs is geographic (lon / lat degrees) - crs definitions differ in
+//  format (e.g. 'EPSG:4326', wkt, proj strings) so they are compared by
+//  converting sample coordinates, crs equivalent to wgs84 need none and
+//  geographic crs move coordinates by at most a datum shift
+fn wgs84_projection(crs: &str)
+        -> Result<(Option<Proj>, bool), Box<dyn Error>> {
+    let projection = Proj::new_known_crs(crs, "EPSG:4326", None)
+        .map_err(|e| format!("failed to initialize reprojection: {}", e))?;
+
+    let within = |tolerance: f64| [(10.0, 10.0), (-100.0, 60.0),
+            (150.0, -40.0)].iter()
+        .all(|&(x, y)| match projection.convert((x, y)) {
+            Ok((lon, lat)) => (lon - x).abs() < tolerance
+                && (lat - y).abs() < tolerance,
+            Err(_) => false,
+        });
+
+    match (within(1e-9), within(0.1)) {
+        (true, _) => Ok((None, true)),
+        (false, geographic) => Ok((Some(projection), geographic)),
+    }
+}
+
+// split a polygon crossing the antimeridian into parts within -180..180
+//  - polygons spanning over 180 degrees of longitude are assumed to wrap
+//  (e.g. rings alternating between 179 and -179)
+fn split_antimeridian(polygon: &Polygon<f64>) -> Vec<Polygon<f64>> {
+    let polygon = match polygon.bounding_rect() {
+        Some(rect) if rect.max().x - rect.min().x > 180.0 =>
+            polygon.map_coords(|&(x, y)| match x < 0.0 {
+                true => (x + 360.0, y),
+                false => (x, y),
+            }),
+        Some(_) => polygon.clone(),
+        None => return Vec::new(),
+    };
+
+    let rect = match polygon.bounding_rect() {
+        Some(rect) if rect.min().x < -180.0 || rect.max().x > 180.0 => rect,
+        _ => return vec![polygon],
+    };
+
+    // clip to each 360 degree band and shift it into -180..180
+    let (min_y, max_y) = (rect.min().y - 1.0, rect.max().y + 1.0);
+    let mut parts = Vec::new();
+    for offset in [-360.0, 0.0, 360.0].iter() {
+        let (min_x, max_x) = (offset - 180.0, offset + 180.0);
+        let band = Polygon::new(LineString::from(vec![(min_x, min_y),
+            (max_x, min_y), (max_x, max_y), (min_x, max_y),
+            (min_x, min_y)]), vec![]);
+
+        let exterior = clip_ring(polygon.exterior(), &band);
+        if ring_area(&exterior) == 0.0 {
+            continue;
+        }
+
+        let interiors = polygon.interiors().iter()
+            .map(|x| clip_ring(x, &band))
+            .filter(|x| ring_area(x) != 0.0)
+            .map(|x| close_ring(x, *offset)).collect();
+        parts.push(Polygon::new(close_ring(exterior, *offset), interiors));
+    }
+
+    parts
+}
+
+// close an open ring shifted west by a longitude offset
+fn close_ring(ring: Vec<Coordinate<f64>>, offset: f64) -> LineString<f64> {
+    let mut ring: Vec<Coordinate<f64>> = ring.into_iter()
+        .map(|x| Coordinate { x: x.x - offset, y: x.y }).collect();
+    ring.push(ring[0]);
+    LineString(ring)
+}
+
+// split a line crossing the antimeridian into parts within -180..180
+//  - lines spanning over 180 degrees of longitude are assumed to wrap
+fn split_line_antimeridian(line: &LineString<f64>) -> Vec<LineString<f64>> {
+    let line = match line.bounding_rect() {
+        Some(rect) if rect.max().x - rect.min().x > 180.0 =>
+            line.map_coords(|&(x, y)| match x < 0.0 {
+                true => (x + 360.0, y),
+                false => (x, y),
+            }),
+        Some(_) => line.clone(),
+        None => return Vec::new(),
+    };
+
+    match line.bounding_rect() {
+        Some(rect) if rect.min().x < -180.0 || rect.max().x > 180.0 => {},
+        _ => return vec![line],
+    }
+
+    // clip segments to each 360 degree band and shift them into -180..180
+    let mut parts = Vec::new();
+    for offset in [-360.0, 0.0, 360.0].iter() {
+        let (min_x, max_x) = (offset - 180.0, offset + 180.0);
+        let mut part: Vec<Coordinate<f64>> = Vec::new();
+        for segment in line.0.windows(2) {
+            let (p, q) = (segment[0], segment[1]);
+            let dx = q.x - p.x;
+            let (t_min, t_max) = match dx == 0.0 {
+                true if p.x < min_x || p.x > max_x => (1.0, 0.0),
+                true => (0.0, 1.0),
+                false => {
+                    let (t0, t1) = ((min_x - p.x) / dx, (max_x - p.x) / dx);
+                    (t0.min(t1).max(0.0), t0.max(t1).min(1.0))
+                },
+            };
+
+            // segment ends are kept exact so consecutive segments join
+            let point = |t: f64| match t == 1.0 {
+                true => Coordinate { x: q.x - offset, y: q.y },
+                false => Coordinate { x: p.x + t * dx - offset,
+                    y: p.y + t * (q.y - p.y) },
+            };
+
+            // start a new part where the line reenters the band
+            if t_max < t_min || part.last() != Some(&point(t_min)) {
+                if part.len() > 1 {
+                    parts.push(LineString(part));
+                }
+
+                part = Vec::new();
+            }
+
+            if t_max >= t_min {
+                if part.is_empty() {
+                    part.push(point(t_min));
+                }
+
+                part.push(point(t_max));
+            }
+        }
+
+        if part.len() > 1 {
+            parts.push(LineString(part));
+        }
+    }
+
+    parts
+}
+
+// compute the area of a polygon clipped to a convex cell
+fn clip_area(polygon: &Polygon<f64>, cell: &Polygon<f64>) -> f64 {
+    let mut area = ring_area(&clip_ring(polygon.exterior(), cell)).abs();
+    for interior in polygon.interiors() {
+        area -= ring_area(&clip_ring(interior, cell)).abs();
+    }
+
+    area.max(0.0)
+}
+
+// clip a ring to a convex cell (sutherland-hodgman)
+fn clip_ring(ring: &LineString<f64>, cell: &Polygon<f64>)
+        -> Vec<Coordinate<f64>> {
+    let edges = cell_edges(cell);
+
+    let mut output = ring.0[..ring.0.len().saturating_sub(1)].to_vec();
+    for k in 0..edges.len() {
+        if output.is_empty() {
+            break;
+        }
+
+        let (a, b) = (edges[k], edges[(k + 1) % edges.len()]);
+        let input = std::mem::replace(&mut output, Vec::new());
+
+        // retain portions of the ring on the inside of edge a -> b
+        for l in 0..input.len() {
+            let (p, q) = (input[(l + input.len() - 1) % input.len()], input[l]);
+            let (p_side, q_side) = (cross(&a, &b, &p), cross(&a, &b, &q));
+
+            if q_side >= 0.0 {
+                if p_side < 0.0 {
+                    output.push(intersection(&p, &q, p_side, q_side));
+                }
+
+                output.push(q);
+            } else if p_side >= 0.0 {
+                output.push(intersection(&p, &q, p_side, q_side));
+            }
+        }
+    }
+
+    output
+}
+
+// compute the length of a line within a convex cell (cyrus-beck)
+fn clip_length(line: &LineString<f64>, cell: &Polygon<f64>) -> f64 {
+    let edges = cell_edges(cell);
+
+    let mut length = 0.0;
+    for segment in line.0.windows(2) {
+        let (p, q) = (segment[0], segment[1]);
+
+        // narrow the [t_min, t_max] portion of segment p -> q on the
+        //  inside of each edge
+        let (mut t_min, mut t_max) = (0.0f64, 1.0f64);
+        for k in 0..edges.len() {
+            let (a, b) = (edges[k], edges[(k + 1) % edges.len()]);
+            let (p_side, q_side) = (cross(&a, &b, &p), cross(&a, &b, &q));
+
+            if p_side < 0.0 && q_side < 0.0 {
+                t_max = t_min;
+                break;
+            } else if p_side < 0.0 {
+                t_min = t_min.max(p_side / (p_side - q_side));
+            } else if q_side < 0.0 {
+                t_max = t_max.min(p_side / (p_side - q_side));
+            }
+        }
+
+        if t_max > t_min {
+            length += (t_max - t_min) * (q.x - p.x).hypot(q.y - p.y);
+        }
+    }
+
+    length
+}
+
+// compute the length of a line
+fn line_length(line: &LineString<f64>) -> f64 {
+    line.0.windows(2)
+        .map(|x| (x[1].x - x[0].x).hypot(x[1].y - x[0].y)).sum()
+}
+
+// test whether a point falls within a convex cell - cells are half-open,
+//  containing points on their west / south (min) edges but not on their
+//  east / north (max) edges so points on shared edges fall in one cell
+fn contains(cell: &Polygon<f64>, point: &Point<f64>) -> bool {
+    let edges = cell_edges(cell);
+    (0..edges.len()).all(|k| {
+        let (a, b) = (&edges[k], &edges[(k + 1) % edges.len()]);
+        match cross(a, b, &point.0) {
+            x if x > 0.0 => true,
+            // outward normals of min edges point west (or due south)
+            x if x == 0.0 => b.y - a.y < 0.0
+                || (b.y == a.y && b.x - a.x > 0.0),
+            _ => false,
+        }
+    })
+}
+
+// compute the open ring of cell vertices traversed counterclockwise
+fn cell_edges(cell: &Polygon<f64>) -> Vec<Coordinate<f64>> {
+    let exterior = &cell.exterior().0;
+    let mut edges = exterior[..exterior.len() - 1].to_vec();
+    if ring_area(&edges) < 0.0 {
+        edges.reverse();
+    }
+
+    edges
+}
+
+// compute which side of edge a -> b point p falls on (positive is left)
+fn cross(a: &Coordinate<f64>, b: &Coordinate<f64>,
+        p: &Coordinate<f64>) -> f64 {
+    (b.x - a.x) * (p.y - a.y) - (b.y - a.y) * (p.x - a.x)
+}
+
+// compute the point where segment p -> q crosses an edge
+fn intersection(p: &Coordinate<f64>, q: &Coordinate<f64>,
+        p_side: f64, q_side: f64) -> Coordinate<f64> {
+    let t = p_side / (p_side - q_side);
+    Coordinate { x: p.x + t * (q.x - p.x), y: p.y + t * (q.y - p.y) }
+}
+
+// compute the signed area of an open ring (shoelace)
+fn ring_area(ring: &[Coordinate<f64>]) -> f64 {
+    let mut area = 0.0;
+    for k in 0..ring.len() {
+        let (p, q) = (ring[k], ring[(k + 1) % ring.len()]);
+        area += p.x * q.y - q.x * p.y;
+    }
+
+    area / 2.0
+}
@@ -0,0 +1,103 @@
+use chrono::{TimeZone, Utc};
+use structopt::StructOpt;
+
+use ncproj_rs::dataset::{AttrValue, Dataset};
+use ncproj_rs::grid::{Grid, LonConvention};
+
+use std::error::Error;
+use std::path::PathBuf;
+
+#[derive(StructOpt)]
+pub struct Info {
+    #[structopt(parse(from_os_str), index = 1)]
+    data_file: PathBuf,
+}
+
+impl Info {
+    pub fn execute(&self) -> Result<(), Box<dyn Error>> {
+        let reader = Dataset::open(&self.data_file)?;
+
+        // print dimensions
+        println!("dimensions:");
+        for dimension in reader.dimensions() {
+            println!("  {} = {}", dimension.name(), dimension.len());
+        }
+
+        // print variables and their encoding attributes
+        println!("variables:");
+        for variable in reader.variables() {
+            let dimensions: Vec<String> = variable.dimensions().iter()
+                .map(|x| x.name()).collect();
+            println!("  {}({})", variable.name(), dimensions.join(", "));
+
+            for name in ["units", "_FillValue", "missing_value",
+                    "scale_factor", "add_offset", "valid_range", "valid_min",
+                    "valid_max"].iter() {
+                if let Some(value) = variable.attribute(name)? {
+                    println!("    {} = {}", name, format_attribute(&value));
+                }
+            }
+        }
+
+        // print time coverage
+        if reader.variable("time").is_some() {
+            let times = ncproj_rs::cf::parse_times(&reader)?;
+            if let (Some(first), Some(last)) = (times.first(), times.last()) {
+                println!("time:");
+                println!("  count = {}", times.len());
+                println!("  start = {}", Utc.timestamp(*first, 0).to_rfc3339());
+                println!("  end = {}", Utc.timestamp(*last, 0).to_rfc3339());
+            }
+        }
+
+        // print grid extent and resolution
+        let grid = Grid::open(&reader, LonConvention::Auto)?;
+        let (y_len, x_len) = grid.dimensions();
+        let (min_x, min_y, max_x, max_y) = grid.extent();
+
+        println!("grid:");
+        match &grid {
+            Grid::Curvilinear { .. } => println!("  type = curvilinear"),
+            Grid::Rectilinear { latitude_bounds, longitude_bounds, .. } => {
+                // compute the (min, mean, max) cell width along an axis
+                let widths = |bounds: &[(f64, f64)]| {
+                    let widths: Vec<f64> =
+                        bounds.iter().map(|x| (x.1 - x.0).abs()).collect();
+                    (widths.iter().cloned().fold(f64::MAX, f64::min),
+                        widths.iter().sum::<f64>() / widths.len() as f64,
+                        widths.iter().cloned().fold(f64::MIN, f64::max))
+                };
+
+                let (x_min, x_mean, x_max) = widths(longitude_bounds);
+                let (y_min, y_mean, y_max) = widths(latitude_bounds);
+                println!("  type = rectilinear");
+                println!("  resolution = {} x {}", x_mean, y_mean);
+
+                // report ranges of non-uniform spacing (e.g. gaussian)
+                if x_max - x_min > 1e-6 * x_mean
+                        || y_max - y_min > 1e-6 * y_mean {
+                    println!("  spacing = irregular ({}..{} x {}..{})",
+                        x_min, x_max, y_min, y_max);
+                }
+            },
+        }
+
+        println!("  dimensions = {} x {}", x_len, y_len);
+        println!("  extent = [{}, {}] x [{}, {}]", min_x, max_x, min_y, max_y);
+
+        if let Some(crs) = ncproj_rs::cf::read_grid_crs(&reader)? {
+            println!("  crs = {}", crs);
+        }
+
+        Ok(())
+    }
+}
+
+fn format_attribute(value: &AttrValue) -> String {
+    match value {
+        AttrValue::Array(x) => format!("{:?}", x),
+        AttrValue::Double(x) => x.to_string(),
+        AttrValue::Int(x) => x.to_string(),
+        AttrValue::Str(x) => format!("\"{}\"", x),
+    }
+}
@@ -0,0 +1,524 @@
+// reader for kerchunk reference files describing zarr (v2) arrays whose
+//  chunks are byte ranges of local, http, or s3 objects
+use flate2::read::{GzDecoder, ZlibDecoder};
+use serde_json::{Map, Value};
+
+use crate::dataset::AttrValue;
+
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::Path;
+
+pub struct File {
+    attributes: Vec<(String, AttrValue)>,
+    dimensions: Vec<(String, usize)>,
+    references: BTreeMap<String, Reference>,
+    variables: Vec<Variable>,
+}
+
+pub struct Variable {
+    pub attributes: Vec<(String, AttrValue)>,
+    pub chunks: Vec<usize>,
+    codecs: Vec<Codec>,
+    dimensions: Vec<String>,
+    dtype: Dtype,
+    fill_value: f64,
+    pub name: String,
+    shape: Vec<usize>,
+}
+
+enum Codec {
+    Gzip,
+    Shuffle(usize),
+    Zlib,
+    Zstd,
+}
+
+struct Dtype {
+    big_endian: bool,
+    kind: char,
+    size: usize,
+}
+
+enum Reference {
+    Inline(Vec<u8>),
+    Range(String, Option<(u64, u64)>),
+}
+
+impl File {
+    pub fn open(path: &Path) -> Result<File, Box<dyn Error>> {
+        let json: Value = serde_json::from_reader(crate::open_input(
+            &path.to_path_buf())?)?;
+
+        // version 1 files nest references under 'refs'
+        let templates = json.get("templates").and_then(|x| x.as_object())
+            .cloned().unwrap_or_default();
+        let refs = match json.get("refs").and_then(|x| x.as_object()) {
+            Some(refs) => refs.clone(),
+            None => json.as_object().cloned()
+                .ok_or("invalid kerchunk reference file")?,
+        };
+
+        let mut references = BTreeMap::new();
+        for (key, value) in refs.iter() {
+            references.insert(key.to_string(),
+                parse_reference(value, &templates)?);
+        }
+
+        // parse variable metadata from '.zarray' and '.zattrs' keys
+        let mut variables = Vec::new();
+        let mut dimensions: Vec<(String, usize)> = Vec::new();
+        for key in references.keys() {
+            let name = match key.strip_suffix("/.zarray") {
+                Some(name) => name.to_string(),
+                None => continue,
+            };
+
+            let zarray = read_json(&references[key])?;
+            let zattrs = match references.get(&format!("{}/.zattrs", name)) {
+                Some(reference) => read_json(reference)?,
+                None => Value::Object(Map::new()),
+            };
+
+            let variable = parse_variable(name, &zarray, &zattrs)?;
+            for (dimension, len) in variable.dimensions.iter()
+                    .zip(variable.shape.iter()) {
+                if !dimensions.iter().any(|x| &x.0 == dimension) {
+                    dimensions.push((dimension.to_string(), *len));
+                }
+            }
+
+            variables.push(variable);
+        }
+
+        // parse global attributes from the root '.zattrs'
+        let mut attributes = Vec::new();
+        if let Some(reference) = references.get(".zattrs") {
+            if let Some(zattrs) = read_json(reference)?.as_object() {
+                for (key, value) in zattrs.iter() {
+                    if let Some(value) = attribute_value(value) {
+                        attributes.push((key.to_string(), value));
+                    }
+                }
+            }
+        }
+
+        Ok(File {
+            attributes: attributes,
+            dimensions: dimensions,
+            references: references,
+            variables: variables,
+        })
+    }
+
+    pub fn attributes(&self) -> &[(String, AttrValue)] {
+        &self.attributes
+    }
+
+    pub fn dimensions(&self) -> &[(String, usize)] {
+        &self.dimensions
+    }
+
+    pub fn variables(&self) -> &[Variable] {
+        &self.variables
+    }
+
+    // (name, length) of each variable dimension
+    pub fn variable_dimensions(&self, variable: &Variable)
+            -> Vec<(String, usize)> {
+        variable.dimensions.iter().cloned()
+            .zip(variable.shape.iter().cloned()).collect()
+    }
+
+    // read a hyperslab of values in row-major order
+    pub fn read(&self, variable: &Variable, start: &[usize],
+            count: &[usize]) -> Result<Vec<f64>, Box<dyn Error>> {
+        let rank = variable.shape.len();
+        if start.len() != rank || count.len() != rank {
+            return Err(format!("invalid hyperslab rank for variable '{}'",
+                variable.name).into());
+        }
+
+        let len: usize = count.iter().product();
+        let mut values = vec![variable.fill_value; len];
+        if len == 0 {
+            return Ok(values);
+        }
+
+        // identify the range of chunk indices covering the hyperslab
+        let first: Vec<usize> = (0..rank)
+            .map(|k| start[k] / variable.chunks[k]).collect();
+        let last: Vec<usize> = (0..rank)
+            .map(|k| (start[k] + count[k] - 1) / variable.chunks[k])
+            .collect();
+
+        let mut chunk_index = first.clone();
+        loop {
+            let key = match rank {
+                0 => format!("{}/0", variable.name),
+                _ => format!("{}/{}", variable.name, chunk_index.iter()
+                    .map(|x| x.to_string()).collect::<Vec<String>>()
+                    .join(".")),
+            };
+
+            // missing chunks are entirely fill values
+            if let Some(reference) = self.references.get(&key) {
+                let bytes = read_reference(reference)?;
+                let chunk = decode_chunk(variable, &bytes)?;
+                copy_chunk(variable, &chunk_index, &chunk,
+                    start, count, &mut values);
+            }
+
+            // advance chunk indices
+            let mut k = rank;
+            loop {
+                if k == 0 {
+                    return Ok(values);
+                }
+
+                k -= 1;
+                chunk_index[k] += 1;
+                if chunk_index[k] <= last[k] {
+                    break;
+                }
+
+                chunk_index[k] = first[k];
+            }
+        }
+    }
+}
+
+// copy the portion of a chunk within the hyperslab into values
+fn copy_chunk(variable: &Variable, chunk_index: &[usize], chunk: &[f64],
+        start: &[usize], count: &[usize], values: &mut [f64]) {
+    let rank = variable.shape.len();
+    if rank == 0 {
+        values[0] = chunk[0];
+        return;
+    }
+
+    // compute the intersection of chunk and hyperslab
+    let mut lower = Vec::new();
+    let mut upper = Vec::new();
+    for k in 0..rank {
+        let chunk_start = chunk_index[k] * variable.chunks[k];
+        lower.push(std::cmp::max(chunk_start, start[k]));
+        upper.push(std::cmp::min(chunk_start + variable.chunks[k],
+            start[k] + count[k]));
+    }
+
+    let mut index = lower.clone();
+    loop {
+        let (mut chunk_offset, mut value_offset) = (0, 0);
+        for k in 0..rank {
+            chunk_offset = chunk_offset * variable.chunks[k]
+                + index[k] - chunk_index[k] * variable.chunks[k];
+            value_offset = value_offset * count[k] + index[k] - start[k];
+        }
+
+        values[value_offset] = chunk[chunk_offset];
+
+        let mut k = rank;
+        loop {
+            if k == 0 {
+                return;
+            }
+
+            k -= 1;
+            index[k] += 1;
+            if index[k] < upper[k] {
+                break;
+            }
+
+            index[k] = lower[k];
+        }
+    }
+}
+
+// decompress and unfilter chunk bytes into values
+fn decode_chunk(variable: &Variable, bytes: &[u8])
+        -> Result<Vec<f64>, Box<dyn Error>> {
+    let mut bytes = bytes.to_vec();
+    for codec in variable.codecs.iter().rev() {
+        bytes = match codec {
+            Codec::Gzip => {
+                let mut buf = Vec::new();
+                GzDecoder::new(&bytes[..]).read_to_end(&mut buf)?;
+                buf
+            },
+            Codec::Shuffle(size) => unshuffle(&bytes, *size),
+            Codec::Zlib => {
+                let mut buf = Vec::new();
+                ZlibDecoder::new(&bytes[..]).read_to_end(&mut buf)?;
+                buf
+            },
+            Codec::Zstd => zstd::decode_all(&bytes[..])?,
+        };
+    }
+
+    let chunk_len: usize = variable.chunks.iter().product();
+    if bytes.len() < chunk_len * variable.dtype.size {
+        return Err(format!("truncated chunk for variable '{}'",
+            variable.name).into());
+    }
+
+    bytes.chunks(variable.dtype.size).take(chunk_len)
+        .map(|x| variable.dtype.decode(x)).collect()
+}
+
+// reverse the byte shuffle filter
+fn unshuffle(bytes: &[u8], size: usize) -> Vec<u8> {
+    if size <= 1 || bytes.len() % size != 0 {
+        return bytes.to_vec();
+    }
+
+    let len = bytes.len() / size;
+    let mut buf = vec![0u8; bytes.len()];
+    for i in 0..len {
+        for j in 0..size {
+            buf[i * size + j] = bytes[j * len + i];
+        }
+    }
+
+    buf
+}
+
+impl Variable {
+    // netcdf type code of the zarr dtype
+    pub fn nc_type(&self) -> Result<u32, Box<dyn Error>> {
+        match (self.dtype.kind, self.dtype.size) {
+            ('i', 1) => Ok(1),
+            ('i', 2) => Ok(3),
+            ('i', 4) => Ok(4),
+            ('f', 4) => Ok(5),
+            ('f', 8) => Ok(6),
+            ('u', 1) => Ok(7),
+            ('u', 2) => Ok(8),
+            ('u', 4) => Ok(9),
+            ('i', 8) => Ok(10),
+            ('u', 8) => Ok(11),
+            (kind, size) => Err(format!("unsupported dtype '{}{}' of \
+                variable '{}'", kind, size, self.name).into()),
+        }
+    }
+}
+
+impl Dtype {
+    fn parse(s: &str) -> Result<Dtype, Box<dyn Error>> {
+        let mut chars = s.chars();
+        let big_endian = match chars.next() {
+            Some('>') => true,
+            Some('<') | Some('|') => false,
+            _ => return Err(format!("unsupported dtype '{}'", s).into()),
+        };
+
+        let kind = chars.next()
+            .ok_or_else(|| format!("unsupported dtype '{}'", s))?;
+        let size = chars.as_str().parse::<usize>()?;
+        match (kind, size) {
+            ('f', 4) | ('f', 8) | ('i', 1) | ('i', 2) | ('i', 4) | ('i', 8)
+                | ('u', 1) | ('u', 2) | ('u', 4) | ('u', 8) => Ok(Dtype {
+                    big_endian: big_endian,
+                    kind: kind,
+                    size: size,
+                }),
+            _ => Err(format!("unsupported dtype '{}'", s).into()),
+        }
+    }
+
+    fn decode(&self, bytes: &[u8]) -> Result<f64, Box<dyn Error>> {
+        let mut buf = [0u8; 8];
+        buf[..self.size].copy_from_slice(bytes);
+        if self.big_endian {
+            buf[..self.size].reverse();
+        }
+
+        let value = match (self.kind, self.size) {
+            ('f', 4) => f32::from_le_bytes([buf[0], buf[1], buf[2], buf[3]])
+                as f64,
+            ('f', 8) => f64::from_le_bytes(buf),
+            ('i', 1) => buf[0] as i8 as f64,
+            ('i', 2) => i16::from_le_bytes([buf[0], buf[1]]) as f64,
+            ('i', 4) => i32::from_le_bytes([buf[0], buf[1], buf[2], buf[3]])
+                as f64,
+            ('i', 8) => i64::from_le_bytes(buf) as f64,
+            ('u', 1) => buf[0] as f64,
+            ('u', 2) => u16::from_le_bytes([buf[0], buf[1]]) as f64,
+            ('u', 4) => u32::from_le_bytes([buf[0], buf[1], buf[2], buf[3]])
+                as f64,
+            ('u', 8) => u64::from_le_bytes(buf) as f64,
+            _ => return Err("unsupported dtype".into()),
+        };
+
+        Ok(value)
+    }
+}
+
+fn parse_variable(name: String, zarray: &Value, zattrs: &Value)
+        -> Result<Variable, Box<dyn Error>> {
+    let error = |field: &str| format!(
+        "invalid '{}' in variable '{}' metadata", field, name);
+
+    let shape: Vec<usize> = zarray["shape"].as_array()
+        .ok_or_else(|| error("shape"))?.iter()
+        .map(|x| x.as_u64().map(|x| x as usize))
+        .collect::<Option<Vec<usize>>>().ok_or_else(|| error("shape"))?;
+    let chunks: Vec<usize> = zarray["chunks"].as_array()
+        .ok_or_else(|| error("chunks"))?.iter()
+        .map(|x| x.as_u64().map(|x| x as usize))
+        .collect::<Option<Vec<usize>>>().ok_or_else(|| error("chunks"))?;
+    if chunks.len() != shape.len() || chunks.contains(&0) {
+        return Err(error("chunks").into());
+    }
+
+    if zarray["order"].as_str().unwrap_or("C") != "C" {
+        return Err(error("order").into());
+    }
+
+    let dtype = Dtype::parse(zarray["dtype"].as_str()
+        .ok_or_else(|| error("dtype"))?)?;
+
+    // fill values may be encoded as strings ('NaN', 'Infinity')
+    let fill_value = match &zarray["fill_value"] {
+        Value::Number(x) => x.as_f64().unwrap_or(f64::NAN),
+        Value::String(x) => x.parse::<f64>().unwrap_or(f64::NAN),
+        _ => f64::NAN,
+    };
+
+    // filters are applied before the compressor when encoding
+    let mut codecs = Vec::new();
+    let filters = zarray["filters"].as_array().cloned().unwrap_or_default();
+    for codec in filters.iter().chain(std::iter::once(&zarray["compressor"])) {
+        if codec.is_null() {
+            continue;
+        }
+
+        codecs.push(match codec["id"].as_str() {
+            Some("shuffle") => Codec::Shuffle(codec["elementsize"]
+                .as_u64().unwrap_or(dtype.size as u64) as usize),
+            Some("gzip") => Codec::Gzip,
+            Some("zlib") => Codec::Zlib,
+            Some("zstd") => Codec::Zstd,
+            x => return Err(format!("unsupported codec '{:?}' in \
+                variable '{}'", x, name).into()),
+        });
+    }
+
+    // parse attributes - dimension names are stored by xarray
+    let mut attributes = Vec::new();
+    let mut dimensions = Vec::new();
+    if let Some(zattrs) = zattrs.as_object() {
+        for (key, value) in zattrs.iter() {
+            if key == "_ARRAY_DIMENSIONS" {
+                dimensions = value.as_array()
+                    .ok_or_else(|| error(key))?.iter()
+                    .map(|x| x.as_str().map(|x| x.to_string()))
+                    .collect::<Option<Vec<String>>>()
+                    .ok_or_else(|| error(key))?;
+            } else if let Some(value) = attribute_value(value) {
+                attributes.push((key.to_string(), value));
+            }
+        }
+    }
+
+    if dimensions.len() != shape.len() {
+        dimensions = (0..shape.len())
+            .map(|k| format!("{}_dim{}", name, k)).collect();
+    }
+
+    Ok(Variable {
+        attributes: attributes,
+        chunks: chunks,
+        codecs: codecs,
+        dimensions: dimensions,
+        dtype: dtype,
+        fill_value: fill_value,
+        name: name,
+        shape: shape,
+    })
+}
+
+fn attribute_value(value: &Value) -> Option<AttrValue> {
+    match value {
+        Value::String(x) => Some(AttrValue::Str(x.to_string())),
+        Value::Number(x) => match x.as_i64() {
+            Some(x) => Some(AttrValue::Int(x)),
+            None => x.as_f64().map(AttrValue::Double),
+        },
+        Value::Array(x) => x.iter().map(|x| x.as_f64())
+            .collect::<Option<Vec<f64>>>().map(AttrValue::Array),
+        _ => None,
+    }
+}
+
+fn parse_reference(value: &Value, templates: &Map<String, Value>)
+        -> Result<Reference, Box<dyn Error>> {
+    match value {
+        // inline data is either base64 encoded or raw text (json metadata)
+        Value::String(x) => match x.strip_prefix("base64:") {
+            Some(x) => Ok(Reference::Inline(base64::decode(x)?)),
+            None => Ok(Reference::Inline(x.as_bytes().to_vec())),
+        },
+        Value::Array(x) => {
+            let mut url = x.get(0).and_then(|x| x.as_str())
+                .ok_or("invalid kerchunk reference")?.to_string();
+            for (name, template) in templates.iter() {
+                if let Some(template) = template.as_str() {
+                    url = url.replace(&format!("{{{{{}}}}}", name), template);
+                }
+            }
+
+            let range = match (x.get(1).and_then(|x| x.as_u64()),
+                    x.get(2).and_then(|x| x.as_u64())) {
+                (Some(offset), Some(len)) => Some((offset, len)),
+                _ => None,
+            };
+
+            Ok(Reference::Range(url, range))
+        },
+        _ => Err("invalid kerchunk reference".into()),
+    }
+}
+
+fn read_json(reference: &Reference) -> Result<Value, Box<dyn Error>> {
+    Ok(serde_json::from_slice(&read_reference(reference)?)?)
+}
+
+// read referenced bytes from local files or http / s3 objects
+fn read_reference(reference: &Reference) -> Result<Vec<u8>, Box<dyn Error>> {
+    let (url, range) = match reference {
+        Reference::Inline(bytes) => return Ok(bytes.clone()),
+        Reference::Range(url, range) => (url, range),
+    };
+
+    let mut buf = Vec::new();
+    if url.starts_with("http://") || url.starts_with("https://")
+            || url.starts_with("s3://") {
+        let url = match url.starts_with("s3://") {
+            true => crate::s3::object_url(url)?,
+            false => url.to_string(),
+        };
+
+        let request = ureq::get(&url);
+        let request = match range {
+            Some((offset, len)) => request.set("Range",
+                &format!("bytes={}-{}", offset, offset + len - 1)),
+            None => request,
+        };
+
+        request.call()?.into_reader().read_to_end(&mut buf)?;
+    } else {
+        let path = url.strip_prefix("file://").unwrap_or(url);
+        let mut file = std::fs::File::open(path)?;
+        match range {
+            Some((offset, len)) => {
+                file.seek(SeekFrom::Start(*offset))?;
+                file.take(*len).read_to_end(&mut buf)?;
+            },
+            None => { file.read_to_end(&mut buf)?; },
+        }
+    }
+
+    Ok(buf)
+}
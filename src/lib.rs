@@ -0,0 +1,195 @@
+use flate2::Compression;
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use indicatif::{ProgressBar, ProgressStyle};
+use ndarray::ArrayD;
+
+use std::error::Error;
+use std::io::{BufReader, BufWriter, Read, Write};
+use std::path::PathBuf;
+
+mod buffer;
+pub mod cf;
+#[cfg(feature = "netcdf3")]
+mod classic;
+pub mod dataset;
+pub mod dumper;
+pub mod error;
+pub mod ffi;
+mod fgb;
+pub mod format;
+pub mod grid;
+pub mod indexer;
+#[cfg(feature = "kerchunk")]
+mod kerchunk;
+mod overlay;
+pub mod resample;
+mod s3;
+pub mod shape;
+mod simd;
+pub mod sink;
+pub mod stat;
+pub mod units;
+pub mod writer;
+pub mod zarr;
+
+#[cfg(not(any(feature = "kerchunk", feature = "netcdf-c",
+    feature = "netcdf3")))]
+compile_error!("one of the 'kerchunk', 'netcdf-c', or 'netcdf3' \
+    features is required");
+
+pub fn get_netcdf_values(reader: &dataset::Dataset, name: &str)
+        -> Result<ArrayD<f64>, Box<dyn Error>> {
+    let variable = match reader.variable(name) {
+        Some(variable) => variable,
+        None => return Err(format!("variable {} not found", name).into()),
+    };
+
+    variable.values()
+}
+
+// expand directories into their netcdf files and glob patterns into
+//  matching paths - each expansion is sorted and urls are unchanged
+pub fn expand_paths(paths: &[PathBuf])
+        -> Result<Vec<PathBuf>, Box<dyn Error>> {
+    let mut expanded = Vec::new();
+    for path in paths.iter() {
+        if dataset::is_remote(path) {
+            expanded.push(path.clone());
+        } else if path.is_dir() {
+            let mut entries = Vec::new();
+            for entry in std::fs::read_dir(path)? {
+                let entry_path = entry?.path();
+                match entry_path.extension().and_then(|x| x.to_str()) {
+                    Some("nc") | Some("nc4") | Some("netcdf") =>
+                        entries.push(entry_path),
+                    _ => {},
+                }
+            }
+
+            entries.sort();
+            expanded.append(&mut entries);
+        } else if path.to_string_lossy().contains(|x| "*?[".contains(x)) {
+            let mut entries = Vec::new();
+            for entry in glob::glob(&path.to_string_lossy())? {
+                entries.push(entry?);
+            }
+
+            if entries.is_empty() {
+                return Err(format!("no files match '{}'",
+                    path.display()).into());
+            }
+
+            entries.sort();
+            expanded.append(&mut entries);
+        } else {
+            expanded.push(path.clone());
+        }
+    }
+
+    Ok(expanded)
+}
+
+// number of logical cpus used as the default thread count
+pub fn default_thread_count() -> u8 {
+    std::cmp::min(num_cpus::get(), u8::MAX as usize) as u8
+}
+
+// initialize a progress bar on stderr - hidden when quiet
+pub fn progress_bar(len: u64, quiet: bool) -> ProgressBar {
+    if quiet {
+        return ProgressBar::hidden();
+    }
+
+    let progress_bar = ProgressBar::new(len);
+    progress_bar.set_style(ProgressStyle::default_bar().template(
+        "{elapsed_precise} [{bar:40}] {pos}/{len} ({per_sec}, eta {eta})"));
+    progress_bar
+}
+
+// open an output file (or stdout) compressing by file extension
+//  - appended compressed data is written as an additional frame
+pub fn open_output(path: &Option<PathBuf>, append: bool)
+        -> Result<Box<dyn Write + Send>, Box<dyn Error>> {
+    let path = match path {
+        Some(path) => path,
+        None => return Ok(Box::new(BufWriter::new(std::io::stdout()))),
+    };
+
+    let file = std::fs::OpenOptions::new().append(append)
+        .create(true).truncate(!append).write(true).open(path)?;
+    let writer = BufWriter::new(file);
+    match path.extension().and_then(|x| x.to_str()) {
+        Some("gz") => Ok(Box::new(
+            GzEncoder::new(writer, Compression::default()))),
+        Some("zst") => Ok(Box::new(ZstdWriter {
+            encoder: Some(zstd::Encoder::new(writer, 0)?) })),
+        _ => Ok(Box::new(writer)),
+    }
+}
+
+// open an input file decompressing by file extension
+pub fn open_input(path: &PathBuf) -> Result<Box<dyn Read>, Box<dyn Error>> {
+    let reader = BufReader::new(std::fs::File::open(path)?);
+    match path.extension().and_then(|x| x.to_str()) {
+        Some("gz") => Ok(Box::new(GzDecoder::new(reader))),
+        Some("zst") => Ok(Box::new(zstd::Decoder::new(reader)?)),
+        _ => Ok(Box::new(reader)),
+    }
+}
+
+// parse a byte size with an optional binary unit suffix (e.g. '8G')
+pub fn parse_size(s: &str) -> Result<usize, String> {
+    let error = || format!("invalid size '{}'", s);
+
+    let size = s.trim().to_uppercase();
+    let size = size.trim_end_matches("IB").trim_end_matches('B');
+    let (digits, shift) = match size.chars().last() {
+        Some('K') => (&size[..size.len() - 1], 10),
+        Some('M') => (&size[..size.len() - 1], 20),
+        Some('G') => (&size[..size.len() - 1], 30),
+        Some('T') => (&size[..size.len() - 1], 40),
+        _ => (size, 0),
+    };
+
+    let value = digits.trim().parse::<f64>().map_err(|_| error())?;
+    if !value.is_finite() || value < 0.0 {
+        return Err(error());
+    }
+
+    Ok((value * (1u64 << shift) as f64) as usize)
+}
+
+// parse a 'key=value' pair
+pub fn parse_key_value(s: &str) -> Result<(String, String), String> {
+    match s.find('=') {
+        Some(i) if i > 0 && i + 1 < s.len() =>
+            Ok((s[..i].trim().to_string(), s[i + 1..].trim().to_string())),
+        _ => Err(format!("invalid mapping '{}' - expected 'key=value'", s)),
+    }
+}
+
+// zstd encoder which finishes the frame when dropped
+struct ZstdWriter<W: Write> {
+    encoder: Option<zstd::Encoder<'static, W>>,
+}
+
+impl<W: Write> Write for ZstdWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.encoder.as_mut().unwrap().write(buf)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.encoder.as_mut().unwrap().flush()
+    }
+}
+
+impl<W: Write> Drop for ZstdWriter<W> {
+    fn drop(&mut self) {
+        if let Some(encoder) = self.encoder.take() {
+            if let Err(e) = encoder.finish().and_then(|mut x| x.flush()) {
+                eprintln!("failed to finish zstd stream: {}", e);
+            }
+        }
+    }
+}
@@ -1,9 +1,18 @@
-use ndarray::ArrayD;
-use netcdf::File;
 use structopt::StructOpt;
 
+mod config;
+mod convert;
 mod dump;
 mod index;
+mod index_stats;
+mod info;
+mod mask;
+mod merge;
+mod rollup;
+mod subset;
+mod trend;
+mod validate;
+mod zonal;
 
 #[derive(StructOpt)]
 struct Opt {
@@ -13,32 +22,54 @@ struct Opt {
 
 #[derive(StructOpt)]
 enum Command {
+    Convert(convert::Convert),
     Dump(dump::Dump),
     Index(index::Index),
+    IndexStats(index_stats::IndexStats),
+    Info(info::Info),
+    Mask(mask::Mask),
+    Merge(merge::Merge),
+    Rollup(rollup::Rollup),
+    Subset(subset::Subset),
+    Trend(trend::Trend),
+    Validate(validate::Validate),
+    Zonal(zonal::Zonal),
 }
 
 fn main() {
-    // parse options
-    let opt = Opt::from_args();
+    // parse options - expanding arguments of a config file
+    let args = match config::expand_args(std::env::args().collect(),
+            Opt::clap()) {
+        Ok(args) => args,
+        Err(e) => exit(e),
+    };
+
+    let opt = Opt::from_iter(args);
 
     // execute subcommand
     let result = match opt.cmd {
+        Command::Convert(convert) => convert.execute(),
         Command::Dump(dump) => dump.execute(),
         Command::Index(index) => index.execute(),
+        Command::IndexStats(index_stats) => index_stats.execute(),
+        Command::Info(info) => info.execute(),
+        Command::Mask(mask) => mask.execute(),
+        Command::Merge(merge) => merge.execute(),
+        Command::Rollup(rollup) => rollup.execute(),
+        Command::Subset(subset) => subset.execute(),
+        Command::Trend(trend) => trend.execute(),
+        Command::Validate(validate) => validate.execute(),
+        Command::Zonal(zonal) => zonal.execute(),
     };
 
     // process result
     if let Err(e) = result {
-        panic!("{}", e);
+        exit(e);
     }
 }
 
-fn get_netcdf_values<T: netcdf::Numeric>(reader: &File, name: &str) 
-        -> Result<ArrayD<T>, netcdf::error::Error> {
-    let variable = match reader.variable(name) {
-        Some(variable) => variable,
-        None => return Err(format!("variable {} not found", name).into()),
-    };
-
-    variable.values::<T>(None, None)
+// print the error and exit with the code of its failure class
+fn exit(error: Box<dyn std::error::Error>) -> ! {
+    eprintln!("error: {}", error);
+    std::process::exit(ncproj_rs::error::exit_code(error.as_ref()));
 }
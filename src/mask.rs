@@ -0,0 +1,90 @@
+use structopt::StructOpt;
+
+use ncproj_rs::dataset::{AttrValue, Dataset};
+use ncproj_rs::writer::Writer;
+
+use std::error::Error;
+use std::path::PathBuf;
+
+#[derive(StructOpt)]
+pub struct Mask {
+    // netcdf file the index was computed over
+    #[structopt(parse(from_os_str), index = 2)]
+    grid_file: PathBuf,
+
+    #[structopt(parse(from_os_str), index = 1)]
+    index_file: PathBuf,
+
+    #[structopt(short = "o", long = "output", parse(from_os_str))]
+    output: PathBuf,
+}
+
+impl Mask {
+    pub fn execute(&self) -> Result<(), Box<dyn Error>> {
+        let shapes = ncproj_rs::format::read_index(&self.index_file)?;
+
+        let reader = Dataset::open(&self.grid_file)?;
+        let (y_name, x_name) =
+            ncproj_rs::grid::read_dimension_names(&reader)?;
+        let (y_len, x_len) = ncproj_rs::grid::read_dimensions(&reader)?;
+
+        // assign each cell to the shape with the largest coverage
+        let mut mask = vec![-1i32; y_len * x_len];
+        let mut weights = vec![0f32; y_len * x_len];
+        for (k, (id, indices)) in shapes.iter().enumerate() {
+            for (x, y, weight) in indices.iter() {
+                if *x >= x_len || *y >= y_len {
+                    return Err(format!("cell ({}, {}) of shape '{}' outside \
+                        of {}x{} grid", x, y, id, x_len, y_len).into());
+                }
+
+                let offset = y * x_len + x;
+                if *weight > weights[offset] {
+                    mask[offset] = k as i32;
+                    weights[offset] = *weight;
+                }
+            }
+        }
+
+        let mut writer = Writer::create(&self.output)?;
+        writer.add_dimension(&y_name, y_len)?;
+        writer.add_dimension(&x_name, x_len)?;
+        writer.add_dimension("shape", shapes.len())?;
+
+        // copy grid coordinate variables
+        for name in ["lat", "lon", "rlat", "rlon"].iter() {
+            let variable = match reader.variable(name) {
+                Some(variable) => variable,
+                None => continue,
+            };
+
+            let dimensions: Vec<String> = variable.dimensions().iter()
+                .map(|x| x.name()).collect();
+            if !dimensions.iter().all(|x| x == &y_name || x == &x_name) {
+                continue;
+            }
+
+            let dimensions: Vec<&str> =
+                dimensions.iter().map(|x| x.as_str()).collect();
+            writer.add_variable(name, &dimensions, &variable.attributes())?;
+
+            let count: Vec<usize> = variable.dimensions().iter()
+                .map(|x| x.len()).collect();
+            let values: Vec<f64> = variable.values()?.iter().cloned().collect();
+            writer.put_values(name, &values, &vec![0; count.len()], &count)?;
+        }
+
+        // write shape index of each cell and the id lookup table
+        writer.add_int_variable("shape_id", &[&y_name, &x_name], -1)?;
+        writer.add_attribute("comment", &AttrValue::Str(
+            "shape_id indexes the shape_name variable".to_string()))?;
+        writer.put_int_values("shape_id", &mask, &[0, 0], &[y_len, x_len])?;
+
+        writer.add_string_variable("shape_name", &["shape"])?;
+        for (k, id) in shapes.keys().enumerate() {
+            writer.put_string("shape_name", id, &[k])?;
+        }
+
+        Ok(())
+    }
+}
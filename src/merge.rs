@@ -0,0 +1,162 @@
+use ndarray::ArrayD;
+use structopt::StructOpt;
+
+use ncproj_rs::dataset::Dataset;
+use ncproj_rs::format::{Format, IndexWriter};
+
+use std::collections::BTreeMap;
+use std::collections::btree_map::Entry;
+use std::error::Error;
+use std::path::PathBuf;
+use std::str::FromStr;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Duplicates {
+    Error,
+    First,
+    Max,
+}
+
+impl FromStr for Duplicates {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "error" => Ok(Duplicates::Error),
+            "first" => Ok(Duplicates::First),
+            "max" => Ok(Duplicates::Max),
+            x => Err(format!("unsupported duplicate resolution '{}'", x)),
+        }
+    }
+}
+
+#[derive(StructOpt)]
+pub struct Merge {
+    // resolve duplicate (x, y, shape) entries with 'error', 'first', or
+    //  'max' (the largest weight)
+    #[structopt(short = "d", long = "duplicates", default_value = "max")]
+    duplicates: Duplicates,
+
+    #[structopt(short = "f", long = "format", default_value = "text")]
+    format: Format,
+
+    // comma-separated grid files the indexes were computed over (e.g. one
+    //  per index) - validates the grids share coordinates and grid
+    //  mapping and all cells fall within the grid
+    #[structopt(short = "g", long = "grid-files", parse(from_os_str),
+        use_delimiter = true)]
+    grid_files: Vec<PathBuf>,
+
+    #[structopt(parse(from_os_str), required = true, min_values = 2)]
+    index_files: Vec<PathBuf>,
+
+    #[structopt(short = "o", long = "output", parse(from_os_str))]
+    output: Option<PathBuf>,
+}
+
+impl Merge {
+    pub fn execute(&self) -> Result<(), Box<dyn Error>> {
+        // validate the grids of all indexes are equivalent
+        let mut grid_dimensions = None;
+        let mut reference: Option<(&PathBuf, Vec<ArrayD<f64>>,
+            Option<String>)> = None;
+        for grid_file in self.grid_files.iter() {
+            let reader = Dataset::open(grid_file)?;
+            let coordinates = read_coordinates(&reader)?;
+            let crs = ncproj_rs::cf::read_grid_crs(&reader)?;
+
+            match &reference {
+                Some((path, _, reference_crs)) if *reference_crs != crs =>
+                    return Err(format!("grid mapping of '{}' differs from \
+                        '{}'", grid_file.display(), path.display()).into()),
+                Some((path, reference_coordinates, _))
+                        if !coordinates_equal(reference_coordinates,
+                            &coordinates) =>
+                    return Err(format!("coordinates of '{}' differ from \
+                        '{}'", grid_file.display(), path.display()).into()),
+                Some(_) => {},
+                None => {
+                    grid_dimensions =
+                        Some(ncproj_rs::grid::read_dimensions(&reader)?);
+                    reference = Some((grid_file, coordinates, crs));
+                },
+            }
+        }
+
+        // merge entries keyed by (shape, x, y)
+        let mut entries: BTreeMap<(String, usize, usize), f32> =
+            BTreeMap::new();
+        let mut duplicate_count = 0;
+        for index_file in self.index_files.iter() {
+            let shapes = ncproj_rs::format::read_index(index_file)?;
+            for (id, indices) in shapes.into_iter() {
+                for (x, y, weight) in indices.into_iter() {
+                    // validate cells are within the grid
+                    if let Some((y_len, x_len)) = grid_dimensions {
+                        if x >= x_len || y >= y_len {
+                            return Err(format!("'{}' cell ({}, {}) of shape \
+                                '{}' outside of {}x{} grid",
+                                index_file.display(), x, y, id,
+                                x_len, y_len).into());
+                        }
+                    }
+
+                    let key = (id.to_string(), x, y);
+                    let mut entry = match entries.entry(key) {
+                        Entry::Occupied(entry) => entry,
+                        Entry::Vacant(entry) => {
+                            entry.insert(weight);
+                            continue;
+                        },
+                    };
+
+                    duplicate_count += 1;
+                    match self.duplicates {
+                        Duplicates::Error => return Err(format!(
+                            "duplicate entry: shape '{}' cell ({}, {}) in \
+                            '{}'", id, x, y, index_file.display()).into()),
+                        Duplicates::First => {},
+                        Duplicates::Max => {
+                            let current = entry.get_mut();
+                            *current = current.max(weight);
+                        },
+                    }
+                }
+            }
+        }
+
+        if duplicate_count > 0 {
+            eprintln!("resolved {} duplicate entries", duplicate_count);
+        }
+
+        // write merged index
+        let mut writer = IndexWriter::new(
+            ncproj_rs::open_output(&self.output, false)?, self.format)?;
+        for ((id, x, y), weight) in entries.iter() {
+            writer.write(*x, *y, id, *weight as f64)?;
+        }
+
+        writer.flush()
+    }
+}
+
+// read the coordinate variables defining grid cells - latitudes and
+//  longitudes and rotated pole coordinates where present
+fn read_coordinates(reader: &Dataset)
+        -> Result<Vec<ArrayD<f64>>, Box<dyn Error>> {
+    let mut coordinates = Vec::new();
+    for name in ["lat", "lon", "rlat", "rlon"].iter() {
+        if reader.variable(name).is_some() {
+            coordinates.push(ncproj_rs::get_netcdf_values(reader, name)?);
+        }
+    }
+
+    Ok(coordinates)
+}
+
+// compare coordinate variables within floating point precision
+fn coordinates_equal(a: &[ArrayD<f64>], b: &[ArrayD<f64>]) -> bool {
+    a.len() == b.len() && a.iter().zip(b.iter()).all(|(a, b)|
+        a.shape() == b.shape() && a.iter().zip(b.iter())
+            .all(|(a, b)| (a - b).abs() <= 1e-6))
+}
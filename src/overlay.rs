@@ -0,0 +1,317 @@
+// union of polygons under the nonzero winding rule - rings are split at
+//  their intersections and the edges bounding covered regions are chained
+//  into simple rings (as in polygon clipping libraries, e.g. clipper)
+use geo_types::{Coordinate, LineString, MultiPolygon, Polygon};
+use rstar::{AABB, RTree, RTreeObject};
+
+use std::cmp::Ordering;
+use std::collections::{HashMap, HashSet};
+
+// tolerance of segment parameters relative to segment length
+const EPSILON: f64 = 1e-10;
+
+// directed segment of an input ring
+#[derive(Clone, Copy, Debug)]
+struct Segment {
+    a: Coordinate<f64>,
+    b: Coordinate<f64>,
+    envelope: AABB<[f64; 2]>,
+    index: usize,
+}
+
+impl RTreeObject for Segment {
+    type Envelope = AABB<[f64; 2]>;
+
+    fn envelope(&self) -> Self::Envelope {
+        self.envelope
+    }
+}
+
+// union polygons - exteriors are oriented counterclockwise and interiors
+//  clockwise so holes only subtract from the polygon they belong to
+pub fn union_polygons(polygons: &[Polygon<f64>]) -> MultiPolygon<f64> {
+    let mut rings = Vec::new();
+    for polygon in polygons.iter() {
+        rings.push(oriented(&polygon.exterior().0, true));
+        for interior in polygon.interiors().iter() {
+            rings.push(oriented(&interior.0, false));
+        }
+    }
+
+    union_rings(&rings)
+}
+
+// compute polygons covering points of positive winding number about the
+//  rings - rings may self-intersect and overlap (e.g. raw offset curves)
+pub fn union_rings(rings: &[Vec<Coordinate<f64>>]) -> MultiPolygon<f64> {
+    let mut segments = Vec::new();
+    for ring in rings.iter() {
+        let points = open_ring(ring);
+        if points.len() < 3 {
+            continue;
+        }
+
+        for k in 0..points.len() {
+            let (a, b) = (points[k], points[(k + 1) % points.len()]);
+            segments.push(Segment {
+                a: a,
+                b: b,
+                envelope: AABB::from_corners([a.x.min(b.x), a.y.min(b.y)],
+                    [a.x.max(b.x), a.y.max(b.y)]),
+                index: segments.len(),
+            });
+        }
+    }
+
+    if segments.is_empty() {
+        return MultiPolygon(Vec::new());
+    }
+
+    let tree = RTree::bulk_load(segments.clone());
+    let max_x = tree.root().envelope().upper()[0];
+
+    // split segments where they meet - points are computed once per
+    //  pair so split edges share identical endpoints
+    let mut splits: Vec<Vec<(f64, Coordinate<f64>)>> = segments.iter()
+        .map(|x| vec![(0.0, x.a), (1.0, x.b)]).collect();
+    for s in segments.iter() {
+        for t in tree.locate_in_envelope_intersecting(&s.envelope) {
+            if t.index <= s.index {
+                continue;
+            }
+
+            for (index, parameter, point) in intersections(s, t) {
+                splits[index].push((parameter, point));
+            }
+        }
+    }
+
+    // retain split edges with covered regions on their left only
+    let mut boundary = Vec::new();
+    let mut retained = HashSet::new();
+    for split in splits.iter_mut() {
+        split.sort_by(|x, y| x.0.partial_cmp(&y.0)
+            .unwrap_or(Ordering::Equal));
+
+        for k in 1..split.len() {
+            let (p, q) = (split[k - 1].1, split[k].1);
+            if p == q {
+                continue;
+            }
+
+            // test points offset either side of the edge midpoint
+            let (dx, dy) = (q.x - p.x, q.y - p.y);
+            let scale = 1e-6;
+            let midpoint = Coordinate { x: (p.x + q.x) / 2.0,
+                y: (p.y + q.y) / 2.0 };
+            let left = Coordinate { x: midpoint.x - dy * scale,
+                y: midpoint.y + dx * scale };
+            let right = Coordinate { x: midpoint.x + dy * scale,
+                y: midpoint.y - dx * scale };
+
+            if winding(&tree, &left, max_x) > 0
+                    && winding(&tree, &right, max_x) <= 0
+                    && retained.insert((key(&p), key(&q))) {
+                boundary.push((p, q));
+            }
+        }
+    }
+
+    // chain boundary edges into rings - taking the leftmost turn where
+    //  rings touch so each ring is simple
+    let mut outgoing: HashMap<(u64, u64), Vec<usize>> = HashMap::new();
+    for (i, (p, _)) in boundary.iter().enumerate() {
+        outgoing.entry(key(p)).or_insert_with(Vec::new).push(i);
+    }
+
+    let mut used = vec![false; boundary.len()];
+    let mut rings = Vec::new();
+    for start in 0..boundary.len() {
+        if used[start] {
+            continue;
+        }
+
+        used[start] = true;
+        let mut ring = vec![boundary[start].0];
+        let mut current = start;
+        let closed = loop {
+            let (p, q) = boundary[current];
+            if q == boundary[start].0 {
+                break true;
+            }
+
+            ring.push(q);
+            let turn = |i: &usize| {
+                let r = boundary[*i].1;
+                let (ax, ay, bx, by) = (q.x - p.x, q.y - p.y,
+                    r.x - q.x, r.y - q.y);
+                (ax * by - ay * bx).atan2(ax * bx + ay * by)
+            };
+
+            let next = outgoing.get(&key(&q)).and_then(|x| x.iter()
+                .filter(|i| !used[**i])
+                .max_by(|a, b| turn(*a).partial_cmp(&turn(*b))
+                    .unwrap_or(Ordering::Equal)));
+
+            match next {
+                Some(next) => {
+                    used[*next] = true;
+                    current = *next;
+                },
+                None => break false,
+            }
+        };
+
+        if closed && ring.len() > 2 {
+            rings.push(ring);
+        }
+    }
+
+    // assign holes to the smallest exterior containing them
+    let (mut exteriors, holes): (Vec<_>, Vec<_>) = rings.into_iter()
+        .filter(|x| ring_area(x) != 0.0)
+        .partition(|x| ring_area(x) > 0.0);
+    exteriors.sort_by(|a, b| ring_area(a).partial_cmp(&ring_area(b))
+        .unwrap_or(Ordering::Equal));
+
+    let mut interiors = vec![Vec::new(); exteriors.len()];
+    for hole in holes.into_iter() {
+        let point = Coordinate { x: (hole[0].x + hole[1].x) / 2.0,
+            y: (hole[0].y + hole[1].y) / 2.0 };
+        if let Some(i) = exteriors.iter()
+                .position(|x| ring_winding(x, &point) != 0) {
+            interiors[i].push(close(hole));
+        }
+    }
+
+    MultiPolygon(exteriors.into_iter().zip(interiors.into_iter())
+        .map(|(exterior, interiors)|
+            Polygon::new(close(exterior), interiors))
+        .collect())
+}
+
+// compute (segment index, parameter, point) splits where two segments
+//  meet - endpoints lying on the other segment split it at the endpoint
+//  which also covers overlapping collinear segments
+fn intersections(s: &Segment, t: &Segment)
+        -> Vec<(usize, f64, Coordinate<f64>)> {
+    let mut points = Vec::new();
+    for (segment, other) in [(s, t), (t, s)].iter() {
+        for point in [other.a, other.b].iter() {
+            if let Some(parameter) = on_segment(segment, point) {
+                points.push((segment.index, parameter, *point));
+            }
+        }
+    }
+
+    // proper crossings of the segment interiors
+    let (rx, ry) = (s.b.x - s.a.x, s.b.y - s.a.y);
+    let (qx, qy) = (t.b.x - t.a.x, t.b.y - t.a.y);
+    let denominator = rx * qy - ry * qx;
+    if denominator.abs() <= EPSILON * (rx.hypot(ry) * qx.hypot(qy)) {
+        return points;
+    }
+
+    let (px, py) = (t.a.x - s.a.x, t.a.y - s.a.y);
+    let u = (px * qy - py * qx) / denominator;
+    let v = (px * ry - py * rx) / denominator;
+    let interior = |x: f64| x > EPSILON && x < 1.0 - EPSILON;
+    if interior(u) && interior(v) {
+        let point = Coordinate { x: s.a.x + u * rx, y: s.a.y + u * ry };
+        points.push((s.index, u, point));
+        points.push((t.index, v, point));
+    }
+
+    points
+}
+
+// compute the parameter of a point within the interior of a segment
+fn on_segment(segment: &Segment, point: &Coordinate<f64>) -> Option<f64> {
+    let (rx, ry) = (segment.b.x - segment.a.x, segment.b.y - segment.a.y);
+    let (px, py) = (point.x - segment.a.x, point.y - segment.a.y);
+    let length = rx * rx + ry * ry;
+
+    let parameter = (px * rx + py * ry) / length;
+    match (rx * py - ry * px).abs() <= EPSILON * length
+            && parameter > EPSILON && parameter < 1.0 - EPSILON {
+        true => Some(parameter),
+        false => None,
+    }
+}
+
+// compute the winding number of segments about a point - only segments
+//  crossing the ray from the point towards positive x contribute
+fn winding(tree: &RTree<Segment>, point: &Coordinate<f64>, max_x: f64)
+        -> i32 {
+    let ray = AABB::from_corners([point.x, point.y],
+        [max_x.max(point.x), point.y]);
+    tree.locate_in_envelope_intersecting(&ray)
+        .map(|x| crossing(&x.a, &x.b, point)).sum()
+}
+
+// compute the winding number of a ring about a point
+fn ring_winding(ring: &[Coordinate<f64>], point: &Coordinate<f64>) -> i32 {
+    (0..ring.len()).map(|k| crossing(&ring[k],
+        &ring[(k + 1) % ring.len()], point)).sum()
+}
+
+// compute the contribution of segment a -> b to a winding number - one
+//  for upward crossings right of the point, minus one for downward
+fn crossing(a: &Coordinate<f64>, b: &Coordinate<f64>,
+        point: &Coordinate<f64>) -> i32 {
+    let side = (b.x - a.x) * (point.y - a.y) - (b.y - a.y) * (point.x - a.x);
+    match (a.y <= point.y, b.y <= point.y) {
+        (true, false) if side > 0.0 => 1,
+        (false, true) if side < 0.0 => -1,
+        _ => 0,
+    }
+}
+
+// orient a ring counterclockwise or clockwise
+fn oriented(ring: &[Coordinate<f64>], counterclockwise: bool)
+        -> Vec<Coordinate<f64>> {
+    let mut ring = open_ring(ring);
+    if (ring_area(&ring) > 0.0) != counterclockwise {
+        ring.reverse();
+    }
+
+    ring
+}
+
+// remove repeated vertices and the closing vertex of a ring
+fn open_ring(ring: &[Coordinate<f64>]) -> Vec<Coordinate<f64>> {
+    let mut points: Vec<Coordinate<f64>> = Vec::new();
+    for point in ring.iter() {
+        if points.last() != Some(point) {
+            points.push(*point);
+        }
+    }
+
+    while points.len() > 1 && points.first() == points.last() {
+        points.pop();
+    }
+
+    points
+}
+
+// close an open ring
+fn close(mut ring: Vec<Coordinate<f64>>) -> LineString<f64> {
+    ring.push(ring[0]);
+    LineString(ring)
+}
+
+// key a coordinate by its exact value
+fn key(point: &Coordinate<f64>) -> (u64, u64) {
+    (point.x.to_bits(), point.y.to_bits())
+}
+
+// compute the signed area of an open ring (shoelace)
+fn ring_area(ring: &[Coordinate<f64>]) -> f64 {
+    let mut area = 0.0;
+    for k in 0..ring.len() {
+        let (p, q) = (ring[k], ring[(k + 1) % ring.len()]);
+        area += p.x * q.y - q.x * p.y;
+    }
+
+    area / 2.0
+}
@@ -0,0 +1,118 @@
+use chrono::{Datelike, TimeZone, Utc};
+
+use crate::stat::Stat;
+
+use std::collections::BTreeMap;
+use std::str::FromStr;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Period {
+    Annual,
+    Monthly,
+    Seasonal,
+}
+
+impl Period {
+    // compute the timestamp at the start of the period containing timestamp
+    pub fn start(&self, timestamp: i64) -> i64 {
+        let datetime = Utc.timestamp(timestamp, 0);
+        let (year, month) = (datetime.year(), datetime.month());
+
+        let (year, month) = match self {
+            Period::Annual => (year, 1),
+            Period::Monthly => (year, month),
+            // seasons are DJF, MAM, JJA, and SON
+            Period::Seasonal => match month {
+                12 => (year, 12),
+                1 | 2 => (year - 1, 12),
+                x => (year, x - (x % 3)),
+            },
+        };
+
+        Utc.ymd(year, month, 1).and_hms(0, 0, 0).timestamp()
+    }
+}
+
+impl FromStr for Period {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "annual" => Ok(Period::Annual),
+            "monthly" => Ok(Period::Monthly),
+            "seasonal" => Ok(Period::Seasonal),
+            x => Err(format!("unsupported resample period '{}'", x)),
+        }
+    }
+}
+
+// aggregates per-timestep statistics of a single shape over a period
+struct Aggregate {
+    counts: Vec<usize>,
+    values: Vec<f32>,
+}
+
+// accumulates per-timestep statistics into (period, shape) aggregates
+pub struct Resampler {
+    aggregates: BTreeMap<(i64, usize), Aggregate>,
+    period: Period,
+    stats: Vec<Stat>, // statistic of each column
+}
+
+impl Resampler {
+    pub fn new(period: Period, stats: Vec<Stat>) -> Resampler {
+        Resampler {
+            aggregates: BTreeMap::new(),
+            period: period,
+            stats: stats,
+        }
+    }
+
+    pub fn update(&mut self, shape: usize, timestamp: i64, data: &[f32]) {
+        let key = (self.period.start(timestamp), shape);
+        let aggregate = self.aggregates.entry(key).or_insert(Aggregate {
+            counts: vec![0; data.len()],
+            values: vec![0f32; data.len()],
+        });
+
+        // values are laid out by feature and then statistic
+        for (k, value) in data.iter().enumerate() {
+            if value.is_nan() {
+                continue;
+            }
+
+            let current = &mut aggregate.values[k];
+            *current = match (self.stats[k],
+                    aggregate.counts[k]) {
+                (_, 0) => *value,
+                (Stat::Max, _) => current.max(*value),
+                (Stat::Min, _) => current.min(*value),
+                // remaining statistics are averaged over the period
+                _ => *current + *value,
+            };
+
+            aggregate.counts[k] += 1;
+        }
+    }
+
+    // compute (period timestamp, shape, values) ordered by period
+    pub fn finish(self) -> Vec<(i64, usize, Vec<f32>)> {
+        let stats = self.stats;
+        self.aggregates.into_iter().map(|((timestamp, shape), aggregate)| {
+            let values = aggregate.values.iter()
+                .zip(aggregate.counts.iter()).enumerate()
+                .map(|(k, (value, count))| {
+                    match (stats[k], count) {
+                        (_, 0) => f32::NAN,
+                        (Stat::Count, _) | (Stat::CountThreshold(_, _), _)
+                            | (Stat::DegreeDays(_, _), _)
+                            | (Stat::Max, _) | (Stat::Min, _)
+                            | (Stat::Sum, _) => *value,
+                        _ => *value / *count as f32,
+                    }
+                }).collect();
+
+            (timestamp, shape, values)
+        }).collect()
+    }
+}
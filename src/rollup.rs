@@ -0,0 +1,309 @@
+use structopt::StructOpt;
+
+use ncproj_rs::sink::{Columns, OutputFormat, TextFormat};
+
+use std::collections::{BTreeMap, HashMap};
+use std::error::Error;
+use std::path::PathBuf;
+
+// columns of dumped rows which are not statistics
+const KEY_COLUMNS: [&str; 6] =
+    ["day", "gis_join", "index_name", "month", "timestamp", "year"];
+
+#[derive(StructOpt)]
+pub struct Rollup {
+    // number of rows in each output batch (arrow, parquet)
+    #[structopt(long = "batch-size", default_value = "8192")]
+    batch_size: usize,
+
+    // csv field delimiter of input and output - 'comma', 'tab', 'pipe',
+    //  or a character
+    #[structopt(short = "d", long = "delimiter", default_value = "comma",
+        parse(try_from_str = ncproj_rs::sink::parse_delimiter))]
+    delimiter: u8,
+
+    // csv rows written by dump with epoch timestamps
+    #[structopt(parse(from_os_str), index = 1)]
+    input: PathBuf,
+
+    // csv mapping 'gis_join' shapes to 'parent' shapes (e.g. counties to
+    //  states) - shapes may map to several parents
+    #[structopt(short = "m", long = "mapping", parse(from_os_str))]
+    mapping: PathBuf,
+
+    // csv representation of cells without values - 'empty', 'nan', or
+    //  a literal string
+    #[structopt(long = "null-as", default_value = "nan",
+        parse(try_from_str = ncproj_rs::sink::parse_null))]
+    null_as: String,
+
+    #[structopt(short = "o", long = "output", parse(from_os_str))]
+    output: Option<PathBuf>,
+
+    // 'csv', 'ndjson', 'arrow', or 'parquet'
+    #[structopt(short = "f", long = "output-format", default_value = "csv")]
+    output_format: OutputFormat,
+
+    // number of decimal places of csv and ndjson values
+    #[structopt(long = "precision", default_value = "3")]
+    precision: usize,
+
+    // mapping column of shape weights (e.g. 'area' or 'population')
+    //  - shapes are weighted equally when unset
+    #[structopt(short = "w", long = "weight-column")]
+    weight_column: Option<String>,
+}
+
+impl Rollup {
+    pub fn execute(&self) -> Result<(), Box<dyn Error>> {
+        if self.output_format == OutputFormat::Netcdf {
+            return Err("netcdf output is unsupported for rollups".into());
+        }
+
+        let (parents, mapping) = self.read_mapping()?;
+
+        // identify statistic columns and their rollup
+        let mut reader = csv::ReaderBuilder::new()
+            .delimiter(self.delimiter)
+            .from_reader(ncproj_rs::open_input(&self.input)?);
+        let headers = reader.headers()?.clone();
+        let position = |name: &str| headers.iter().position(|x| x == name);
+
+        let gis_join_index = position("gis_join")
+            .ok_or("failed to identify 'gis_join' column")?;
+        let timestamp_index = position("timestamp")
+            .ok_or("failed to identify 'timestamp' column")?;
+        let member_index = position("member");
+
+        let columns: Vec<(usize, String)> = headers.iter().enumerate()
+            .filter(|(_, x)| !KEY_COLUMNS.contains(x) && *x != "member")
+            .map(|(i, x)| (i, x.to_string())).collect();
+        let combines: Vec<Combine> = columns.iter()
+            .map(|(_, x)| Combine::new(x, &columns)).collect();
+
+        // accumulate rows of each (timestamp, parent, member)
+        let mut groups: BTreeMap<(i64, usize, i64), Vec<Accumulator>> =
+            BTreeMap::new();
+        let mut unmapped_count = 0;
+        for result in reader.records() {
+            let record = result?;
+            let entries = match mapping.get(&record[gis_join_index]) {
+                Some(entries) => entries,
+                None => {
+                    unmapped_count += 1;
+                    continue;
+                },
+            };
+
+            let timestamp = record[timestamp_index].parse::<i64>()
+                .map_err(|_| format!("invalid timestamp '{}' - rollup \
+                    requires epoch timestamps", &record[timestamp_index]))?;
+            let member = match member_index {
+                Some(index) => record[index].parse::<f64>()
+                    .map_err(|_| format!("invalid member '{}'",
+                        &record[index]))? as i64,
+                None => 0,
+            };
+
+            let values: Vec<f64> = columns.iter()
+                .map(|(i, _)| parse_value(&record[*i])).collect();
+            for (parent, weight) in entries.iter() {
+                let accumulators = groups.entry((timestamp, *parent, member))
+                    .or_insert_with(|| vec![Accumulator::default();
+                        columns.len()]);
+
+                for (k, combine) in combines.iter().enumerate() {
+                    accumulators[k].update(combine, &values, k, *weight);
+                }
+            }
+        }
+
+        if unmapped_count > 0 {
+            eprintln!("skipped {} rows of unmapped shapes", unmapped_count);
+        }
+
+        // write rows of parent shapes
+        let mut values: Vec<String> =
+            columns.iter().map(|x| x.1.to_string()).collect();
+        if member_index.is_some() {
+            values.insert(0, "member".to_string());
+        }
+
+        let text_format = TextFormat {
+            delimiter: self.delimiter,
+            null: self.null_as.clone(),
+            precision: self.precision,
+            ..TextFormat::default()
+        };
+
+        let mut sink = ncproj_rs::sink::open(self.output_format,
+            &self.output, Columns::new(values), self.batch_size,
+            &text_format, false)?;
+        for ((timestamp, parent, member), accumulators) in groups.iter() {
+            let mut data: Vec<f32> = accumulators.iter()
+                .zip(combines.iter()).map(|(x, y)| x.compute(y)).collect();
+            if member_index.is_some() {
+                data.insert(0, *member as f32);
+            }
+
+            sink.write_row(&parents[*parent], *timestamp, &data)?;
+        }
+
+        sink.close()
+    }
+
+    // read parent ids and the (parent index, weight) entries of shapes
+    fn read_mapping(&self) -> Result<(Vec<String>,
+            HashMap<String, Vec<(usize, f64)>>), Box<dyn Error>> {
+        let mut reader = csv::ReaderBuilder::new()
+            .from_reader(ncproj_rs::open_input(&self.mapping)?);
+        let headers = reader.headers()?.clone();
+        let position = |name: &str| headers.iter().position(|x| x == name)
+            .ok_or_else(|| format!("failed to identify mapping column '{}'",
+                name));
+
+        let gis_join_index = position("gis_join")?;
+        let parent_index = position("parent")?;
+        let weight_index = match &self.weight_column {
+            Some(weight_column) => Some(position(weight_column)?),
+            None => None,
+        };
+
+        let (mut parents, mut parent_indices) = (Vec::new(), HashMap::new());
+        let mut mapping: HashMap<String, Vec<(usize, f64)>> = HashMap::new();
+        for result in reader.records() {
+            let record = result?;
+            let weight = match weight_index {
+                Some(index) => match record[index].trim().parse::<f64>() {
+                    Ok(weight) if weight.is_finite() && weight >= 0.0 =>
+                        weight,
+                    _ => return Err(format!("invalid weight '{}' of shape \
+                        '{}'", &record[index], &record[gis_join_index])
+                        .into()),
+                },
+                None => 1.0,
+            };
+
+            let parent = record[parent_index].to_string();
+            let parent = *parent_indices.entry(parent.clone())
+                .or_insert_with(|| {
+                    parents.push(parent);
+                    parents.len() - 1
+                });
+
+            mapping.entry(record[gis_join_index].to_string())
+                .or_insert_with(Vec::new).push((parent, weight));
+        }
+
+        Ok((parents, mapping))
+    }
+}
+
+// combination of a statistic column over child shapes
+enum Combine {
+    Max,
+    Mean, // weighted mean (e.g. means, percentiles, and fractions)
+    Min,
+    Mode, // class with the largest total weight
+    Std(Option<usize>), // pooled with the column of the feature mean
+    Sum, // unweighted total (e.g. sums and counts)
+}
+
+impl Combine {
+    // identify the combination by the statistic name prefix of a column
+    fn new(column: &str, columns: &[(usize, String)]) -> Combine {
+        let prefix_len = column.find('_').map(|x| x + 1).unwrap_or(0);
+        match column.split_at(prefix_len) {
+            ("count_", _) | ("sum_", _) => Combine::Sum,
+            ("max_", _) => Combine::Max,
+            ("min_", _) => Combine::Min,
+            ("mode_", _) => Combine::Mode,
+            ("std_", feature) => Combine::Std(columns.iter()
+                .position(|x| x.1 == format!("mean_{}", feature))),
+            _ => Combine::Mean,
+        }
+    }
+}
+
+// combines values of a column over the child shapes of a parent
+#[derive(Clone, Default)]
+struct Accumulator {
+    classes: Vec<(f32, f64)>, // total weight of each mode class
+    squares: f64, // weighted sum of second moments
+    sum: f64,
+    value: Option<f32>, // minimum or maximum
+    weight: f64,
+}
+
+impl Accumulator {
+    // update with the value of column k - undefined values are skipped
+    fn update(&mut self, combine: &Combine, values: &[f64], k: usize,
+            weight: f64) {
+        let value = values[k];
+        if !value.is_finite() {
+            return;
+        }
+
+        match combine {
+            Combine::Max => self.value = Some(self.value
+                .map(|x| x.max(value as f32)).unwrap_or(value as f32)),
+            Combine::Mean => {
+                self.sum += weight * value;
+                self.weight += weight;
+            },
+            Combine::Min => self.value = Some(self.value
+                .map(|x| x.min(value as f32)).unwrap_or(value as f32)),
+            Combine::Mode => {
+                match self.classes.iter_mut().find(|x| x.0 == value as f32) {
+                    Some(class) => class.1 += weight,
+                    None => self.classes.push((value as f32, weight)),
+                }
+            },
+            Combine::Std(mean) => {
+                // variance of the union is the weighted mean of second
+                //  moments less the square of the combined mean
+                let mean = match mean.map(|x| values[x]) {
+                    Some(mean) if mean.is_finite() => mean,
+                    Some(_) => return,
+                    None => 0.0,
+                };
+
+                self.squares += weight * (value * value + mean * mean);
+                self.sum += weight * mean;
+                self.weight += weight;
+            },
+            Combine::Sum => {
+                self.sum += value;
+                self.weight += 1.0;
+            },
+        }
+    }
+
+    fn compute(&self, combine: &Combine) -> f32 {
+        match combine {
+            Combine::Max | Combine::Min =>
+                return self.value.unwrap_or(f32::NAN),
+            Combine::Mode => return self.classes.iter()
+                .fold(None, |mode: Option<&(f32, f64)>, x| match mode {
+                    Some(mode) if mode.1 >= x.1 => Some(mode),
+                    _ => Some(x),
+                }).map(|x| x.0).unwrap_or(f32::NAN),
+            _ if self.weight <= 0.0 => return f32::NAN,
+            _ => {},
+        }
+
+        let mean = self.sum / self.weight;
+        match combine {
+            Combine::Std(_) =>
+                (self.squares / self.weight - mean * mean).max(0.0).sqrt()
+                    as f32,
+            Combine::Sum => self.sum as f32,
+            _ => mean as f32,
+        }
+    }
+}
+
+// parse a csv value - empty and unparseable values are undefined
+fn parse_value(s: &str) -> f64 {
+    s.trim().parse::<f64>().unwrap_or(f64::NAN)
+}
@@ -0,0 +1,318 @@
+// signing of s3 object urls - netcdf-c and kerchunk issue plain ranged
+//  GETs so requests are authorized with sigv4 presigned urls
+use chrono::{DateTime, Duration, Utc};
+use hmac::{Hmac, Mac, NewMac};
+use sha2::{Digest, Sha256};
+
+use std::cell::RefCell;
+use std::error::Error;
+use std::path::PathBuf;
+
+// validity of presigned urls - the longest sigv4 allows, urls signed
+//  with temporary credentials expire with the credentials
+const EXPIRES_SECONDS: i64 = 604800;
+
+// base url of the ec2 instance metadata service
+const IMDS_URL: &str = "http://169.254.169.254/latest";
+
+// base url of the ecs container credentials endpoint
+const CONTAINER_URL: &str = "http://169.254.170.2";
+
+#[derive(Clone)]
+struct Credentials {
+    access_key_id: String,
+    expiration: Option<DateTime<Utc>>,
+    secret_access_key: String,
+    session_token: Option<String>,
+}
+
+thread_local! {
+    // credentials resolved by this thread - refreshed before expiring
+    static CREDENTIALS: RefCell<Option<Credentials>> = RefCell::new(None);
+}
+
+// convert 's3://bucket/key' into an https url - signed with credentials of
+//  the standard aws chain unless AWS_NO_SIGN_REQUEST is set
+//  - the endpoint defaults to aws in the region from the environment
+//  and may be overridden with AWS_ENDPOINT_URL (e.g. minio)
+pub fn object_url(url: &str) -> Result<String, Box<dyn Error>> {
+    let (bucket, key) = match url.strip_prefix("s3://")
+            .and_then(|x| x.split_once('/')) {
+        Some((bucket, key)) if !bucket.is_empty() && !key.is_empty() =>
+            (bucket, key),
+        _ => return Err(format!("invalid s3 url '{}'", url).into()),
+    };
+
+    let region = std::env::var("AWS_REGION")
+        .or_else(|_| std::env::var("AWS_DEFAULT_REGION"))
+        .unwrap_or("us-east-1".to_string());
+
+    // custom endpoints are addressed by path and aws by virtual host
+    let (scheme, host, path) = match std::env::var("AWS_ENDPOINT_URL") {
+        Ok(endpoint) => {
+            let (scheme, rest) = match endpoint.split_once("://") {
+                Some((scheme, rest)) => (scheme.to_string(), rest),
+                None => ("https".to_string(), endpoint.as_str()),
+            };
+
+            let (host, prefix) = match rest.split_once('/') {
+                Some((host, prefix)) => (host, prefix.trim_matches('/')),
+                None => (rest, ""),
+            };
+
+            let path = match prefix.is_empty() {
+                true => format!("/{}/{}", bucket, key),
+                false => format!("/{}/{}/{}", prefix, bucket, key),
+            };
+
+            (scheme, host.to_string(), path)
+        },
+        Err(_) => ("https".to_string(),
+            format!("{}.s3.{}.amazonaws.com", bucket, region),
+            format!("/{}", key)),
+    };
+
+    let path = encode(&path, false);
+    if unsigned() {
+        return Ok(format!("{}://{}{}", scheme, host, path));
+    }
+
+    let credentials = credentials().map_err(|e| format!("no aws \
+        credentials to sign requests for '{}' ({}) - set \
+        AWS_NO_SIGN_REQUEST=true to read public objects unsigned", url, e))?;
+
+    Ok(format!("{}://{}{}?{}", scheme, host, path,
+        presign(&credentials, &region, &host, &path, Utc::now())))
+}
+
+// check if requests are opted out of signing (e.g. public buckets)
+fn unsigned() -> bool {
+    match std::env::var("AWS_NO_SIGN_REQUEST") {
+        Ok(value) => match value.to_lowercase().as_str() {
+            "1" | "true" | "yes" => true,
+            _ => false,
+        },
+        Err(_) => false,
+    }
+}
+
+// compute the sigv4 query string authorizing a GET of the path - only
+//  the host header is signed so ranged requests remain valid
+fn presign(credentials: &Credentials, region: &str, host: &str, path: &str,
+        now: DateTime<Utc>) -> String {
+    let (timestamp, date) = (now.format("%Y%m%dT%H%M%SZ").to_string(),
+        now.format("%Y%m%d").to_string());
+    let scope = format!("{}/{}/s3/aws4_request", date, region);
+
+    // parameters are sorted by name
+    let mut parameters = vec![
+        ("X-Amz-Algorithm", "AWS4-HMAC-SHA256".to_string()),
+        ("X-Amz-Credential",
+            format!("{}/{}", credentials.access_key_id, scope)),
+        ("X-Amz-Date", timestamp.clone()),
+        ("X-Amz-Expires", EXPIRES_SECONDS.to_string()),
+    ];
+
+    if let Some(token) = &credentials.session_token {
+        parameters.push(("X-Amz-Security-Token", token.to_string()));
+    }
+
+    parameters.push(("X-Amz-SignedHeaders", "host".to_string()));
+
+    let query = parameters.iter()
+        .map(|(name, value)| format!("{}={}", name, encode(value, true)))
+        .collect::<Vec<String>>().join("&");
+
+    let request = format!("GET\n{}\n{}\nhost:{}\n\nhost\nUNSIGNED-PAYLOAD",
+        path, query, host);
+    let string_to_sign = format!("AWS4-HMAC-SHA256\n{}\n{}\n{}", timestamp,
+        scope, hex(&Sha256::digest(request.as_bytes())));
+
+    // derive the signing key of the date, region, and service
+    let mut key = format!("AWS4{}", credentials.secret_access_key)
+        .into_bytes();
+    for part in [date.as_str(), region, "s3", "aws4_request"].iter() {
+        key = sign(&key, part.as_bytes());
+    }
+
+    format!("{}&X-Amz-Signature={}", query,
+        hex(&sign(&key, string_to_sign.as_bytes())))
+}
+
+// resolve credentials from the environment, the shared credentials file,
+//  the ecs container endpoint, or the ec2 instance metadata service
+fn credentials() -> Result<Credentials, Box<dyn Error>> {
+    let cached = CREDENTIALS.with(|x| x.borrow().clone());
+    if let Some(credentials) = cached {
+        match credentials.expiration {
+            Some(expiration)
+                if expiration - Duration::minutes(5) < Utc::now() => {},
+            _ => return Ok(credentials),
+        }
+    }
+
+    let credentials = match environment_credentials() {
+        Some(credentials) => credentials,
+        None => match profile_credentials()? {
+            Some(credentials) => credentials,
+            None => match container_credentials()? {
+                Some(credentials) => credentials,
+                None => instance_credentials()?,
+            },
+        },
+    };
+
+    CREDENTIALS.with(|x| *x.borrow_mut() = Some(credentials.clone()));
+    Ok(credentials)
+}
+
+// read AWS_ACCESS_KEY_ID, AWS_SECRET_ACCESS_KEY, and AWS_SESSION_TOKEN
+fn environment_credentials() -> Option<Credentials> {
+    match (std::env::var("AWS_ACCESS_KEY_ID"),
+            std::env::var("AWS_SECRET_ACCESS_KEY")) {
+        (Ok(access_key_id), Ok(secret_access_key)) => Some(Credentials {
+            access_key_id: access_key_id,
+            expiration: None,
+            secret_access_key: secret_access_key,
+            session_token: std::env::var("AWS_SESSION_TOKEN").ok(),
+        }),
+        _ => None,
+    }
+}
+
+// read the AWS_PROFILE (or 'default') section of the shared credentials
+//  file (AWS_SHARED_CREDENTIALS_FILE or '~/.aws/credentials')
+fn profile_credentials() -> Result<Option<Credentials>, Box<dyn Error>> {
+    let path = match std::env::var("AWS_SHARED_CREDENTIALS_FILE") {
+        Ok(path) => PathBuf::from(path),
+        Err(_) => match std::env::var("HOME") {
+            Ok(home) => PathBuf::from(home).join(".aws").join("credentials"),
+            Err(_) => return Ok(None),
+        },
+    };
+
+    if !path.exists() {
+        return Ok(None);
+    }
+
+    let profile = std::env::var("AWS_PROFILE")
+        .unwrap_or("default".to_string());
+    let (mut section, mut values) = (String::new(), Vec::new());
+    for line in std::fs::read_to_string(&path)?.lines() {
+        let line = line.trim();
+        if line.starts_with('[') && line.ends_with(']') {
+            section = line[1..line.len() - 1].trim().to_string();
+        } else if section == profile {
+            if let Some((name, value)) = line.split_once('=') {
+                values.push((name.trim().to_string(),
+                    value.trim().to_string()));
+            }
+        }
+    }
+
+    let value = |name: &str| values.iter()
+        .find(|x| x.0 == name).map(|x| x.1.to_string());
+    match (value("aws_access_key_id"), value("aws_secret_access_key")) {
+        (Some(access_key_id), Some(secret_access_key)) => Ok(Some(Credentials {
+            access_key_id: access_key_id,
+            expiration: None,
+            secret_access_key: secret_access_key,
+            session_token: value("aws_session_token"),
+        })),
+        _ => Ok(None),
+    }
+}
+
+// request task role credentials of ecs containers
+fn container_credentials() -> Result<Option<Credentials>, Box<dyn Error>> {
+    let url = match (std::env::var("AWS_CONTAINER_CREDENTIALS_RELATIVE_URI"),
+            std::env::var("AWS_CONTAINER_CREDENTIALS_FULL_URI")) {
+        (Ok(uri), _) => format!("{}{}", CONTAINER_URL, uri),
+        (_, Ok(url)) => url,
+        _ => return Ok(None),
+    };
+
+    let request = ureq::get(&url)
+        .timeout(std::time::Duration::from_secs(5));
+    let request = match std::env::var("AWS_CONTAINER_AUTHORIZATION_TOKEN") {
+        Ok(token) => request.set("Authorization", &token),
+        Err(_) => request,
+    };
+
+    parse_credentials(&request.call()?.into_string()?).map(Some)
+}
+
+// request instance role credentials from the instance metadata service
+//  (imdsv2 session tokens)
+fn instance_credentials() -> Result<Credentials, Box<dyn Error>> {
+    let timeout = std::time::Duration::from_secs(2);
+    let token = ureq::put(&format!("{}/api/token", IMDS_URL))
+        .timeout(timeout)
+        .set("X-aws-ec2-metadata-token-ttl-seconds", "21600")
+        .call().map_err(|e| format!("instance metadata unavailable: {}", e))?
+        .into_string()?;
+
+    let url = format!("{}/meta-data/iam/security-credentials/", IMDS_URL);
+    let get = |url: &str| -> Result<String, Box<dyn Error>> {
+        Ok(ureq::get(url).timeout(timeout)
+            .set("X-aws-ec2-metadata-token", &token)
+            .call()?.into_string()?)
+    };
+
+    let role = get(&url)?;
+    let role = match role.lines().next() {
+        Some(role) if !role.trim().is_empty() => role.trim().to_string(),
+        _ => return Err("instance has no iam role".into()),
+    };
+
+    parse_credentials(&get(&format!("{}{}", url, role))?)
+}
+
+// parse credentials of the container and instance metadata endpoints
+fn parse_credentials(body: &str) -> Result<Credentials, Box<dyn Error>> {
+    let value: serde_json::Value = serde_json::from_str(body)?;
+    let field = |name: &str| value.get(name).and_then(|x| x.as_str())
+        .map(|x| x.to_string());
+
+    match (field("AccessKeyId"), field("SecretAccessKey")) {
+        (Some(access_key_id), Some(secret_access_key)) => Ok(Credentials {
+            access_key_id: access_key_id,
+            expiration: match field("Expiration") {
+                Some(x) => Some(DateTime::parse_from_rfc3339(&x)?
+                    .with_timezone(&Utc)),
+                None => None,
+            },
+            secret_access_key: secret_access_key,
+            session_token: field("Token"),
+        }),
+        _ => Err("credentials response missing access keys".into()),
+    }
+}
+
+// percent-encode all but unreserved characters - slashes are retained
+//  in paths
+fn encode(value: &str, encode_slash: bool) -> String {
+    let mut encoded = String::new();
+    for byte in value.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9'
+                | b'-' | b'_' | b'.' | b'~' => encoded.push(byte as char),
+            b'/' if !encode_slash => encoded.push('/'),
+            _ => encoded.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+
+    encoded
+}
+
+// compute the hmac-sha256 of data - hmac accepts keys of any length
+fn sign(key: &[u8], data: &[u8]) -> Vec<u8> {
+    let mut mac = Hmac::<Sha256>::new_from_slice(key).unwrap();
+    mac.update(data);
+    mac.finalize().into_bytes().to_vec()
+}
+
+// format bytes as lowercase hex
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|x| format!("{:02x}", x)).collect()
+}
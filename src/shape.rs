@@ -0,0 +1,754 @@
+use dbase::FieldValue;
+use geo::algorithm::bounding_rect::BoundingRect;
+use geo::algorithm::centroid::Centroid;
+use geo::algorithm::map_coords::TryMapCoords;
+use geo_types::{Coordinate, Geometry, LineString, MultiLineString,
+    MultiPoint, MultiPolygon, Point, Polygon, Rect};
+use geojson::GeoJson;
+use rusqlite::{Connection, OpenFlags};
+use rusqlite::types::Value as SqlValue;
+use shapefile::Reader;
+use wkt::Wkt;
+
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, HashMap};
+use std::convert::TryInto;
+use std::error::Error;
+use std::path::PathBuf;
+use std::str::FromStr;
+
+// read the crs definition of a shape file if one is recorded
+pub fn read_crs(path: &PathBuf, layer: &Option<String>)
+        -> Result<Option<String>, Box<dyn Error>> {
+    match path.extension().and_then(|x| x.to_str()) {
+        // geojson coordinates are wgs84 by definition
+        Some("geojson") | Some("json") => Ok(Some("EPSG:4326".to_string())),
+        Some("csv") => Ok(None),
+        Some("fgb") => Ok(crate::fgb::Reader::open(path)?.crs()),
+        Some("gpkg") => {
+            let connection = Connection::open_with_flags(path,
+                OpenFlags::SQLITE_OPEN_READ_ONLY)?;
+            let layer = geopackage_layer(&connection, layer)?;
+
+            let (organization, id, definition): (String, i64, String) =
+                connection.query_row("SELECT s.organization,
+                        s.organization_coordsys_id, s.definition
+                    FROM gpkg_geometry_columns g JOIN gpkg_spatial_ref_sys s
+                        ON g.srs_id = s.srs_id WHERE g.table_name = ?",
+                    &[&layer], |row| Ok((row.get(0)?,
+                        row.get(1)?, row.get(2)?)))?;
+
+            match (organization.to_uppercase().as_str(), definition.as_str()) {
+                ("EPSG", _) => Ok(Some(format!("EPSG:{}", id))),
+                (_, "undefined") => Ok(None),
+                _ => Ok(Some(definition)),
+            }
+        },
+        // shapefiles record their crs in a sidecar '.prj' file
+        _ => match std::fs::read_to_string(path.with_extension("prj")) {
+            Ok(wkt) => Ok(Some(wkt.trim().to_string())),
+            Err(ref e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e.into()),
+        },
+    }
+}
+
+// geometry of a shape - polygons are indexed by cell coverage, points
+//  to their containing cells, and lines to the cells they cross
+#[derive(Clone, Debug)]
+pub enum Shape {
+    Lines(MultiLineString<f64>),
+    Points(MultiPoint<f64>),
+    Polygons(MultiPolygon<f64>),
+}
+
+impl Shape {
+    // convert a geometry - None for unsupported types (e.g. collections)
+    fn from_geometry(geometry: Geometry<f64>) -> Option<Shape> {
+        match geometry {
+            Geometry::LineString(x) =>
+                Some(Shape::Lines(MultiLineString(vec![x]))),
+            Geometry::MultiLineString(x) => Some(Shape::Lines(x)),
+            Geometry::MultiPoint(x) => Some(Shape::Points(x)),
+            Geometry::MultiPolygon(x) => Some(Shape::Polygons(x)),
+            Geometry::Point(x) => Some(Shape::Points(MultiPoint(vec![x]))),
+            Geometry::Polygon(x) =>
+                Some(Shape::Polygons(MultiPolygon(vec![x]))),
+            _ => None,
+        }
+    }
+
+    pub fn bounding_rect(&self) -> Option<Rect<f64>> {
+        match self {
+            Shape::Lines(x) => x.bounding_rect(),
+            Shape::Points(x) => x.bounding_rect(),
+            Shape::Polygons(x) => x.bounding_rect(),
+        }
+    }
+
+    // compute the centroid - lines are weighted by segment length
+    pub fn centroid(&self) -> Option<Point<f64>> {
+        match self {
+            Shape::Lines(lines) => {
+                let (mut x_sum, mut y_sum, mut length) = (0.0, 0.0, 0.0);
+                for line in lines.0.iter() {
+                    for segment in line.0.windows(2) {
+                        let (p, q) = (segment[0], segment[1]);
+                        let segment_length = (q.x - p.x).hypot(q.y - p.y);
+                        x_sum += segment_length * (p.x + q.x) / 2.0;
+                        y_sum += segment_length * (p.y + q.y) / 2.0;
+                        length += segment_length;
+                    }
+                }
+
+                match length > 0.0 {
+                    true => Some(Point::new(x_sum / length, y_sum / length)),
+                    false => lines.0.iter().flat_map(|x| x.0.iter())
+                        .next().map(|x| Point(*x)),
+                }
+            },
+            Shape::Points(points) => match points.0.len() {
+                0 => None,
+                len => Some(Point::new(
+                    points.0.iter().map(|x| x.x()).sum::<f64>() / len as f64,
+                    points.0.iter().map(|x| x.y()).sum::<f64>() / len as f64)),
+            },
+            Shape::Polygons(x) => x.centroid(),
+        }
+    }
+
+    // apply a fallible coordinate transformation (e.g. reprojection)
+    pub fn try_map_coords(&self, func: impl Fn(&(f64, f64))
+            -> Result<(f64, f64), Box<dyn Error + Send + Sync>> + Copy)
+            -> Result<Shape, Box<dyn Error + Send + Sync>> {
+        Ok(match self {
+            Shape::Lines(x) => Shape::Lines(x.try_map_coords(func)?),
+            Shape::Points(x) => Shape::Points(x.try_map_coords(func)?),
+            Shape::Polygons(x) => Shape::Polygons(x.try_map_coords(func)?),
+        })
+    }
+}
+
+// read shapes from a file, dispatching on the file extension
+//  - only shapes with attributes matching all filters are read
+//  - spatially indexed formats (flatgeobuf) skip shapes outside of the
+//  (min x, min y, max x, max y) extent, which others may include
+pub fn read_shapes(path: &PathBuf, id_field: &Option<String>,
+        layer: &Option<String>, filters: &[Filter],
+        extent: Option<&[f64; 4]>)
+        -> Result<BTreeMap<String, Shape>, Box<dyn Error>> {
+    // shapes of repeated ids replace earlier shapes
+    Ok(read_features(path, id_field, layer, filters, extent)?
+        .into_iter().collect())
+}
+
+// read shapes dissolved by an attribute - shapes sharing a value are
+//  combined into one shape identified by the value (e.g. counties into
+//  states by 'STATEFP10') - polygons are unioned
+pub fn dissolve_shapes(path: &PathBuf, field: &str,
+        layer: &Option<String>, filters: &[Filter],
+        extent: Option<&[f64; 4]>)
+        -> Result<BTreeMap<String, Shape>, Box<dyn Error>> {
+    let mut shapes = BTreeMap::new();
+    for (id, shape) in read_features(path, &Some(field.to_string()),
+            layer, filters, extent)? {
+        let shape = match (shapes.remove(&id), shape) {
+            (None, shape) => shape,
+            (Some(Shape::Lines(mut x)), Shape::Lines(y)) => {
+                x.0.extend(y.0);
+                Shape::Lines(x)
+            },
+            (Some(Shape::Points(mut x)), Shape::Points(y)) => {
+                x.0.extend(y.0);
+                Shape::Points(x)
+            },
+            (Some(Shape::Polygons(mut x)), Shape::Polygons(y)) => {
+                x.0.extend(y.0);
+                Shape::Polygons(x)
+            },
+            _ => return Err(format!("failed to dissolve mixed geometry \
+                types of '{}' value '{}'", field, id).into()),
+        };
+
+        shapes.insert(id, shape);
+    }
+
+    // union polygons so overlapping parts are not weighted twice
+    for shape in shapes.values_mut() {
+        if let Shape::Polygons(polygons) = shape {
+            if polygons.0.len() > 1 {
+                *polygons = crate::overlay::union_polygons(&polygons.0);
+            }
+        }
+    }
+
+    Ok(shapes)
+}
+
+// read (id, shape) features in file order, dispatching on the extension
+fn read_features(path: &PathBuf, id_field: &Option<String>,
+        layer: &Option<String>, filters: &[Filter],
+        extent: Option<&[f64; 4]>)
+        -> Result<Vec<(String, Shape)>, Box<dyn Error>> {
+    match path.extension().and_then(|x| x.to_str()) {
+        Some("geojson") | Some("json") =>
+            read_geojson(path, id_field, filters),
+        Some("fgb") => read_flatgeobuf(path, id_field, filters, extent),
+        Some("csv") | Some("gpkg") if !filters.is_empty() => Err(
+            "attribute filters require shapefile, geojson, or flatgeobuf \
+            shapes".into()),
+        Some("csv") => read_csv(path, id_field),
+        Some("gpkg") => read_geopackage(path, id_field, layer),
+        _ => read_shapefile(path, id_field, filters),
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Operator {
+    Eq,
+    Ge,
+    Gt,
+    Le,
+    Lt,
+    Ne,
+}
+
+impl Operator {
+    fn test(&self, ordering: Option<Ordering>) -> bool {
+        match (self, ordering) {
+            (_, None) => false,
+            (Operator::Eq, Some(x)) => x == Ordering::Equal,
+            (Operator::Ge, Some(x)) => x != Ordering::Less,
+            (Operator::Gt, Some(x)) => x == Ordering::Greater,
+            (Operator::Le, Some(x)) => x != Ordering::Greater,
+            (Operator::Lt, Some(x)) => x == Ordering::Less,
+            (Operator::Ne, Some(x)) => x != Ordering::Equal,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Literal {
+    Number(f64),
+    Text(String),
+}
+
+// comparison of a shape attribute to a literal (e.g. "STATEFP10 == '08'")
+//  - quoted literals compare as text and others numerically
+#[derive(Clone, Debug, PartialEq)]
+pub struct Filter {
+    field: String,
+    literal: Literal,
+    operator: Operator,
+}
+
+impl Filter {
+    pub fn field(&self) -> &str {
+        &self.field
+    }
+
+    // test an attribute value - non-numeric values never match numbers
+    pub fn matches(&self, value: &str) -> bool {
+        match &self.literal {
+            Literal::Number(x) => match value.trim().parse::<f64>() {
+                Ok(value) => self.operator.test(value.partial_cmp(x)),
+                Err(_) => false,
+            },
+            Literal::Text(x) =>
+                self.operator.test(Some(value.cmp(x.as_str()))),
+        }
+    }
+}
+
+impl FromStr for Filter {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let error = || format!("invalid filter '{}' - expected \
+            'field op value' with op '==', '!=', '<', '<=', '>', or '>='", s);
+
+        // split on the first operator
+        let index = s.find(|x: char| "=!<>".contains(x))
+            .ok_or_else(error)?;
+        let (operator, len) = match s[index..].get(..2) {
+            Some("==") => (Operator::Eq, 2),
+            Some("!=") => (Operator::Ne, 2),
+            Some("<=") => (Operator::Le, 2),
+            Some(">=") => (Operator::Ge, 2),
+            _ if s[index..].starts_with('<') => (Operator::Lt, 1),
+            _ if s[index..].starts_with('>') => (Operator::Gt, 1),
+            _ => return Err(error()),
+        };
+
+        let (field, value) = (s[..index].trim(), s[index + len..].trim());
+        if field.is_empty() || value.is_empty() {
+            return Err(error());
+        }
+
+        let literal = match value.chars().next() {
+            Some(quote) if (quote == '\'' || quote == '"')
+                    && value.len() >= 2 && value.ends_with(quote) =>
+                Literal::Text(value[1..value.len() - 1].to_string()),
+            _ => match value.parse::<f64>() {
+                Ok(x) => Literal::Number(x),
+                Err(_) => Literal::Text(value.to_string()),
+            },
+        };
+
+        Ok(Filter {
+            field: field.to_string(),
+            literal: literal,
+            operator: operator,
+        })
+    }
+}
+
+// read a csv with id and wkt columns
+fn read_csv(path: &PathBuf, id_field: &Option<String>)
+        -> Result<Vec<(String, Shape)>, Box<dyn Error>> {
+    let mut reader = csv::Reader::from_path(path)?;
+
+    // identify id and wkt columns
+    let id_field = id_field.as_ref().map(|x| x.as_str()).unwrap_or("id");
+    let headers = reader.headers()?.clone();
+    let id_index = match headers.iter().position(|x| x == id_field) {
+        Some(index) => index,
+        None => return Err(format!(
+            "failed to identify shape id column '{}'", id_field).into()),
+    };
+
+    let wkt_index = match headers.iter().position(|x| x == "wkt") {
+        Some(index) => index,
+        None => return Err("failed to identify 'wkt' column".into()),
+    };
+
+    // iterate over records
+    let mut shapes = Vec::new();
+    for result in reader.records() {
+        let record = result?;
+        let id = record[id_index].to_string();
+
+        let wkt = Wkt::<f64>::from_str(&record[wkt_index])
+            .map_err(|e| format!("failed to parse wkt of '{}': {}", id, e))?;
+        let geometry: Geometry<f64> = wkt.try_into()
+            .map_err(|e| format!("failed to convert wkt of '{}': {:?}", id, e))?;
+
+        let shape = match Shape::from_geometry(geometry) {
+            Some(shape) => shape,
+            None => return Err(format!(
+                "unsupported geometry type for shape '{}'", id).into()),
+        };
+
+        shapes.push((id, shape));
+    }
+
+    Ok(shapes)
+}
+
+fn read_geojson(path: &PathBuf, id_field: &Option<String>,
+        filters: &[Filter])
+        -> Result<Vec<(String, Shape)>, Box<dyn Error>> {
+    let mut shapes = Vec::new();
+
+    // parse feature collection
+    let geojson = std::fs::read_to_string(path)?.parse::<GeoJson>()?;
+    let feature_collection = match geojson {
+        GeoJson::FeatureCollection(feature_collection) => feature_collection,
+        _ => return Err("geojson must contain a feature collection".into()),
+    };
+
+    // iterate over features
+    for feature in feature_collection.features {
+        // skip features without matching properties
+        let matched = filters.iter().all(|filter| {
+            let value = match feature.property(filter.field()) {
+                Some(serde_json::Value::Bool(x)) => x.to_string(),
+                Some(serde_json::Value::Number(x)) => x.to_string(),
+                Some(serde_json::Value::String(x)) => x.to_string(),
+                _ => return false,
+            };
+
+            filter.matches(&value)
+        });
+
+        if !matched {
+            continue;
+        }
+
+        // parse shape id - defaulting to the feature id
+        let id = match id_field {
+            Some(id_field) => match feature.property(id_field) {
+                Some(serde_json::Value::String(id)) => id.to_string(),
+                Some(serde_json::Value::Number(id)) => id.to_string(),
+                Some(x) => return Err(format!(
+                    "unsupported property type: {}", x).into()),
+                None => return Err(format!(
+                    "failed to identify shape id property '{}'",
+                    id_field).into()),
+            },
+            None => match &feature.id {
+                Some(geojson::feature::Id::String(id)) => id.to_string(),
+                Some(geojson::feature::Id::Number(id)) => id.to_string(),
+                None => return Err("failed to identify shape id".into()),
+            },
+        };
+
+        // parse geometry
+        let geometry: Geometry<f64> = match feature.geometry {
+            Some(geometry) => geometry.value.try_into()?,
+            None => continue,
+        };
+
+        let shape = match Shape::from_geometry(geometry) {
+            Some(shape) => shape,
+            None => return Err(format!(
+                "unsupported geometry type for shape '{}'", id).into()),
+        };
+
+        shapes.push((id, shape));
+    }
+
+    Ok(shapes)
+}
+
+fn read_flatgeobuf(path: &PathBuf, id_field: &Option<String>,
+        filters: &[Filter], extent: Option<&[f64; 4]>)
+        -> Result<Vec<(String, Shape)>, Box<dyn Error>> {
+    // features are unidentified so the id must be a property
+    let id_field = id_field.as_ref()
+        .ok_or("flatgeobuf shapes require an id field")?;
+
+    let mut shapes = Vec::new();
+    let mut reader = crate::fgb::Reader::open(path)?;
+    for feature in reader.features(extent)? {
+        // skip features without matching properties
+        let matched = filters.iter().all(|filter|
+            match feature.properties.get(filter.field()) {
+                Some(value) => filter.matches(value),
+                None => false,
+            });
+
+        if !matched {
+            continue;
+        }
+
+        let id = match feature.properties.get(id_field) {
+            Some(id) => id.trim().to_string(),
+            None => return Err(format!(
+                "failed to identify shape id property '{}'",
+                id_field).into()),
+        };
+
+        if let Some(multipolygon) = feature.geometry {
+            shapes.push((id, Shape::Polygons(multipolygon)));
+        }
+    }
+
+    Ok(shapes)
+}
+
+fn read_geopackage(path: &PathBuf, id_field: &Option<String>,
+        layer: &Option<String>)
+        -> Result<Vec<(String, Shape)>, Box<dyn Error>> {
+    let connection =
+        Connection::open_with_flags(path, OpenFlags::SQLITE_OPEN_READ_ONLY)?;
+
+    let layer = geopackage_layer(&connection, layer)?;
+
+    // identify geometry column
+    let geometry_column: String = connection.query_row(
+        "SELECT column_name FROM gpkg_geometry_columns WHERE table_name = ?",
+        &[&layer], |row| row.get(0))?;
+
+    // identify id column - defaulting to the primary key
+    let id_column = match id_field {
+        Some(id_field) => id_field.to_string(),
+        None => {
+            let mut statement = connection.prepare(
+                &format!("PRAGMA table_info(\"{}\")", layer))?;
+            let mut rows = statement.query(rusqlite::NO_PARAMS)?;
+
+            let mut id_column = None;
+            while let Some(row) = rows.next()? {
+                if row.get::<_, i64>(5)? != 0 {
+                    id_column = Some(row.get::<_, String>(1)?);
+                    break;
+                }
+            }
+
+            match id_column {
+                Some(id_column) => id_column,
+                None => return Err(format!(
+                    "failed to identify primary key of layer '{}'",
+                    layer).into()),
+            }
+        },
+    };
+
+    // iterate over features
+    let mut shapes = Vec::new();
+    let mut statement = connection.prepare(&format!(
+        "SELECT \"{}\", \"{}\" FROM \"{}\"",
+        id_column, geometry_column, layer))?;
+    let mut rows = statement.query(rusqlite::NO_PARAMS)?;
+
+    while let Some(row) = rows.next()? {
+        let id = match row.get::<_, SqlValue>(0)? {
+            SqlValue::Integer(id) => id.to_string(),
+            SqlValue::Real(id) => format_number(id),
+            SqlValue::Text(id) => id,
+            x => return Err(format!(
+                "unsupported column type: {:?}", x).into()),
+        };
+
+        let blob = match row.get::<_, SqlValue>(1)? {
+            SqlValue::Blob(blob) => blob,
+            SqlValue::Null => continue,
+            x => return Err(format!(
+                "unsupported geometry type: {:?}", x).into()),
+        };
+
+        let multipolygon = match parse_geopackage_geometry(&blob)? {
+            Some(multipolygon) => multipolygon,
+            None => continue,
+        };
+
+        shapes.push((id, Shape::Polygons(multipolygon)));
+    }
+
+    Ok(shapes)
+}
+
+// identify geopackage layer - defaulting to the first feature table
+fn geopackage_layer(connection: &Connection, layer: &Option<String>)
+        -> Result<String, Box<dyn Error>> {
+    match layer {
+        Some(layer) => Ok(layer.to_string()),
+        None => Ok(connection.query_row("SELECT table_name FROM gpkg_contents
+                WHERE data_type = 'features' ORDER BY table_name LIMIT 1",
+            rusqlite::NO_PARAMS, |row| row.get::<_, String>(0))?),
+    }
+}
+
+// parse a geopackage binary geometry - returns None for empty geometries
+fn parse_geopackage_geometry(blob: &[u8])
+        -> Result<Option<MultiPolygon<f64>>, Box<dyn Error>> {
+    if blob.len() < 8 || &blob[0..2] != b"GP" {
+        return Err("invalid geopackage geometry header".into());
+    }
+
+    // skip header - magic, version, flags, srs id, and envelope
+    let flags = blob[3];
+    let envelope_len = match (flags >> 1) & 0x07 {
+        0 => 0,
+        1 => 32,
+        2 | 3 => 48,
+        4 => 64,
+        x => return Err(format!(
+            "invalid geopackage envelope indicator '{}'", x).into()),
+    };
+
+    if flags & 0x10 != 0 {
+        return Ok(None);
+    }
+
+    let mut wkb = Wkb { buffer: blob, offset: 8 + envelope_len };
+    match wkb.read_geometry()? {
+        Geometry::Polygon(polygon) => Ok(Some(MultiPolygon(vec![polygon]))),
+        Geometry::MultiPolygon(multipolygon) => Ok(Some(multipolygon)),
+        _ => Err("unsupported geopackage geometry type".into()),
+    }
+}
+
+// minimal well-known binary reader for polygonal geometries
+struct Wkb<'a> {
+    buffer: &'a [u8],
+    offset: usize,
+}
+
+impl<'a> Wkb<'a> {
+    fn read_bytes(&mut self, len: usize) -> Result<&'a [u8], Box<dyn Error>> {
+        if self.offset + len > self.buffer.len() {
+            return Err("unexpected end of wkb geometry".into());
+        }
+
+        let bytes = &self.buffer[self.offset..self.offset + len];
+        self.offset += len;
+        Ok(bytes)
+    }
+
+    fn read_u32(&mut self, little_endian: bool)
+            -> Result<u32, Box<dyn Error>> {
+        let mut bytes = [0u8; 4];
+        bytes.copy_from_slice(self.read_bytes(4)?);
+        match little_endian {
+            true => Ok(u32::from_le_bytes(bytes)),
+            false => Ok(u32::from_be_bytes(bytes)),
+        }
+    }
+
+    fn read_f64(&mut self, little_endian: bool)
+            -> Result<f64, Box<dyn Error>> {
+        let mut bytes = [0u8; 8];
+        bytes.copy_from_slice(self.read_bytes(8)?);
+        match little_endian {
+            true => Ok(f64::from_le_bytes(bytes)),
+            false => Ok(f64::from_be_bytes(bytes)),
+        }
+    }
+
+    fn read_geometry(&mut self) -> Result<Geometry<f64>, Box<dyn Error>> {
+        let little_endian = self.read_bytes(1)?[0] == 1;
+        let geometry_type = self.read_u32(little_endian)?;
+
+        // identify coordinate dimensions (iso and extended wkb)
+        let mut dimensions = match (geometry_type % 10000) / 1000 {
+            1 | 2 => 3,
+            3 => 4,
+            _ => 2,
+        };
+
+        if geometry_type & 0x80000000 != 0 {
+            dimensions += 1;
+        }
+
+        if geometry_type & 0x40000000 != 0 {
+            dimensions += 1;
+        }
+
+        match (geometry_type & 0x0fffffff) % 1000 {
+            3 => Ok(Geometry::Polygon(
+                self.read_polygon(little_endian, dimensions)?)),
+            6 => {
+                let count = self.read_u32(little_endian)?;
+                let mut polygons = Vec::new();
+                for _ in 0..count {
+                    match self.read_geometry()? {
+                        Geometry::Polygon(polygon) => polygons.push(polygon),
+                        _ => return Err(
+                            "invalid wkb multipolygon member".into()),
+                    }
+                }
+
+                Ok(Geometry::MultiPolygon(MultiPolygon(polygons)))
+            },
+            x => Err(format!("unsupported wkb geometry type '{}'", x).into()),
+        }
+    }
+
+    fn read_polygon(&mut self, little_endian: bool, dimensions: usize)
+            -> Result<Polygon<f64>, Box<dyn Error>> {
+        let mut rings = Vec::new();
+        for _ in 0..self.read_u32(little_endian)? {
+            let mut coordinates = Vec::new();
+            for _ in 0..self.read_u32(little_endian)? {
+                let x = self.read_f64(little_endian)?;
+                let y = self.read_f64(little_endian)?;
+
+                // skip z and m values
+                for _ in 2..dimensions {
+                    self.read_f64(little_endian)?;
+                }
+
+                coordinates.push(Coordinate { x: x, y: y });
+            }
+
+            rings.push(LineString(coordinates));
+        }
+
+        if rings.is_empty() {
+            return Err("invalid wkb polygon with no rings".into());
+        }
+
+        let exterior = rings.remove(0);
+        Ok(Polygon::new(exterior, rings))
+    }
+}
+
+fn read_shapefile(path: &PathBuf, id_field: &Option<String>,
+        filters: &[Filter])
+        -> Result<Vec<(String, Shape)>, Box<dyn Error>> {
+    let mut shapes = Vec::new();
+
+    // open shapefile reader and iterator over shapes of any type
+    let reader = Reader::from_path(path)?;
+    let iterator = reader.iter_shapes_and_records()?;
+
+    // iterate over shapefile
+    for result in iterator {
+        let (shape, record) = result?;
+
+        // skip records without matching fields - null fields never match
+        let mut matched = true;
+        for filter in filters.iter() {
+            let value = match record.get(filter.field()) {
+                Some(value) => format_field(value),
+                None => return Err(format!(
+                    "failed to identify filter field '{}'",
+                    filter.field()).into()),
+            };
+
+            matched &= value.map(|x| filter.matches(&x)).unwrap_or(false);
+        }
+
+        if !matched {
+            continue;
+        }
+
+        // parse record metadata
+        let id = match id_field {
+            Some(id_field) => parse_field(&record, id_field)?,
+            None => {
+                let statefp = parse_field(&record, "STATEFP10")?;
+                let countyfp = parse_field(&record, "COUNTYFP10")?;
+                format!("G{}0{}0", statefp, countyfp)
+            },
+        };
+
+        let geometry: Geometry<f64> = match shape {
+            shapefile::Shape::NullShape => continue,
+            shape => shape.try_into()?,
+        };
+
+        let shape = match Shape::from_geometry(geometry) {
+            Some(shape) => shape,
+            None => return Err(format!(
+                "unsupported geometry type for shape '{}'", id).into()),
+        };
+
+        shapes.push((id, shape));
+    }
+
+    Ok(shapes)
+}
+
+fn parse_field(record: &HashMap<String, FieldValue>, name: &str) -> Result<String, Box<dyn Error>> {
+    match record.get(name) {
+        Some(value) => match format_field(value) {
+            Some(id) => Ok(id),
+            None => Err(format!("unsupported field type: {}", value).into()),
+        },
+        None => Err(format!("failed to identify shape id field '{}'",
+            name).into()),
+    }
+}
+
+// format a dbase field value - None for nulls and unsupported types
+fn format_field(value: &FieldValue) -> Option<String> {
+    match value {
+        FieldValue::Character(Some(x)) => Some(x.trim().to_string()),
+        FieldValue::Integer(x) => Some(x.to_string()),
+        FieldValue::Numeric(Some(x)) => Some(format_number(*x)),
+        FieldValue::Double(x) => Some(format_number(*x)),
+        FieldValue::Float(Some(x)) => Some(format_number(*x as f64)),
+        _ => None,
+    }
+}
+
+// format integral numeric ids without a decimal point
+fn format_number(value: f64) -> String {
+    match value.fract() == 0.0 {
+        true => format!("{}", value as i64),
+        false => format!("{}", value),
+    }
+}
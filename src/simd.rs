@@ -0,0 +1,124 @@
+// vectorized reductions over contiguous cell values and weights
+//  - avx is selected at runtime on x86_64 with a scalar fallback
+
+// min, max, and weighted sums of a set of values
+pub struct Moments {
+    pub max: f32,
+    pub min: f32,
+    pub sum: f32,
+    pub weight: f32,
+}
+
+// compute moments of values weighted by weights (equal lengths)
+pub fn moments(values: &[f32], weights: &[f32]) -> Moments {
+    #[cfg(target_arch = "x86_64")]
+    {
+        if is_x86_feature_detected!("avx") {
+            return unsafe { moments_avx(values, weights) };
+        }
+    }
+
+    moments_scalar(values, weights)
+}
+
+// compute the weighted sum of squared deviations from mean
+pub fn squared_deviation(values: &[f32], weights: &[f32], mean: f32) -> f32 {
+    #[cfg(target_arch = "x86_64")]
+    {
+        if is_x86_feature_detected!("avx") {
+            return unsafe { squared_deviation_avx(values, weights, mean) };
+        }
+    }
+
+    squared_deviation_scalar(values, weights, mean)
+}
+
+fn moments_scalar(values: &[f32], weights: &[f32]) -> Moments {
+    let mut moments = Moments {
+        max: f32::MIN,
+        min: f32::MAX,
+        sum: 0.0,
+        weight: 0.0,
+    };
+
+    for (value, weight) in values.iter().zip(weights.iter()) {
+        moments.max = moments.max.max(*value);
+        moments.min = moments.min.min(*value);
+        moments.sum += value * weight;
+        moments.weight += weight;
+    }
+
+    moments
+}
+
+fn squared_deviation_scalar(values: &[f32], weights: &[f32],
+        mean: f32) -> f32 {
+    values.iter().zip(weights.iter())
+        .map(|(value, weight)| weight * (value - mean) * (value - mean))
+        .sum()
+}
+
+#[cfg(target_arch = "x86_64")]
+#[target_feature(enable = "avx")]
+unsafe fn moments_avx(values: &[f32], weights: &[f32]) -> Moments {
+    use std::arch::x86_64::*;
+
+    let len = std::cmp::min(values.len(), weights.len());
+    let lanes = len / 8 * 8;
+
+    let mut max = _mm256_set1_ps(f32::MIN);
+    let mut min = _mm256_set1_ps(f32::MAX);
+    let mut sum = _mm256_setzero_ps();
+    let mut weight = _mm256_setzero_ps();
+    for k in (0..lanes).step_by(8) {
+        let v = _mm256_loadu_ps(values.as_ptr().add(k));
+        let w = _mm256_loadu_ps(weights.as_ptr().add(k));
+
+        max = _mm256_max_ps(max, v);
+        min = _mm256_min_ps(min, v);
+        sum = _mm256_add_ps(sum, _mm256_mul_ps(v, w));
+        weight = _mm256_add_ps(weight, w);
+    }
+
+    // reduce lanes and the remaining tail
+    let (mut max_lanes, mut min_lanes) = ([0f32; 8], [0f32; 8]);
+    let (mut sum_lanes, mut weight_lanes) = ([0f32; 8], [0f32; 8]);
+    _mm256_storeu_ps(max_lanes.as_mut_ptr(), max);
+    _mm256_storeu_ps(min_lanes.as_mut_ptr(), min);
+    _mm256_storeu_ps(sum_lanes.as_mut_ptr(), sum);
+    _mm256_storeu_ps(weight_lanes.as_mut_ptr(), weight);
+
+    let mut moments = moments_scalar(&values[lanes..len], &weights[lanes..len]);
+    for k in 0..8 {
+        moments.max = moments.max.max(max_lanes[k]);
+        moments.min = moments.min.min(min_lanes[k]);
+        moments.sum += sum_lanes[k];
+        moments.weight += weight_lanes[k];
+    }
+
+    moments
+}
+
+#[cfg(target_arch = "x86_64")]
+#[target_feature(enable = "avx")]
+unsafe fn squared_deviation_avx(values: &[f32], weights: &[f32],
+        mean: f32) -> f32 {
+    use std::arch::x86_64::*;
+
+    let len = std::cmp::min(values.len(), weights.len());
+    let lanes = len / 8 * 8;
+
+    let m = _mm256_set1_ps(mean);
+    let mut sum = _mm256_setzero_ps();
+    for k in (0..lanes).step_by(8) {
+        let d = _mm256_sub_ps(_mm256_loadu_ps(values.as_ptr().add(k)), m);
+        let w = _mm256_loadu_ps(weights.as_ptr().add(k));
+        sum = _mm256_add_ps(sum, _mm256_mul_ps(w, _mm256_mul_ps(d, d)));
+    }
+
+    let mut sum_lanes = [0f32; 8];
+    _mm256_storeu_ps(sum_lanes.as_mut_ptr(), sum);
+
+    sum_lanes.iter().sum::<f32>() + squared_deviation_scalar(
+        &values[lanes..len], &weights[lanes..len], mean)
+}
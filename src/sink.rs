@@ -0,0 +1,1124 @@
+use arrow::array::{ArrayRef, Float32Builder, Int64Builder, StringBuilder};
+use arrow::datatypes::{DataType, Field, Schema};
+use arrow::ipc::writer::StreamWriter;
+use arrow::record_batch::RecordBatch;
+use chrono::{Datelike, SecondsFormat, TimeZone, Utc};
+use chrono::format::{Item, StrftimeItems};
+use parquet::arrow::ArrowWriter;
+use postgres::{Client, NoTls};
+use rusqlite::Connection;
+use rusqlite::types::Value as SqlValue;
+use serde_json::{Map, Value};
+
+use crate::dataset::AttrValue;
+
+use std::collections::{BTreeMap, HashMap};
+use std::error::Error;
+use std::fs::File;
+use std::io::Write;
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::sync::Arc;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum OutputFormat {
+    Arrow,
+    Csv,
+    Ndjson,
+    Netcdf,
+    Parquet,
+}
+
+impl FromStr for OutputFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "arrow" => Ok(OutputFormat::Arrow),
+            "csv" => Ok(OutputFormat::Csv),
+            "ndjson" => Ok(OutputFormat::Ndjson),
+            "netcdf" => Ok(OutputFormat::Netcdf),
+            "parquet" => Ok(OutputFormat::Parquet),
+            x => Err(format!("unsupported output format '{}'", x)),
+        }
+    }
+}
+
+impl OutputFormat {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            OutputFormat::Arrow => "arrow",
+            OutputFormat::Csv => "csv",
+            OutputFormat::Ndjson => "ndjson",
+            OutputFormat::Netcdf => "nc",
+            OutputFormat::Parquet => "parquet",
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Partition {
+    Month,
+    Shape,
+    Year,
+}
+
+impl Partition {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Partition::Month => "month",
+            Partition::Shape => "shape",
+            Partition::Year => "year",
+        }
+    }
+
+    // (name, value) fields identifying the partition of a row
+    pub fn fields(&self, gis_join: &str, timestamp: i64)
+            -> Vec<(&'static str, String)> {
+        let datetime = Utc.timestamp(timestamp, 0);
+        match self {
+            Partition::Month => vec![("year", datetime.year().to_string()),
+                ("month", format!("{:02}", datetime.month()))],
+            Partition::Shape => vec![("gis_join", gis_join.to_string())],
+            Partition::Year => vec![("year", datetime.year().to_string())],
+        }
+    }
+
+    // relative path of a partition file - shapes are named by a file
+    //  name safe gis_join and periods use hive-style directories
+    //  (e.g. 'year=2020/month=01/data.csv')
+    pub fn path(&self, fields: &[(&'static str, String)], extension: &str)
+            -> PathBuf {
+        match self {
+            Partition::Shape => {
+                let name: String = fields[0].1.chars()
+                    .map(|x| match x.is_ascii_alphanumeric() || x == '-' {
+                        true => x,
+                        false => '_',
+                    }).collect();
+
+                PathBuf::from(format!("{}.{}", name, extension))
+            },
+            _ => fields.iter()
+                .map(|(name, value)| format!("{}={}", name, value))
+                .collect::<PathBuf>().join(format!("data.{}", extension)),
+        }
+    }
+}
+
+impl FromStr for Partition {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "month" => Ok(Partition::Month),
+            "shape" => Ok(Partition::Shape),
+            "year" => Ok(Partition::Year),
+            x => Err(format!("unsupported partition '{}'", x)),
+        }
+    }
+}
+
+// names of the text key columns and real value columns of rows
+#[derive(Clone, Debug)]
+pub struct Columns {
+    pub keys: Vec<String>,
+    pub values: Vec<String>,
+}
+
+impl Columns {
+    // value columns of rows keyed by 'gis_join'
+    pub fn new(values: Vec<String>) -> Columns {
+        Columns {
+            keys: vec!["gis_join".to_string()],
+            values: values,
+        }
+    }
+
+    pub fn keys(mut self, keys: Vec<String>) -> Self {
+        self.keys = keys;
+        self
+    }
+}
+
+pub trait Sink {
+    // write a row with a value for each key column
+    fn write_keyed_row(&mut self, keys: &[&str], timestamp: i64,
+        values: &[f32]) -> Result<(), Box<dyn Error>>;
+
+    fn write_row(&mut self, gis_join: &str, timestamp: i64, values: &[f32])
+            -> Result<(), Box<dyn Error>> {
+        self.write_keyed_row(&[gis_join], timestamp, values)
+    }
+
+    // persist all written rows so they survive a restart
+    fn flush(&mut self) -> Result<(), Box<dyn Error>>;
+
+    fn close(&mut self) -> Result<(), Box<dyn Error>>;
+}
+
+// open the sink for the provided output format and destination
+//  - appending resumes an existing output (csv and ndjson only)
+//  - the text format applies to csv and ndjson values
+pub fn open(format: OutputFormat, output: &Option<PathBuf>,
+        columns: Columns, batch_size: usize, text_format: &TextFormat,
+        append: bool)
+        -> Result<Box<dyn Sink + Send>, Box<dyn Error>> {
+    if append && (format == OutputFormat::Arrow
+            || format == OutputFormat::Netcdf
+            || format == OutputFormat::Parquet) {
+        return Err(format!("{:?} output does not support appending",
+            format).into());
+    }
+
+    match (format, output) {
+        (OutputFormat::Arrow, _) => Ok(Box::new(ArrowSink::new(
+            crate::open_output(output, false)?, &columns, batch_size)?)),
+        (OutputFormat::Csv, _) => Ok(Box::new(CsvSink::new(
+            crate::open_output(output, append)?, &columns, text_format,
+            !append)?)),
+        (OutputFormat::Ndjson, _) => Ok(Box::new(NdjsonSink::new(
+            crate::open_output(output, append)?, columns, text_format))),
+        (OutputFormat::Netcdf, _) =>
+            Err("netcdf output is only supported for shape dumps".into()),
+        (OutputFormat::Parquet, Some(path)) => Ok(Box::new(
+            ParquetSink::new(File::create(path)?, &columns, batch_size)?)),
+        (OutputFormat::Parquet, None) =>
+            Err("parquet output requires an output file".into()),
+    }
+}
+
+// open a sink writing a file of the output format for each partition
+//  within the directory
+pub fn open_partitioned(format: OutputFormat, partition: Partition,
+        directory: &PathBuf, columns: Columns, batch_size: usize,
+        text_format: &TextFormat, append: bool)
+        -> Result<Box<dyn Sink + Send>, Box<dyn Error>> {
+    if format == OutputFormat::Netcdf {
+        return Err("netcdf output does not support partitioning".into());
+    }
+
+    std::fs::create_dir_all(directory)?;
+
+    // resumed runs retain partitions listed by the existing manifest
+    let mut partitions = BTreeMap::new();
+    let manifest_path = directory.join("manifest.json");
+    if append && manifest_path.exists() {
+        let manifest: Value =
+            serde_json::from_reader(File::open(&manifest_path)?)?;
+        let entries = manifest["partitions"].as_array().cloned()
+            .unwrap_or_default();
+        for entry in entries {
+            // partitions removed by truncating to a checkpoint are dropped
+            match entry["path"].as_str().map(String::from) {
+                Some(path) if directory.join(&path).exists() => {
+                    partitions.insert(path, entry);
+                },
+                _ => {},
+            }
+        }
+    }
+
+    Ok(Box::new(PartitionSink {
+        append: append,
+        batch_size: batch_size,
+        columns: columns,
+        directory: directory.clone(),
+        format: format,
+        keys: HashMap::new(),
+        partition: partition,
+        partitions: partitions,
+        sinks: HashMap::new(),
+        text_format: text_format.clone(),
+        uses: 0,
+    }))
+}
+
+// open a sink writing a netcdf file with (shape, time) variables
+pub fn open_netcdf(output: &Option<PathBuf>, gis_joins: &[String],
+        columns: Vec<String>) -> Result<Box<dyn Sink + Send>, Box<dyn Error>> {
+    match output {
+        Some(path) => Ok(Box::new(NetcdfSink::new(path, gis_joins, columns)?)),
+        None => Err("netcdf output requires an output file".into()),
+    }
+}
+
+// open a sink which bulk-inserts rows into a postgres table
+pub fn open_postgres(url: &str, table: &str, columns: Columns,
+        batch_size: usize) -> Result<Box<dyn Sink + Send>, Box<dyn Error>> {
+    Ok(Box::new(PostgresSink::new(url, table, columns, batch_size)?))
+}
+
+// open a sink which inserts rows into a sqlite table
+pub fn open_sqlite(path: &PathBuf, table: &str, columns: Columns,
+        batch_size: usize) -> Result<Box<dyn Sink + Send>, Box<dyn Error>> {
+    Ok(Box::new(SqliteSink::new(path, table, columns, batch_size)?))
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum TimeFormat {
+    Custom(String),
+    Date,
+    Epoch,
+    Iso8601,
+}
+
+impl TimeFormat {
+    pub fn format(&self, timestamp: i64) -> String {
+        let datetime = Utc.timestamp(timestamp, 0);
+        match self {
+            TimeFormat::Custom(pattern) => datetime.format(pattern).to_string(),
+            TimeFormat::Date => datetime.format("%Y-%m-%d").to_string(),
+            TimeFormat::Epoch => timestamp.to_string(),
+            TimeFormat::Iso8601 =>
+                datetime.to_rfc3339_opts(SecondsFormat::Secs, true),
+        }
+    }
+}
+
+impl FromStr for TimeFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "date" => Ok(TimeFormat::Date),
+            "epoch" => Ok(TimeFormat::Epoch),
+            "iso8601" => Ok(TimeFormat::Iso8601),
+            x if x.contains('%') => match StrftimeItems::new(x)
+                    .any(|x| x == Item::Error) {
+                true => Err(format!("invalid strftime pattern '{}'", x)),
+                false => Ok(TimeFormat::Custom(x.to_string())),
+            },
+            x => Err(format!("unsupported time format '{}'", x)),
+        }
+    }
+}
+
+// formatting of values in text (csv and ndjson) output
+#[derive(Clone, Debug)]
+pub struct TextFormat {
+    pub date_columns: bool, // add year, month, and day columns
+    pub delimiter: u8, // csv field delimiter
+    pub null: String, // csv representation of undefined values
+    pub precision: usize, // decimal places of values
+    pub time_format: TimeFormat,
+}
+
+impl Default for TextFormat {
+    fn default() -> TextFormat {
+        TextFormat {
+            date_columns: false,
+            delimiter: b',',
+            null: "NaN".to_string(),
+            precision: 3,
+            time_format: TimeFormat::Epoch,
+        }
+    }
+}
+
+// parse a csv field delimiter - 'comma', 'tab', 'pipe', or any
+//  single ascii character
+pub fn parse_delimiter(s: &str) -> Result<u8, String> {
+    match s {
+        "comma" => Ok(b','),
+        "pipe" => Ok(b'|'),
+        "tab" | "\\t" => Ok(b'\t'),
+        x if x.len() == 1 && x.is_ascii() => Ok(x.as_bytes()[0]),
+        x => Err(format!("unsupported delimiter '{}'", x)),
+    }
+}
+
+// parse a csv null representation - 'empty', 'nan', or a literal string
+pub fn parse_null(s: &str) -> Result<String, String> {
+    match s {
+        "empty" => Ok(String::new()),
+        "nan" => Ok("NaN".to_string()),
+        x => Ok(x.to_string()),
+    }
+}
+
+// delimited rows - fields containing the delimiter or quotes are quoted
+pub struct CsvSink<W: Write> {
+    text_format: TextFormat,
+    writer: csv::Writer<W>,
+}
+
+impl<W: Write> CsvSink<W> {
+    pub fn new(writer: W, columns: &Columns, text_format: &TextFormat,
+            header: bool) -> Result<CsvSink<W>, Box<dyn Error>> {
+        let mut writer = csv::WriterBuilder::new()
+            .delimiter(text_format.delimiter).from_writer(writer);
+
+        // write csv header
+        if header {
+            for key in columns.keys.iter() {
+                writer.write_field(key)?;
+            }
+
+            writer.write_field("timestamp")?;
+            if text_format.date_columns {
+                for column in ["year", "month", "day"].iter() {
+                    writer.write_field(column)?;
+                }
+            }
+
+            for column in columns.values.iter() {
+                writer.write_field(column)?;
+            }
+            writer.write_record(None::<&[u8]>)?;
+        }
+
+        Ok(CsvSink {
+            text_format: text_format.clone(),
+            writer: writer,
+        })
+    }
+}
+
+impl<W: Write> Sink for CsvSink<W> {
+    fn write_keyed_row(&mut self, keys: &[&str], timestamp: i64,
+            values: &[f32]) -> Result<(), Box<dyn Error>> {
+        for key in keys.iter() {
+            self.writer.write_field(key)?;
+        }
+        self.writer.write_field(
+            self.text_format.time_format.format(timestamp))?;
+        if self.text_format.date_columns {
+            let datetime = Utc.timestamp(timestamp, 0);
+            self.writer.write_field(datetime.year().to_string())?;
+            self.writer.write_field(datetime.month().to_string())?;
+            self.writer.write_field(datetime.day().to_string())?;
+        }
+
+        for value in values.iter() {
+            match value.is_nan() {
+                true => self.writer.write_field(&self.text_format.null)?,
+                false => self.writer.write_field(format!("{:.*}",
+                    self.text_format.precision, value))?,
+            }
+        }
+        self.writer.write_record(None::<&[u8]>)?;
+
+        Ok(())
+    }
+
+    fn flush(&mut self) -> Result<(), Box<dyn Error>> {
+        self.writer.flush()?;
+        Ok(())
+    }
+
+    fn close(&mut self) -> Result<(), Box<dyn Error>> {
+        self.writer.flush()?;
+        Ok(())
+    }
+}
+
+pub struct NdjsonSink<W: Write> {
+    columns: Columns,
+    text_format: TextFormat,
+    writer: W,
+}
+
+impl<W: Write> NdjsonSink<W> {
+    pub fn new(writer: W, columns: Columns, text_format: &TextFormat)
+            -> NdjsonSink<W> {
+        NdjsonSink {
+            columns: columns,
+            text_format: text_format.clone(),
+            writer: writer,
+        }
+    }
+}
+
+impl<W: Write> Sink for NdjsonSink<W> {
+    fn write_keyed_row(&mut self, keys: &[&str], timestamp: i64,
+            values: &[f32]) -> Result<(), Box<dyn Error>> {
+        let mut object = Map::new();
+        for (name, key) in self.columns.keys.iter().zip(keys.iter()) {
+            object.insert(name.to_string(), Value::from(*key));
+        }
+
+        let timestamp_value = match &self.text_format.time_format {
+            TimeFormat::Epoch => Value::from(timestamp),
+            x => Value::from(x.format(timestamp)),
+        };
+        object.insert("timestamp".to_string(), timestamp_value);
+
+        if self.text_format.date_columns {
+            let datetime = Utc.timestamp(timestamp, 0);
+            object.insert("year".to_string(), Value::from(datetime.year()));
+            object.insert("month".to_string(), Value::from(datetime.month()));
+            object.insert("day".to_string(), Value::from(datetime.day()));
+        }
+
+        // round to precision - undefined statistics become null
+        let scale = 10f64.powi(self.text_format.precision as i32);
+        for (column, value) in self.columns.values.iter().zip(values.iter()) {
+            let value = (*value as f64 * scale).round() / scale;
+            object.insert(column.to_string(), Value::from(value));
+        }
+
+        serde_json::to_writer(&mut self.writer, &object)?;
+        writeln!(self.writer)?;
+
+        Ok(())
+    }
+
+    fn flush(&mut self) -> Result<(), Box<dyn Error>> {
+        self.writer.flush()?;
+        Ok(())
+    }
+
+    fn close(&mut self) -> Result<(), Box<dyn Error>> {
+        self.writer.flush()?;
+        Ok(())
+    }
+}
+
+// accumulates rows into arrow record batches
+pub struct BatchBuilder {
+    capacity: usize,
+    keys: Vec<StringBuilder>,
+    len: usize,
+    schema: Arc<Schema>,
+    timestamps: Int64Builder,
+    values: Vec<Float32Builder>,
+}
+
+impl BatchBuilder {
+    pub fn new(columns: &Columns, capacity: usize) -> BatchBuilder {
+        let mut fields: Vec<Field> = columns.keys.iter()
+            .map(|x| Field::new(x, DataType::Utf8, false)).collect();
+        fields.push(Field::new("timestamp", DataType::Int64, false));
+
+        for column in columns.values.iter() {
+            fields.push(Field::new(column, DataType::Float32, true));
+        }
+
+        BatchBuilder {
+            capacity: capacity,
+            keys: columns.keys.iter()
+                .map(|_| StringBuilder::new(capacity)).collect(),
+            len: 0,
+            schema: Arc::new(Schema::new(fields)),
+            timestamps: Int64Builder::new(capacity),
+            values: columns.values.iter()
+                .map(|_| Float32Builder::new(capacity)).collect(),
+        }
+    }
+
+    pub fn append(&mut self, keys: &[&str], timestamp: i64, values: &[f32])
+            -> Result<(), Box<dyn Error>> {
+        for (builder, key) in self.keys.iter_mut().zip(keys.iter()) {
+            builder.append_value(key)?;
+        }
+
+        self.timestamps.append_value(timestamp)?;
+
+        // store undefined statistics as nulls
+        for (builder, value) in self.values.iter_mut().zip(values.iter()) {
+            match value.is_nan() {
+                true => builder.append_null()?,
+                false => builder.append_value(*value)?,
+            }
+        }
+
+        self.len += 1;
+        Ok(())
+    }
+
+    pub fn finish(&mut self) -> Result<RecordBatch, Box<dyn Error>> {
+        let mut arrays: Vec<ArrayRef> = Vec::new();
+        for builder in self.keys.iter_mut() {
+            arrays.push(Arc::new(builder.finish()));
+        }
+
+        arrays.push(Arc::new(self.timestamps.finish()));
+
+        for builder in self.values.iter_mut() {
+            arrays.push(Arc::new(builder.finish()));
+        }
+
+        self.len = 0;
+        Ok(RecordBatch::try_new(self.schema.clone(), arrays)?)
+    }
+
+    pub fn is_full(&self) -> bool {
+        self.len >= self.capacity
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn schema(&self) -> Arc<Schema> {
+        self.schema.clone()
+    }
+}
+
+pub struct ArrowSink<W: Write> {
+    builder: BatchBuilder,
+    writer: StreamWriter<W>,
+}
+
+impl<W: Write> ArrowSink<W> {
+    pub fn new(writer: W, columns: &Columns, batch_size: usize)
+            -> Result<ArrowSink<W>, Box<dyn Error>> {
+        let builder = BatchBuilder::new(columns, batch_size);
+        let writer = StreamWriter::try_new(writer, &builder.schema())?;
+
+        Ok(ArrowSink { builder: builder, writer: writer })
+    }
+}
+
+impl<W: Write> Sink for ArrowSink<W> {
+    fn write_keyed_row(&mut self, keys: &[&str], timestamp: i64,
+            values: &[f32]) -> Result<(), Box<dyn Error>> {
+        self.builder.append(keys, timestamp, values)?;
+
+        // stream full record batches
+        if self.builder.is_full() {
+            let batch = self.builder.finish()?;
+            self.writer.write(&batch)?;
+        }
+
+        Ok(())
+    }
+
+    fn flush(&mut self) -> Result<(), Box<dyn Error>> {
+        Err("flushing is unsupported for arrow output".into())
+    }
+
+    fn close(&mut self) -> Result<(), Box<dyn Error>> {
+        if self.builder.len() != 0 {
+            let batch = self.builder.finish()?;
+            self.writer.write(&batch)?;
+        }
+
+        self.writer.finish()?;
+        Ok(())
+    }
+}
+
+// writes rows of (shape, time) variables a timestep at a time - rows
+//  must arrive ordered by timestamp and shapes without a row are left as
+//  fill values, so only the pending timestep is held in memory
+pub struct NetcdfSink {
+    columns: Vec<String>,
+    pending: Option<(i64, Vec<f32>)>,
+    shape_indices: HashMap<String, usize>,
+    time_index: usize,
+    timestamp: Option<i64>,
+    writer: crate::writer::Writer,
+}
+
+impl NetcdfSink {
+    pub fn new(path: &PathBuf, gis_joins: &[String], columns: Vec<String>)
+            -> Result<NetcdfSink, Box<dyn Error>> {
+        let mut writer = crate::writer::Writer::create(path)?;
+        writer.add_dimension("shape", gis_joins.len())?;
+        writer.add_dimension("time", 0)?;
+
+        // write shape ids as a string coordinate
+        writer.add_string_variable("gis_join", &["shape"])?;
+        for (i, gis_join) in gis_joins.iter().enumerate() {
+            writer.put_string("gis_join", gis_join, &[i])?;
+        }
+
+        writer.add_variable("time", &["time"], &[
+            ("units".to_string(), AttrValue::Str(
+                "seconds since 1970-01-01 00:00:00".to_string())),
+            ("calendar".to_string(), AttrValue::Str("standard".to_string())),
+        ])?;
+
+        for column in columns.iter() {
+            writer.add_variable(column, &["shape", "time"], &[
+                ("_FillValue".to_string(), AttrValue::Double(f64::NAN)),
+                ("coordinates".to_string(),
+                    AttrValue::Str("gis_join".to_string())),
+            ])?;
+        }
+
+        let shape_indices = gis_joins.iter().enumerate()
+            .map(|(i, x)| (x.to_string(), i)).collect();
+
+        Ok(NetcdfSink {
+            columns: columns,
+            pending: None,
+            shape_indices: shape_indices,
+            time_index: 0,
+            timestamp: None,
+            writer: writer,
+        })
+    }
+
+    // write the pending timestep - shapes without a row are left as fill
+    //  values
+    fn write_timestep(&mut self) -> Result<(), Box<dyn Error>> {
+        let (timestamp, values) = match self.pending.take() {
+            Some(pending) => pending,
+            None => return Ok(()),
+        };
+
+        let shape_count = self.shape_indices.len();
+
+        self.writer.put_values("time", &[timestamp as f64],
+            &[self.time_index], &[1])?;
+        for (k, column) in self.columns.iter().enumerate() {
+            let values: Vec<f64> = values[k * shape_count..]
+                .iter().take(shape_count).map(|x| *x as f64).collect();
+            self.writer.put_values(column, &values,
+                &[0, self.time_index], &[shape_count, 1])?;
+        }
+
+        self.time_index += 1;
+        self.timestamp = Some(timestamp);
+        Ok(())
+    }
+}
+
+impl Sink for NetcdfSink {
+    fn write_keyed_row(&mut self, keys: &[&str], timestamp: i64,
+            values: &[f32]) -> Result<(), Box<dyn Error>> {
+        let gis_join = match keys {
+            [gis_join] => gis_join,
+            _ => return Err("netcdf output requires a single key".into()),
+        };
+
+        let shape_index = *self.shape_indices.get(*gis_join)
+            .ok_or_else(|| format!("unknown shape '{}'", gis_join))?;
+        if self.timestamp.map(|x| timestamp <= x).unwrap_or(false) {
+            return Err(format!("timestamp {} written after its timestep \
+                was completed", timestamp).into());
+        }
+
+        // rows arrive ordered by timestamp so a later timestamp
+        //  completes the pending timestep
+        if self.pending.as_ref().map(|x| x.0 < timestamp).unwrap_or(false) {
+            self.write_timestep()?;
+        }
+
+        if self.pending.as_ref().map(|x| x.0 > timestamp).unwrap_or(false) {
+            return Err(format!("timestamp {} written out of order",
+                timestamp).into());
+        }
+
+        let (shape_count, len) = (self.shape_indices.len(),
+            self.columns.len() * self.shape_indices.len());
+        let (_, buffer) = self.pending
+            .get_or_insert_with(|| (timestamp, vec![f32::NAN; len]));
+        for (k, value) in values.iter().enumerate() {
+            buffer[k * shape_count + shape_index] = *value;
+        }
+
+        Ok(())
+    }
+
+    fn flush(&mut self) -> Result<(), Box<dyn Error>> {
+        self.write_timestep()
+    }
+
+    fn close(&mut self) -> Result<(), Box<dyn Error>> {
+        self.flush()
+    }
+}
+
+// maximum number of partition sinks held open at once
+const MAX_OPEN_PARTITIONS: usize = 256;
+
+// routes rows to a sink per partition - sinks are opened on first use
+//  and the least recently used csv and ndjson sinks are closed beyond
+//  MAX_OPEN_PARTITIONS and reopened for appending (arrow and parquet
+//  files can not be reopened so are held open until close)
+pub struct PartitionSink {
+    append: bool,
+    batch_size: usize,
+    columns: Columns,
+    directory: PathBuf,
+    format: OutputFormat,
+    keys: HashMap<PathBuf, String>, // partition fields by path
+    partition: Partition,
+    partitions: BTreeMap<String, Value>, // manifest entries by path
+    sinks: HashMap<PathBuf, (u64, Box<dyn Sink + Send>)>, // by last use
+    text_format: TextFormat,
+    uses: u64,
+}
+
+impl PartitionSink {
+    // write 'manifest.json' listing the fields and path of partitions
+    fn write_manifest(&self) -> Result<(), Box<dyn Error>> {
+        let partitions = self.partitions.values().cloned().collect();
+
+        let mut manifest = Map::new();
+        manifest.insert("format".to_string(),
+            Value::from(self.format.extension()));
+        manifest.insert("partition_by".to_string(),
+            Value::from(self.partition.name()));
+        manifest.insert("partitions".to_string(), Value::Array(partitions));
+
+        // replace atomically so readers never observe a partial manifest
+        let path = self.directory.join("manifest.json");
+        let tmp_path = self.directory.join("manifest.json.tmp");
+        serde_json::to_writer_pretty(File::create(&tmp_path)?,
+            &Value::Object(manifest))?;
+        std::fs::rename(&tmp_path, &path)?;
+        Ok(())
+    }
+}
+
+impl Sink for PartitionSink {
+    fn write_keyed_row(&mut self, keys: &[&str], timestamp: i64,
+            values: &[f32]) -> Result<(), Box<dyn Error>> {
+        // shapes are partitioned by the last (gis_join) key
+        let gis_join = keys.last().ok_or("rows require a key")?;
+        let fields = self.partition.fields(gis_join, timestamp);
+        let path = self.partition.path(&fields, self.format.extension());
+
+        // distinct partitions may share a sanitized file name
+        //  (e.g. gis_joins 'a/b' and 'a_b')
+        let key = fields.iter()
+            .map(|(name, value)| format!("{}={}", name, value))
+            .collect::<Vec<String>>().join("/");
+        match self.keys.get(&path) {
+            Some(x) if *x != key => return Err(format!("partitions '{}' \
+                and '{}' share the file '{}'", x, key, path.display())
+                .into()),
+            Some(_) => {},
+            None => {
+                // partitions of resumed runs are listed by the manifest
+                let entry = self.partitions
+                    .get(path.to_string_lossy().as_ref());
+                if let Some(entry) = entry {
+                    if fields.iter().any(|(name, value)|
+                            entry[*name].as_str() != Some(value.as_str())) {
+                        return Err(format!("partition '{}' shares the \
+                            file '{}' with a resumed partition", key,
+                            path.display()).into());
+                    }
+                }
+
+                self.keys.insert(path.clone(), key);
+            },
+        }
+
+        self.uses += 1;
+        if !self.sinks.contains_key(&path) {
+            let reopenable = match self.format {
+                OutputFormat::Csv | OutputFormat::Ndjson => true,
+                _ => false,
+            };
+
+            if reopenable && self.sinks.len() >= MAX_OPEN_PARTITIONS {
+                let evicted = self.sinks.iter().min_by_key(|(_, x)| x.0)
+                    .map(|(path, _)| path.clone());
+                if let Some((_, mut sink)) =
+                        evicted.and_then(|x| self.sinks.remove(&x)) {
+                    sink.close()?;
+                }
+            }
+
+            let full_path = self.directory.join(&path);
+            if let Some(parent) = full_path.parent() {
+                std::fs::create_dir_all(parent)?;
+            }
+
+            // resumed runs append to existing partitions only and
+            //  closed partitions are reopened for appending
+            let manifest_key = path.to_string_lossy().to_string();
+            let append = (self.append
+                    || self.partitions.contains_key(&manifest_key))
+                && full_path.exists();
+            let sink = open(self.format, &Some(full_path),
+                self.columns.clone(), self.batch_size, &self.text_format,
+                append)?;
+
+            let mut entry = Map::new();
+            for (name, value) in fields.into_iter() {
+                entry.insert(name.to_string(), Value::from(value));
+            }
+
+            entry.insert("path".to_string(),
+                Value::from(manifest_key.clone()));
+            self.partitions.insert(manifest_key, Value::Object(entry));
+            self.sinks.insert(path.clone(), (self.uses, sink));
+        }
+
+        let uses = self.uses;
+        let (used, sink) = self.sinks.get_mut(&path).unwrap();
+        *used = uses;
+        sink.write_keyed_row(keys, timestamp, values)
+    }
+
+    fn flush(&mut self) -> Result<(), Box<dyn Error>> {
+        for (_, sink) in self.sinks.values_mut() {
+            sink.flush()?;
+        }
+
+        self.write_manifest()
+    }
+
+    fn close(&mut self) -> Result<(), Box<dyn Error>> {
+        for (_, sink) in self.sinks.values_mut() {
+            sink.close()?;
+        }
+
+        self.write_manifest()
+    }
+}
+
+pub struct ParquetSink {
+    builder: BatchBuilder,
+    writer: ArrowWriter<File>,
+}
+
+impl ParquetSink {
+    pub fn new(file: File, columns: &Columns, batch_size: usize)
+            -> Result<ParquetSink, Box<dyn Error>> {
+        let builder = BatchBuilder::new(columns, batch_size);
+        let writer = ArrowWriter::try_new(file, builder.schema(), None)?;
+
+        Ok(ParquetSink { builder: builder, writer: writer })
+    }
+}
+
+impl Sink for ParquetSink {
+    fn write_keyed_row(&mut self, keys: &[&str], timestamp: i64,
+            values: &[f32]) -> Result<(), Box<dyn Error>> {
+        self.builder.append(keys, timestamp, values)?;
+
+        // write full record batches
+        if self.builder.is_full() {
+            let batch = self.builder.finish()?;
+            self.writer.write(&batch)?;
+        }
+
+        Ok(())
+    }
+
+    fn flush(&mut self) -> Result<(), Box<dyn Error>> {
+        Err("flushing is unsupported for parquet output".into())
+    }
+
+    fn close(&mut self) -> Result<(), Box<dyn Error>> {
+        if self.builder.len() != 0 {
+            let batch = self.builder.finish()?;
+            self.writer.write(&batch)?;
+        }
+
+        self.writer.close()?;
+        Ok(())
+    }
+}
+
+pub struct PostgresSink {
+    batch_size: usize,
+    buffer: Vec<u8>,
+    client: Client,
+    rows: usize,
+    statement: String,
+}
+
+impl PostgresSink {
+    pub fn new(url: &str, table: &str, columns: Columns,
+            batch_size: usize) -> Result<PostgresSink, Box<dyn Error>> {
+        let mut client = Client::connect(url, NoTls)?;
+
+        // create table if missing
+        let mut definitions: Vec<String> = columns.keys.iter()
+            .map(|x| format!("{} TEXT NOT NULL", quote_identifier(x)))
+            .collect();
+        definitions.push("timestamp BIGINT NOT NULL".to_string());
+        for column in columns.values.iter() {
+            definitions.push(format!("{} REAL", quote_identifier(column)));
+        }
+
+        client.batch_execute(&format!("CREATE TABLE IF NOT EXISTS {} ({})",
+            quote_identifier(table), definitions.join(", ")))?;
+
+        // compile copy statement
+        let mut names: Vec<String> = columns.keys.iter()
+            .map(|x| quote_identifier(x)).collect();
+        names.push("timestamp".to_string());
+        for column in columns.values.iter() {
+            names.push(quote_identifier(column));
+        }
+
+        let statement = format!("COPY {} ({}) FROM STDIN (FORMAT text)",
+            quote_identifier(table), names.join(", "));
+
+        Ok(PostgresSink {
+            batch_size: batch_size,
+            buffer: Vec::new(),
+            client: client,
+            rows: 0,
+            statement: statement,
+        })
+    }
+
+    // copy buffered rows - each batch is committed independently
+    fn copy_rows(&mut self) -> Result<(), Box<dyn Error>> {
+        let mut writer = self.client.copy_in(self.statement.as_str())?;
+        writer.write_all(&self.buffer)?;
+        writer.finish()?;
+
+        self.buffer.clear();
+        self.rows = 0;
+        Ok(())
+    }
+}
+
+impl Sink for PostgresSink {
+    fn write_keyed_row(&mut self, keys: &[&str], timestamp: i64,
+            values: &[f32]) -> Result<(), Box<dyn Error>> {
+        // escape copy text format delimiters within keys
+        for key in keys.iter() {
+            for c in key.chars() {
+                match c {
+                    '\\' => write!(self.buffer, "\\\\")?,
+                    '\t' => write!(self.buffer, "\\t")?,
+                    '\n' => write!(self.buffer, "\\n")?,
+                    '\r' => write!(self.buffer, "\\r")?,
+                    c => write!(self.buffer, "{}", c)?,
+                }
+            }
+
+            write!(self.buffer, "\t")?;
+        }
+        write!(self.buffer, "{}", timestamp)?;
+
+        // undefined statistics are written as nulls
+        for value in values.iter() {
+            match value.is_nan() {
+                true => write!(self.buffer, "\t\\N")?,
+                false => write!(self.buffer, "\t{}", value)?,
+            }
+        }
+        writeln!(self.buffer)?;
+
+        self.rows += 1;
+        if self.rows >= self.batch_size {
+            self.copy_rows()?;
+        }
+
+        Ok(())
+    }
+
+    fn flush(&mut self) -> Result<(), Box<dyn Error>> {
+        if self.rows != 0 {
+            self.copy_rows()?;
+        }
+
+        Ok(())
+    }
+
+    fn close(&mut self) -> Result<(), Box<dyn Error>> {
+        Sink::flush(self)
+    }
+}
+
+// quote a postgres or sqlite identifier - embedded quotes are doubled
+fn quote_identifier(name: &str) -> String {
+    format!("\"{}\"", name.replace('"', "\"\""))
+}
+
+pub struct SqliteSink {
+    batch_size: usize,
+    connection: Connection,
+    rows: Vec<Vec<SqlValue>>,
+    statement: String,
+}
+
+impl SqliteSink {
+    pub fn new(path: &PathBuf, table: &str, columns: Columns,
+            batch_size: usize) -> Result<SqliteSink, Box<dyn Error>> {
+        let connection = Connection::open(path)?;
+
+        // create table and index if missing
+        let mut definitions: Vec<String> = columns.keys.iter()
+            .map(|x| format!("{} TEXT NOT NULL", quote_identifier(x)))
+            .collect();
+        definitions.push("timestamp INTEGER NOT NULL".to_string());
+        for column in columns.values.iter() {
+            definitions.push(format!("{} REAL", quote_identifier(column)));
+        }
+
+        let keys: Vec<String> = columns.keys.iter()
+            .map(|x| quote_identifier(x)).collect();
+        let index = format!("{}_{}_timestamp", table, columns.keys.join("_"));
+        connection.execute_batch(&format!(
+            "CREATE TABLE IF NOT EXISTS {} ({});
+            CREATE INDEX IF NOT EXISTS {} ON {} ({}, timestamp);",
+            quote_identifier(table), definitions.join(", "),
+            quote_identifier(&index), quote_identifier(table),
+            keys.join(", ")))?;
+
+        // compile insert statement - columns are named so existing tables
+        //  with differently ordered columns are appended to correctly
+        let mut names = keys;
+        names.push("timestamp".to_string());
+        for column in columns.values.iter() {
+            names.push(quote_identifier(column));
+        }
+
+        let parameters = vec!["?"; names.len()];
+        let statement = format!("INSERT INTO {} ({}) VALUES ({})",
+            quote_identifier(table), names.join(", "), parameters.join(", "));
+
+        Ok(SqliteSink {
+            batch_size: batch_size,
+            connection: connection,
+            rows: Vec::new(),
+            statement: statement,
+        })
+    }
+
+    // insert buffered rows within a single transaction
+    fn insert_rows(&mut self) -> Result<(), Box<dyn Error>> {
+        let transaction = self.connection.transaction()?;
+        {
+            let mut statement = transaction.prepare_cached(&self.statement)?;
+            for row in self.rows.drain(..) {
+                statement.execute(rusqlite::params_from_iter(row))?;
+            }
+        }
+
+        transaction.commit()?;
+        Ok(())
+    }
+}
+
+impl Sink for SqliteSink {
+    fn write_keyed_row(&mut self, keys: &[&str], timestamp: i64,
+            values: &[f32]) -> Result<(), Box<dyn Error>> {
+        let mut row: Vec<SqlValue> = keys.iter()
+            .map(|x| SqlValue::Text(x.to_string())).collect();
+        row.push(SqlValue::Integer(timestamp));
+
+        // undefined statistics are written as nulls
+        for value in values.iter() {
+            match value.is_nan() {
+                true => row.push(SqlValue::Null),
+                false => row.push(SqlValue::Real(*value as f64)),
+            }
+        }
+
+        self.rows.push(row);
+        if self.rows.len() >= self.batch_size {
+            self.insert_rows()?;
+        }
+
+        Ok(())
+    }
+
+    fn flush(&mut self) -> Result<(), Box<dyn Error>> {
+        if !self.rows.is_empty() {
+            self.insert_rows()?;
+        }
+
+        Ok(())
+    }
+
+    fn close(&mut self) -> Result<(), Box<dyn Error>> {
+        Sink::flush(self)
+    }
+}
@@ -0,0 +1,630 @@
+use std::cmp::Ordering;
+use std::collections::BTreeMap;
+use std::str::FromStr;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Comparison {
+    Ge,
+    Gt,
+    Le,
+    Lt,
+}
+
+impl Comparison {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Comparison::Ge => "ge",
+            Comparison::Gt => "gt",
+            Comparison::Le => "le",
+            Comparison::Lt => "lt",
+        }
+    }
+
+    pub fn test(&self, value: f32, threshold: f32) -> bool {
+        match self {
+            Comparison::Ge => value >= threshold,
+            Comparison::Gt => value > threshold,
+            Comparison::Le => value <= threshold,
+            Comparison::Lt => value < threshold,
+        }
+    }
+}
+
+impl FromStr for Comparison {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "ge" => Ok(Comparison::Ge),
+            "gt" => Ok(Comparison::Gt),
+            "le" => Ok(Comparison::Le),
+            "lt" => Ok(Comparison::Lt),
+            x => Err(format!("unsupported comparison '{}'", x)),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum DegreeDay {
+    Cooling,
+    Growing,
+    Heating,
+}
+
+impl DegreeDay {
+    pub fn name(&self) -> &'static str {
+        match self {
+            DegreeDay::Cooling => "cdd",
+            DegreeDay::Growing => "gdd",
+            DegreeDay::Heating => "hdd",
+        }
+    }
+
+    // degrees above (cooling, growing) or below (heating) the base
+    pub fn compute(&self, value: f32, base: f32) -> f32 {
+        match self {
+            DegreeDay::Cooling | DegreeDay::Growing => (value - base).max(0.0),
+            DegreeDay::Heating => (base - value).max(0.0),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Stat {
+    Count,
+    CountThreshold(Comparison, f32),
+    DegreeDays(DegreeDay, f32),
+    Fraction(i64),
+    FractionThreshold(Comparison, f32),
+    Max,
+    Mean,
+    Median,
+    Min,
+    Mode,
+    Percentile(f32),
+    Std,
+    Sum,
+}
+
+impl Stat {
+    pub fn name(&self) -> String {
+        match self {
+            Stat::Count => "count".to_string(),
+            Stat::CountThreshold(comparison, threshold) =>
+                format!("count_{}_{}", comparison.name(), threshold),
+            Stat::DegreeDays(degree_day, base) =>
+                format!("{}_{}", degree_day.name(), base),
+            Stat::Fraction(class) => format!("frac{}", class),
+            Stat::FractionThreshold(comparison, threshold) =>
+                format!("frac_{}_{}", comparison.name(), threshold),
+            Stat::Max => "max".to_string(),
+            Stat::Mean => "mean".to_string(),
+            Stat::Median => "median".to_string(),
+            Stat::Min => "min".to_string(),
+            Stat::Mode => "mode".to_string(),
+            Stat::Percentile(p) => format!("p{}", p),
+            Stat::Std => "std".to_string(),
+            Stat::Sum => "sum".to_string(),
+        }
+    }
+
+    // indicates whether computing the statistic requires all cell values
+    pub fn requires_values(&self) -> bool {
+        match self {
+            Stat::CountThreshold(_, _) | Stat::DegreeDays(_, _)
+                | Stat::Fraction(_)
+                | Stat::FractionThreshold(_, _) | Stat::Median | Stat::Mode
+                | Stat::Percentile(_) => true,
+            _ => false,
+        }
+    }
+}
+
+impl FromStr for Stat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim() {
+            "count" => Ok(Stat::Count),
+            "max" => Ok(Stat::Max),
+            "mean" => Ok(Stat::Mean),
+            "median" => Ok(Stat::Median),
+            "min" => Ok(Stat::Min),
+            "mode" => Ok(Stat::Mode),
+            "std" => Ok(Stat::Std),
+            "sum" => Ok(Stat::Sum),
+            x if x.contains(':') => {
+                // threshold statistics (e.g. 'count_gt:32.2', 'frac_lt:0')
+                //  and degree days with a base temperature (e.g. 'gdd:10')
+                let (name, threshold) = x.split_at(x.find(':').unwrap());
+                let threshold = threshold[1..].parse::<f32>()
+                    .map_err(|_| format!("invalid threshold '{}'", x))?;
+
+                match name {
+                    "cdd" => return Ok(Stat::DegreeDays(
+                        DegreeDay::Cooling, threshold)),
+                    "gdd" => return Ok(Stat::DegreeDays(
+                        DegreeDay::Growing, threshold)),
+                    "hdd" => return Ok(Stat::DegreeDays(
+                        DegreeDay::Heating, threshold)),
+                    _ => {},
+                }
+
+                match name.split_at(std::cmp::min(name.len(), 5)) {
+                    ("count", comparison) if comparison.starts_with('_') =>
+                        Ok(Stat::CountThreshold(
+                            Comparison::from_str(&comparison[1..])?,
+                            threshold)),
+                    ("frac_", comparison) => Ok(Stat::FractionThreshold(
+                        Comparison::from_str(comparison)?, threshold)),
+                    _ => Err(format!("unsupported statistic '{}'", x)),
+                }
+            },
+            x if x.starts_with("frac") => match x[4..].parse::<i64>() {
+                Ok(class) => Ok(Stat::Fraction(class)),
+                _ => Err(format!("invalid class fraction '{}'", x)),
+            },
+            x if x.starts_with("p") => match x[1..].parse::<f32>() {
+                Ok(p) if p >= 0.0 && p <= 100.0 => Ok(Stat::Percentile(p)),
+                _ => Err(format!("invalid percentile '{}'", x)),
+            },
+            x => Err(format!("unsupported statistic '{}'", x)),
+        }
+    }
+}
+
+// precision of batch sums - f32 batches use vectorized kernels while
+//  f64 batches are exact for large shapes (running state is f64)
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Precision {
+    F32,
+    F64,
+}
+
+impl FromStr for Precision {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "f32" => Ok(Precision::F32),
+            "f64" => Ok(Precision::F64),
+            x => Err(format!("unsupported precision '{}'", x)),
+        }
+    }
+}
+
+pub struct Accumulator {
+    collect: bool,
+    count: usize,
+    max: f32,
+    mean: f64,
+    min: f32,
+    m2: f64,
+    precision: Precision,
+    selection: Vec<f32>,
+    sum: f64,
+    values: Vec<f32>,
+    weight: f64,
+    weights: Vec<f32>,
+}
+
+impl Accumulator {
+    pub fn new(collect: bool, precision: Precision) -> Accumulator {
+        Accumulator {
+            collect: collect,
+            count: 0,
+            max: f32::MIN,
+            mean: 0f64,
+            min: f32::MAX,
+            m2: 0f64,
+            precision: precision,
+            selection: Vec::new(),
+            sum: 0f64,
+            values: Vec::new(),
+            weight: 0f64,
+            weights: Vec::new(),
+        }
+    }
+
+    pub fn reset(&mut self) {
+        self.count = 0;
+        self.max = f32::MIN;
+        self.mean = 0f64;
+        self.min = f32::MAX;
+        self.m2 = 0f64;
+        self.sum = 0f64;
+        self.values.clear();
+        self.weight = 0f64;
+        self.weights.clear();
+    }
+
+    // update with a cell value weighted by its fractional coverage
+    pub fn update(&mut self, value: f32, weight: f32) {
+        if value < self.min {
+            self.min = value;
+        }
+
+        if value > self.max {
+            self.max = value;
+        }
+
+        // update weighted running mean and variance (west)
+        let (value, weight) = (value as f64, weight as f64);
+        self.count += 1;
+        self.sum += value * weight;
+        self.weight += weight;
+
+        let delta = value - self.mean;
+        self.mean += (weight / self.weight) * delta;
+        self.m2 += weight * delta * (value - self.mean);
+
+        if self.collect {
+            self.values.push(value as f32);
+            self.weights.push(weight as f32);
+        }
+    }
+
+    // update with a batch of cell values weighted by fractional coverage
+    //  using vectorized kernels - batches are merged with existing state
+    pub fn update_slice(&mut self, values: &[f32], weights: &[f32]) {
+        // compute batch (sum, weight, squared deviation)
+        let (min, max, sum, batch_weight, m2) = match self.precision {
+            Precision::F32 => {
+                let moments = crate::simd::moments(values, weights);
+                if moments.weight <= 0.0 {
+                    return;
+                }
+
+                let mean = moments.sum / moments.weight;
+                let m2 = crate::simd::squared_deviation(values, weights, mean);
+                (moments.min, moments.max, moments.sum as f64,
+                    moments.weight as f64, m2 as f64)
+            },
+            Precision::F64 => {
+                let (mut min, mut max) = (f32::MAX, f32::MIN);
+                let (mut sum, mut weight) = (0f64, 0f64);
+                for (value, w) in values.iter().zip(weights.iter()) {
+                    min = min.min(*value);
+                    max = max.max(*value);
+                    sum += *value as f64 * *w as f64;
+                    weight += *w as f64;
+                }
+
+                if weight <= 0.0 {
+                    return;
+                }
+
+                let mean = sum / weight;
+                let m2 = values.iter().zip(weights.iter())
+                    .map(|(value, w)| *w as f64
+                        * (*value as f64 - mean) * (*value as f64 - mean))
+                    .sum::<f64>();
+                (min, max, sum, weight, m2)
+            },
+        };
+
+        self.min = self.min.min(min);
+        self.max = self.max.max(max);
+
+        // merge weighted mean and variance (chan)
+        let mean = sum / batch_weight;
+        let weight = self.weight + batch_weight;
+        let delta = mean - self.mean;
+        self.mean += delta * batch_weight / weight;
+        self.m2 += m2 + delta * delta * self.weight * batch_weight / weight;
+
+        self.count += values.len();
+        self.sum += sum;
+        self.weight = weight;
+
+        if self.collect {
+            self.values.extend_from_slice(values);
+            self.weights.extend_from_slice(weights);
+        }
+    }
+
+    pub fn compute(&mut self, stat: &Stat) -> f32 {
+        match (self.count, stat) {
+            (0, Stat::Count) | (0, Stat::CountThreshold(_, _)) => return 0.0,
+            (0, _) => return f32::NAN,
+            _ => {},
+        }
+
+        match stat {
+            Stat::Count => self.count as f32,
+            Stat::CountThreshold(comparison, threshold) => self.values.iter()
+                .filter(|x| comparison.test(**x, *threshold)).count() as f32,
+            Stat::DegreeDays(degree_day, base) => (self.values.iter()
+                .zip(self.weights.iter())
+                .map(|x| (degree_day.compute(*x.0, *base) * *x.1) as f64)
+                .sum::<f64>() / self.weight) as f32,
+            Stat::FractionThreshold(comparison, threshold) => (self.values
+                .iter().zip(self.weights.iter())
+                .filter(|x| comparison.test(*x.0, *threshold))
+                .map(|x| *x.1 as f64).sum::<f64>() / self.weight) as f32,
+            Stat::Fraction(class) => (self.class_weights().iter()
+                .find(|x| x.0 == *class).map(|x| x.1).unwrap_or(0.0) as f64
+                / self.weight) as f32,
+            Stat::Max => self.max,
+            Stat::Mean => self.mean as f32,
+            Stat::Median => self.percentile(50.0),
+            Stat::Min => self.min,
+            Stat::Mode => self.class_weights().iter()
+                .fold(None, |mode: Option<&(i64, f32)>, x| match mode {
+                    Some(mode) if mode.1 >= x.1 => Some(mode),
+                    _ => Some(x),
+                }).map(|x| x.0 as f32).unwrap_or(f32::NAN),
+            Stat::Percentile(p) => self.percentile(*p),
+            Stat::Std => (self.m2 / self.weight).sqrt() as f32,
+            Stat::Sum => self.sum as f32,
+        }
+    }
+
+    // total weight of each integer category ordered by category
+    fn class_weights(&self) -> Vec<(i64, f32)> {
+        let mut class_weights = BTreeMap::new();
+        for (value, weight) in self.values.iter().zip(self.weights.iter()) {
+            *class_weights.entry(value.round() as i64).or_insert(0f32)
+                += weight;
+        }
+
+        class_weights.into_iter().collect()
+    }
+
+    fn percentile(&mut self, p: f32) -> f32 {
+        let compare = |a: &f32, b: &f32| a.partial_cmp(b)
+            .unwrap_or(Ordering::Equal);
+
+        // select over a copy so values remain aligned with weights
+        self.selection.clear();
+        self.selection.extend_from_slice(&self.values);
+
+        // linearly interpolate between closest ranks
+        let rank = (p / 100.0) * (self.values.len() - 1) as f32;
+        let (lower, upper) = (rank.floor() as usize, rank.ceil() as usize);
+
+        // select closest ranks in linear time rather than sorting - the
+        //  upper rank is the minimum of the values above the lower rank
+        let (_, lower_value, greater) =
+            self.selection.select_nth_unstable_by(lower, compare);
+        let lower_value = *lower_value;
+        let upper_value = match upper > lower {
+            true => greater.iter().cloned().fold(f32::MAX, f32::min),
+            false => lower_value,
+        };
+
+        let fraction = rank - lower as f32;
+        lower_value + (upper_value - lower_value) * fraction
+    }
+}
+
+// ordinary least squares fit of y over x from running sums
+#[derive(Clone, Debug, Default)]
+pub struct Regression {
+    count: usize,
+    x_sum: f64,
+    xx_sum: f64,
+    xy_sum: f64,
+    y_sum: f64,
+    yy_sum: f64,
+}
+
+impl Regression {
+    pub fn update(&mut self, x: f64, y: f64) {
+        self.count += 1;
+        self.x_sum += x;
+        self.xx_sum += x * x;
+        self.xy_sum += x * y;
+        self.y_sum += y;
+        self.yy_sum += y * y;
+    }
+
+    pub fn count(&self) -> usize {
+        self.count
+    }
+
+    // compute (slope, intercept, p-value) where the p-value is the two
+    //  sided t-test of a zero slope - undefined values are NaN
+    pub fn fit(&self) -> (f64, f64, f64) {
+        let n = self.count as f64;
+        let sxx = self.xx_sum - self.x_sum * self.x_sum / n;
+        let sxy = self.xy_sum - self.x_sum * self.y_sum / n;
+        let syy = self.yy_sum - self.y_sum * self.y_sum / n;
+        if self.count < 2 || sxx <= 0.0 {
+            return (f64::NAN, f64::NAN, f64::NAN);
+        }
+
+        let slope = sxy / sxx;
+        let intercept = (self.y_sum - slope * self.x_sum) / n;
+        if self.count < 3 {
+            return (slope, intercept, f64::NAN);
+        }
+
+        // t statistic of the slope with n - 2 degrees of freedom
+        let df = n - 2.0;
+        let residual = (syy - slope * sxy).max(0.0);
+        let p_value = match residual {
+            x if x == 0.0 => 0.0,
+            x => {
+                let t = slope / (x / df / sxx).sqrt();
+                incomplete_beta(df / 2.0, 0.5, df / (df + t * t))
+            },
+        };
+
+        (slope, intercept, p_value)
+    }
+}
+
+// regularized incomplete beta function I_x(a, b)
+fn incomplete_beta(a: f64, b: f64, x: f64) -> f64 {
+    if x <= 0.0 {
+        return 0.0;
+    } else if x >= 1.0 {
+        return 1.0;
+    }
+
+    let front = (ln_gamma(a + b) - ln_gamma(a) - ln_gamma(b)
+        + a * x.ln() + b * (1.0 - x).ln()).exp();
+
+    // the continued fraction converges quickly below (a + 1) / (a + b + 2)
+    match x < (a + 1.0) / (a + b + 2.0) {
+        true => front * beta_fraction(a, b, x) / a,
+        false => 1.0 - front * beta_fraction(b, a, 1.0 - x) / b,
+    }
+}
+
+// continued fraction of the incomplete beta function (modified lentz)
+fn beta_fraction(a: f64, b: f64, x: f64) -> f64 {
+    let tiny = 1e-300;
+    let mut c = 1.0;
+    let mut d = 1.0 - (a + b) * x / (a + 1.0);
+    d = 1.0 / if d.abs() < tiny { tiny } else { d };
+    let mut fraction = d;
+
+    for m in 1..200 {
+        let m = m as f64;
+        for numerator in [
+                m * (b - m) * x / ((a + 2.0 * m - 1.0) * (a + 2.0 * m)),
+                -(a + m) * (a + b + m) * x
+                    / ((a + 2.0 * m) * (a + 2.0 * m + 1.0))].iter() {
+            d = 1.0 + numerator * d;
+            d = 1.0 / if d.abs() < tiny { tiny } else { d };
+            c = 1.0 + numerator / c;
+            c = if c.abs() < tiny { tiny } else { c };
+            fraction *= d * c;
+        }
+
+        if (d * c - 1.0).abs() < 1e-12 {
+            break;
+        }
+    }
+
+    fraction
+}
+
+// natural log of the gamma function (lanczos approximation)
+fn ln_gamma(x: f64) -> f64 {
+    let coefficients = [76.18009172947146, -86.50532032941677,
+        24.01409824083091, -1.231739572450155,
+        0.1208650973866179e-2, -0.5395239384953e-5];
+
+    let tmp = x + 5.5;
+    let mut series = 1.000000000190015;
+    for (i, coefficient) in coefficients.iter().enumerate() {
+        series += coefficient / (x + 1.0 + i as f64);
+    }
+
+    (2.5066282746310005 * series / x).ln() - tmp + (x + 0.5) * tmp.ln()
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum CorrelationMethod {
+    Pearson,
+    Spearman,
+}
+
+impl FromStr for CorrelationMethod {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "pearson" => Ok(CorrelationMethod::Pearson),
+            "spearman" => Ok(CorrelationMethod::Spearman),
+            x => Err(format!("unsupported correlation method '{}'", x)),
+        }
+    }
+}
+
+// correlation between two features over shape cells at each timestep
+#[derive(Clone, Debug, PartialEq)]
+pub struct Correlation {
+    pub features: (String, String),
+    pub method: CorrelationMethod,
+}
+
+impl Correlation {
+    pub fn name(&self) -> String {
+        let method = match self.method {
+            CorrelationMethod::Pearson => "pearson",
+            CorrelationMethod::Spearman => "spearman",
+        };
+
+        format!("{}_{}_{}", method, self.features.0, self.features.1)
+    }
+}
+
+impl FromStr for Correlation {
+    type Err = String;
+
+    // parse 'method:feature:feature' (e.g. 'pearson:tmax:vpd')
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let fields: Vec<&str> = s.trim().split(':').collect();
+        match fields.len() {
+            3 => Ok(Correlation {
+                features: (fields[1].to_string(), fields[2].to_string()),
+                method: CorrelationMethod::from_str(fields[0])?,
+            }),
+            _ => Err(format!("invalid correlation '{}' - expected \
+                'method:feature:feature'", s)),
+        }
+    }
+}
+
+// coverage weighted correlation of paired cell values - spearman
+//  correlates the (tie averaged) ranks of each feature
+pub fn correlate(method: CorrelationMethod, x: &[f32], y: &[f32],
+        weights: &[f32]) -> f32 {
+    match method {
+        CorrelationMethod::Pearson => pearson(x, y, weights),
+        CorrelationMethod::Spearman =>
+            pearson(&ranks(x), &ranks(y), weights),
+    }
+}
+
+fn pearson(x: &[f32], y: &[f32], weights: &[f32]) -> f32 {
+    let weight: f64 = weights.iter().map(|x| *x as f64).sum();
+    if x.len() < 2 || weight <= 0.0 {
+        return f32::NAN;
+    }
+
+    let mean = |values: &[f32]| values.iter().zip(weights.iter())
+        .map(|(v, w)| *v as f64 * *w as f64).sum::<f64>() / weight;
+    let (x_mean, y_mean) = (mean(x), mean(y));
+
+    let (mut xx, mut xy, mut yy) = (0f64, 0f64, 0f64);
+    for ((a, b), w) in x.iter().zip(y.iter()).zip(weights.iter()) {
+        let (dx, dy) = (*a as f64 - x_mean, *b as f64 - y_mean);
+        xx += *w as f64 * dx * dx;
+        xy += *w as f64 * dx * dy;
+        yy += *w as f64 * dy * dy;
+    }
+
+    match xx > 0.0 && yy > 0.0 {
+        true => (xy / (xx * yy).sqrt()) as f32,
+        false => f32::NAN,
+    }
+}
+
+// ranks of values where ties are assigned their average rank
+fn ranks(values: &[f32]) -> Vec<f32> {
+    let mut order: Vec<usize> = (0..values.len()).collect();
+    order.sort_by(|a, b| values[*a].partial_cmp(&values[*b])
+        .unwrap_or(Ordering::Equal));
+
+    let mut ranks = vec![0f32; values.len()];
+    let mut i = 0;
+    while i < order.len() {
+        let mut j = i;
+        while j + 1 < order.len() && values[order[j + 1]] == values[order[i]] {
+            j += 1;
+        }
+
+        for index in order[i..=j].iter() {
+            ranks[*index] = (i + j) as f32 / 2.0;
+        }
+
+        i = j + 1;
+    }
+
+    ranks
+}
@@ -0,0 +1,154 @@
+use chrono::{DateTime, Utc};
+use structopt::StructOpt;
+
+use ncproj_rs::dataset::Dataset;
+use ncproj_rs::grid::{Grid, LonConvention};
+use ncproj_rs::writer::Writer;
+
+use std::error::Error;
+use std::path::PathBuf;
+
+#[derive(StructOpt)]
+pub struct Subset {
+    // crop to cells intersecting 'min_lon,min_lat,max_lon,max_lat'
+    #[structopt(long = "bbox", use_delimiter = true,
+        allow_hyphen_values = true)]
+    bbox: Vec<f64>,
+
+    // number of time intervals copied at once
+    #[structopt(short = "b", long = "buffer-size", default_value = "250")]
+    buffer_size: usize,
+
+    #[structopt(parse(from_os_str), index = 1)]
+    data_file: PathBuf,
+
+    // only copy timesteps at or before this datetime
+    #[structopt(long = "end", parse(try_from_str = ncproj_rs::cf::parse_datetime))]
+    end: Option<DateTime<Utc>>,
+
+    // crop to the bounding box of all cells in an index file
+    #[structopt(short = "i", long = "index-file", parse(from_os_str))]
+    index_file: Option<PathBuf>,
+
+    #[structopt(short = "o", long = "output", parse(from_os_str))]
+    output: PathBuf,
+
+    // only copy timesteps at or after this datetime
+    #[structopt(long = "start",
+        parse(try_from_str = ncproj_rs::cf::parse_datetime))]
+    start: Option<DateTime<Utc>>,
+}
+
+impl Subset {
+    pub fn execute(&self) -> Result<(), Box<dyn Error>> {
+        let reader = Dataset::open(&self.data_file)?;
+        let (y_name, x_name) =
+            ncproj_rs::grid::read_dimension_names(&reader)?;
+        let (y_len, x_len) = ncproj_rs::grid::read_dimensions(&reader)?;
+
+        // compute (min x, min y, max x, max y) cell ranges
+        let (x_min, y_min, x_max, y_max) = match (&self.index_file,
+                self.bbox.len()) {
+            (Some(_), 4) => return Err(
+                "bbox and index file are mutually exclusive".into()),
+            (Some(index_file), _) => {
+                let shapes = ncproj_rs::format::read_index(index_file)?;
+                shapes.values().flat_map(|x| x.iter())
+                    .fold((usize::MAX, usize::MAX, 0, 0), |b, (x, y, _)|
+                        (b.0.min(*x), b.1.min(*y), b.2.max(*x), b.3.max(*y)))
+            },
+            (None, 4) => {
+                // runs wrapping the longitude axis are copied within
+                //  their enclosing window
+                let (x_runs, y_runs) = Grid::open(&reader,
+                        LonConvention::Auto)?
+                    .cell_ranges(&[self.bbox[0], self.bbox[1],
+                        self.bbox[2], self.bbox[3]]);
+                let bounds = |runs: &[(usize, usize)]|
+                        match (runs.first(), runs.last()) {
+                    (Some(first), Some(last)) => (first.0, last.1 - 1),
+                    _ => (usize::MAX, 0),
+                };
+
+                let ((x_min, x_max), (y_min, y_max)) =
+                    (bounds(&x_runs), bounds(&y_runs));
+                (x_min, y_min, x_max, y_max)
+            },
+            (None, 0) => (0, 0, x_len.saturating_sub(1),
+                y_len.saturating_sub(1)),
+            (None, _) => return Err(
+                "bbox requires 'min_lon,min_lat,max_lon,max_lat'".into()),
+        };
+
+        if x_min > x_max || y_min > y_max {
+            return Err("subset does not contain any cells".into());
+        }
+
+        // compute time index range
+        let time_range = match reader.variable("time") {
+            Some(_) => Some(ncproj_rs::cf::time_range(
+                &ncproj_rs::cf::parse_times(&reader)?, self.start, self.end)),
+            None => None,
+        };
+
+        // compute the (start, count) of each dimension
+        let range = |name: &str, len: usize| match (name, time_range) {
+            (x, _) if x == x_name => (x_min, x_max - x_min + 1),
+            (y, _) if y == y_name => (y_min, y_max - y_min + 1),
+            ("time", Some((start_index, end_index))) =>
+                (start_index, end_index.saturating_sub(start_index)),
+            _ => (0, len),
+        };
+
+        // copy dimensions and attributes
+        let mut writer = Writer::create(&self.output)?;
+        for dimension in reader.dimensions() {
+            let (_, count) = range(&dimension.name(), dimension.len());
+            writer.add_dimension(&dimension.name(), count)?;
+        }
+
+        for (name, value) in reader.attributes() {
+            writer.add_attribute(&name, &value)?;
+        }
+
+        // copy variables a buffer of their first dimension at a time
+        for variable in reader.variables() {
+            let dimensions = variable.dimensions();
+            let names: Vec<String> =
+                dimensions.iter().map(|x| x.name()).collect();
+            let names: Vec<&str> = names.iter().map(|x| x.as_str()).collect();
+
+            writer.add_variable(&variable.name(), &names,
+                &variable.attributes())?;
+
+            let (mut start, mut count): (Vec<usize>, Vec<usize>) =
+                dimensions.iter().map(|x| range(&x.name(), x.len())).unzip();
+            if start.is_empty() {
+                let values = variable.values_at(&[], &[])?;
+                writer.put_values(&variable.name(),
+                    &values.iter().cloned().collect::<Vec<f64>>(), &[], &[])?;
+                continue;
+            }
+
+            let (first_start, first_count) = (start[0], count[0]);
+            let mut offset = 0;
+            while offset < first_count {
+                start[0] = first_start + offset;
+                count[0] = std::cmp::min(self.buffer_size,
+                    first_count - offset);
+
+                let values: Vec<f64> = variable.values_at(&start, &count)?
+                    .iter().cloned().collect();
+
+                let mut output_start = vec![0; start.len()];
+                output_start[0] = offset;
+                writer.put_values(&variable.name(),
+                    &values, &output_start, &count)?;
+
+                offset += count[0];
+            }
+        }
+
+        Ok(())
+    }
+}
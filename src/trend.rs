@@ -0,0 +1,117 @@
+use chrono::{DateTime, Utc};
+use structopt::StructOpt;
+
+use ncproj_rs::dumper::Dumper;
+use ncproj_rs::grid::CellWeight;
+use ncproj_rs::stat::{Regression, Stat};
+
+use std::error::Error;
+use std::io::Write;
+use std::path::PathBuf;
+
+const SECONDS_PER_YEAR: f64 = 365.2425 * 86400.0;
+
+#[derive(StructOpt)]
+pub struct Trend {
+    // number of time intervals to include in each buffer
+    #[structopt(short = "b", long = "buffer-size", default_value = "250")]
+    buffer_size: usize,
+
+    // scale cell weights by 'coslat' or spherical cell 'area' (km^2)
+    #[structopt(long = "cell-weight", default_value = "none")]
+    cell_weight: CellWeight,
+
+    // netcdf files, opendap urls, or s3 urls ('s3://bucket/key')
+    #[structopt(parse(from_os_str), index = 2)]
+    data_files: Vec<PathBuf>,
+
+    // only fit timesteps at or before this datetime
+    #[structopt(long = "end", parse(try_from_str = ncproj_rs::cf::parse_datetime))]
+    end: Option<DateTime<Utc>>,
+
+    #[structopt(parse(from_os_str), index = 1)]
+    index_file: PathBuf,
+
+    #[structopt(short = "o", long = "output", parse(from_os_str))]
+    output: Option<PathBuf>,
+
+    // suppress progress reporting
+    #[structopt(short = "q", long = "quiet")]
+    quiet: bool,
+
+    // only fit timesteps at or after this datetime
+    #[structopt(long = "start",
+        parse(try_from_str = ncproj_rs::cf::parse_datetime))]
+    start: Option<DateTime<Utc>>,
+
+    // statistic computed at each timestep to fit the trend over
+    #[structopt(short = "s", long = "stat", default_value = "mean")]
+    stat: Stat,
+
+    // number of threads computing statistics (default logical cpus)
+    #[structopt(short = "t", long = "thread-count")]
+    thread_count: Option<u8>,
+
+    // comma-separated list of variables to process (default all)
+    #[structopt(short = "v", long = "variables", use_delimiter = true)]
+    variables: Vec<String>,
+}
+
+impl Trend {
+    pub fn execute(&self) -> Result<(), Box<dyn Error>> {
+        let records = Dumper::new(self.index_file.clone(),
+                self.data_files.clone())
+            .buffer_size(self.buffer_size)
+            .cell_weight(self.cell_weight)
+            .end(self.end)
+            .start(self.start)
+            .stats(vec![self.stat])
+            .thread_count(self.thread_count
+                .unwrap_or_else(ncproj_rs::default_thread_count))
+            .variables(self.variables.clone())
+            .build()?;
+
+        let columns = records.columns().to_vec();
+        let gis_joins = records.gis_joins().to_vec();
+        let (start_index, end_index) = records.time_range();
+        let progress_bar = ncproj_rs::progress_bar(
+            (end_index.saturating_sub(start_index) * gis_joins.len()) as u64,
+            self.quiet);
+
+        // accumulate regression sums of each (shape, column) over years
+        //  since the epoch rather than retaining every timestep
+        let mut regressions =
+            vec![Regression::default(); gis_joins.len() * columns.len()];
+        for record in records {
+            let record = record?;
+
+            let x = record.timestamp as f64 / SECONDS_PER_YEAR;
+            for (k, value) in record.values.iter().enumerate() {
+                if !value.is_nan() {
+                    regressions[record.shape_index * columns.len() + k]
+                        .update(x, *value as f64);
+                }
+            }
+
+            progress_bar.inc(1);
+        }
+
+        progress_bar.finish();
+
+        // write csv of slopes (per year) and intercepts (at the epoch)
+        let mut writer = ncproj_rs::open_output(&self.output, false)?;
+        writeln!(writer, "gis_join,column,count,slope,intercept,p_value")?;
+        for (j, gis_join) in gis_joins.iter().enumerate() {
+            for (k, column) in columns.iter().enumerate() {
+                let regression = &regressions[j * columns.len() + k];
+                let (slope, intercept, p_value) = regression.fit();
+
+                writeln!(writer, "{},{},{},{:.6},{:.6},{:.6}", gis_join,
+                    column, regression.count(), slope, intercept, p_value)?;
+            }
+        }
+
+        writer.flush()?;
+        Ok(())
+    }
+}
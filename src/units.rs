@@ -0,0 +1,102 @@
+// physical units of variables and linear conversions between them
+//  - each unit is a scale and offset to the base unit of its quantity
+use crate::dataset::{AttrValue, Variable};
+
+use std::error::Error;
+use std::str::FromStr;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Quantity {
+    Length,
+    PrecipitationRate,
+    Pressure,
+    Speed,
+    Temperature,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Units {
+    name: String,
+    offset: f64,
+    quantity: Quantity,
+    scale: f64,
+}
+
+impl Units {
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn quantity(&self) -> Quantity {
+        self.quantity
+    }
+
+    // compute the (scale, offset) converting values in these units to
+    //  the target units - None if the quantities differ
+    pub fn conversion(&self, target: &Units) -> Option<(f64, f64)> {
+        match self.quantity == target.quantity {
+            true => Some((self.scale / target.scale,
+                (self.offset - target.offset) / target.scale)),
+            false => None,
+        }
+    }
+
+    // parse the units attribute of a variable - None if the attribute
+    //  is missing and an error if the units are unrecognized
+    pub fn read(variable: &Variable) -> Result<Option<Units>, Box<dyn Error>> {
+        match variable.attribute("units")? {
+            Some(AttrValue::Str(value)) => Ok(Some(Units::from_str(&value)?)),
+            _ => Ok(None),
+        }
+    }
+}
+
+impl FromStr for Units {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        // normalize case, degree symbols, and exponent notation
+        let normalized = s.trim().to_lowercase()
+            .replace('°', "deg").replace("**", "").replace('^', "");
+        let normalized: Vec<&str> = normalized.split_whitespace().collect();
+
+        let (quantity, scale, offset) = match normalized.join(" ").as_str() {
+            "m" | "meter" | "meters" => (Quantity::Length, 1.0, 0.0),
+            // water equivalent depth (1 kg m-2 is 1 mm)
+            "mm" | "kg m-2" | "kg/m2" => (Quantity::Length, 0.001, 0.0),
+            "cm" => (Quantity::Length, 0.01, 0.0),
+            "km" => (Quantity::Length, 1000.0, 0.0),
+            "in" | "inch" | "inches" => (Quantity::Length, 0.0254, 0.0),
+            "kg m-2 s-1" | "kg/m2/s" | "mm/s" | "mm s-1" =>
+                (Quantity::PrecipitationRate, 1.0, 0.0),
+            "mm/h" | "mm/hr" | "mm h-1" | "mm hr-1" =>
+                (Quantity::PrecipitationRate, 1.0 / 3600.0, 0.0),
+            "mm/day" | "mm/d" | "mm day-1" | "mm d-1" =>
+                (Quantity::PrecipitationRate, 1.0 / 86400.0, 0.0),
+            "in/day" | "in day-1" =>
+                (Quantity::PrecipitationRate, 25.4 / 86400.0, 0.0),
+            "pa" => (Quantity::Pressure, 1.0, 0.0),
+            "hpa" | "mb" | "mbar" => (Quantity::Pressure, 100.0, 0.0),
+            "kpa" => (Quantity::Pressure, 1000.0, 0.0),
+            "m/s" | "m s-1" => (Quantity::Speed, 1.0, 0.0),
+            "km/h" | "km h-1" => (Quantity::Speed, 1.0 / 3.6, 0.0),
+            "mph" => (Quantity::Speed, 0.44704, 0.0),
+            "knots" | "kt" => (Quantity::Speed, 1852.0 / 3600.0, 0.0),
+            "k" | "kelvin" | "degk" | "deg_k" | "degrees_k" =>
+                (Quantity::Temperature, 1.0, 0.0),
+            "c" | "degc" | "deg_c" | "degrees_c" | "celsius"
+                | "degree_celsius" | "degrees_celsius" =>
+                (Quantity::Temperature, 1.0, 273.15),
+            "f" | "degf" | "deg_f" | "degrees_f" | "fahrenheit" =>
+                (Quantity::Temperature, 5.0 / 9.0, 273.15 - 160.0 / 9.0),
+            _ => return Err(format!("unsupported units '{}'", s)),
+        };
+
+        Ok(Units {
+            name: s.trim().to_string(),
+            offset: offset,
+            quantity: quantity,
+            scale: scale,
+        })
+    }
+}
@@ -0,0 +1,80 @@
+use structopt::StructOpt;
+
+use ncproj_rs::dataset::Dataset;
+
+use std::collections::HashSet;
+use std::error::Error;
+use std::path::PathBuf;
+
+#[derive(StructOpt)]
+pub struct Validate {
+    #[structopt(parse(from_os_str), index = 2)]
+    data_files: Vec<PathBuf>,
+
+    // field used as the shape id when a shape file is provided
+    #[structopt(short = "i", long = "id-field")]
+    id_field: Option<String>,
+
+    #[structopt(parse(from_os_str), index = 1)]
+    index_file: PathBuf,
+
+    // layer to read from multi-layer shape files (geopackage)
+    #[structopt(short = "l", long = "layer")]
+    layer: Option<String>,
+
+    // shape file used to report shapes without assigned cells
+    #[structopt(short = "s", long = "shape-file", parse(from_os_str))]
+    shape_file: Option<PathBuf>,
+}
+
+impl Validate {
+    pub fn execute(&self) -> Result<(), Box<dyn Error>> {
+        let shapes = ncproj_rs::format::read_index(&self.index_file)?;
+        let mut issue_count = 0;
+
+        // check for duplicate entries
+        for (id, indices) in shapes.iter() {
+            let mut set = HashSet::new();
+            for (x, y, _) in indices.iter() {
+                if !set.insert((x, y)) {
+                    println!("duplicate entry: shape '{}' cell ({}, {})",
+                        id, x, y);
+                    issue_count += 1;
+                }
+            }
+        }
+
+        // check cell coordinates fall within each data file grid
+        for data_file in self.data_files.iter() {
+            let reader = Dataset::open(data_file)?;
+            let (y_len, x_len) = ncproj_rs::grid::read_dimensions(&reader)?;
+
+            for (id, indices) in shapes.iter() {
+                for (x, y, _) in indices.iter() {
+                    if *x >= x_len || *y >= y_len {
+                        println!("out of bounds: shape '{}' cell ({}, {}) \
+                            exceeds {} x {} grid of {:?}",
+                            id, x, y, x_len, y_len, data_file);
+                        issue_count += 1;
+                    }
+                }
+            }
+        }
+
+        // check for shapes without assigned cells
+        if let Some(shape_file) = &self.shape_file {
+            for id in ncproj_rs::shape::read_shapes(shape_file,
+                    &self.id_field, &self.layer, &[], None)?.keys() {
+                if !shapes.contains_key(id) {
+                    println!("no assigned cells: shape '{}'", id);
+                    issue_count += 1;
+                }
+            }
+        }
+
+        match issue_count {
+            0 => Ok(()),
+            x => Err(format!("index validation found {} issue(s)", x).into()),
+        }
+    }
+}
@@ -0,0 +1,155 @@
+// netcdf-4 output written with netcdf-c
+//  - all numeric variables are stored as doubles
+use crate::dataset::AttrValue;
+
+use std::error::Error;
+use std::path::Path;
+
+pub struct Writer {
+    #[cfg(feature = "netcdf-c")]
+    file: netcdf::MutableFile,
+}
+
+impl Writer {
+    #[cfg(feature = "netcdf-c")]
+    pub fn create<P: AsRef<Path>>(path: P) -> Result<Writer, Box<dyn Error>> {
+        Ok(Writer { file: netcdf::create(path)? })
+    }
+
+    #[cfg(not(feature = "netcdf-c"))]
+    pub fn create<P: AsRef<Path>>(path: P) -> Result<Writer, Box<dyn Error>> {
+        Err(format!("writing netcdf file '{}' requires 'netcdf-c'",
+            path.as_ref().display()).into())
+    }
+
+    // add a dimension - a zero length adds an unlimited dimension
+    pub fn add_dimension(&mut self, name: &str, len: usize)
+            -> Result<(), Box<dyn Error>> {
+        #[cfg(feature = "netcdf-c")]
+        {
+            match len {
+                0 => self.file.add_unlimited_dimension(name)?,
+                len => self.file.add_dimension(name, len)?,
+            };
+        }
+
+        let _ = (name, len);
+        Ok(())
+    }
+
+    pub fn add_attribute(&mut self, name: &str, value: &AttrValue)
+            -> Result<(), Box<dyn Error>> {
+        #[cfg(feature = "netcdf-c")]
+        {
+            match value {
+                AttrValue::Array(x) =>
+                    self.file.add_attribute(name, x.clone())?,
+                AttrValue::Double(x) => self.file.add_attribute(name, *x)?,
+                AttrValue::Int(x) => self.file.add_attribute(name, *x)?,
+                AttrValue::Str(x) =>
+                    self.file.add_attribute(name, x.as_str())?,
+            };
+        }
+
+        let _ = (name, value);
+        Ok(())
+    }
+
+    // add a double variable over dimensions with attributes
+    //  - fill values are converted to the variable type
+    pub fn add_variable(&mut self, name: &str, dimensions: &[&str],
+            attributes: &[(String, AttrValue)])
+            -> Result<(), Box<dyn Error>> {
+        #[cfg(feature = "netcdf-c")]
+        {
+            let mut variable =
+                self.file.add_variable::<f64>(name, dimensions)?;
+            for (key, value) in attributes.iter() {
+                match (key.as_str(), value) {
+                    ("_FillValue", AttrValue::Double(x)) =>
+                        variable.set_fill_value(*x)?,
+                    ("_FillValue", AttrValue::Int(x)) =>
+                        variable.set_fill_value(*x as f64)?,
+                    (_, AttrValue::Array(x)) =>
+                        { variable.add_attribute(key, x.clone())?; },
+                    (_, AttrValue::Double(x)) =>
+                        { variable.add_attribute(key, *x)?; },
+                    (_, AttrValue::Int(x)) =>
+                        { variable.add_attribute(key, *x)?; },
+                    (_, AttrValue::Str(x)) =>
+                        { variable.add_attribute(key, x.as_str())?; },
+                }
+            }
+        }
+
+        let _ = (name, dimensions, attributes);
+        Ok(())
+    }
+
+    // add an integer variable over dimensions with a fill value
+    pub fn add_int_variable(&mut self, name: &str, dimensions: &[&str],
+            fill_value: i32) -> Result<(), Box<dyn Error>> {
+        #[cfg(feature = "netcdf-c")]
+        {
+            self.file.add_variable::<i32>(name, dimensions)?
+                .set_fill_value(fill_value)?;
+        }
+
+        let _ = (name, dimensions, fill_value);
+        Ok(())
+    }
+
+    // add a string variable over dimensions
+    pub fn add_string_variable(&mut self, name: &str, dimensions: &[&str])
+            -> Result<(), Box<dyn Error>> {
+        #[cfg(feature = "netcdf-c")]
+        {
+            self.file.add_string_variable(name, dimensions)?;
+        }
+
+        let _ = (name, dimensions);
+        Ok(())
+    }
+
+    // write values of the hyperslab at start with count length
+    pub fn put_values(&mut self, name: &str, values: &[f64],
+            start: &[usize], count: &[usize]) -> Result<(), Box<dyn Error>> {
+        #[cfg(feature = "netcdf-c")]
+        {
+            let mut variable = self.file.variable_mut(name)
+                .ok_or_else(|| format!("variable '{}' not found", name))?;
+            variable.put_values(values, Some(start), Some(count))?;
+        }
+
+        let _ = (name, values, start, count);
+        Ok(())
+    }
+
+    // write integer values of the hyperslab at start with count length
+    pub fn put_int_values(&mut self, name: &str, values: &[i32],
+            start: &[usize], count: &[usize]) -> Result<(), Box<dyn Error>> {
+        #[cfg(feature = "netcdf-c")]
+        {
+            let mut variable = self.file.variable_mut(name)
+                .ok_or_else(|| format!("variable '{}' not found", name))?;
+            variable.put_values(values, Some(start), Some(count))?;
+        }
+
+        let _ = (name, values, start, count);
+        Ok(())
+    }
+
+    // write a string at index of a string variable
+    pub fn put_string(&mut self, name: &str, value: &str, index: &[usize])
+            -> Result<(), Box<dyn Error>> {
+        #[cfg(feature = "netcdf-c")]
+        {
+            let mut variable = self.file.variable_mut(name)
+                .ok_or_else(|| format!("variable '{}' not found", name))?;
+            variable.put_string(value, Some(index))?;
+        }
+
+        let _ = (name, value, index);
+        Ok(())
+    }
+}
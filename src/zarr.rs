@@ -0,0 +1,185 @@
+// zarr v2 directory store writer - chunks are zstd compressed
+//  little-endian arrays readable by xarray and the kerchunk reader
+use serde_json::{json, Map, Value};
+
+use crate::dataset::AttrValue;
+
+use std::error::Error;
+use std::fs::File;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+pub struct Store {
+    path: PathBuf,
+}
+
+impl Store {
+    // create the store directory with root group metadata
+    pub fn create<P: AsRef<Path>>(path: P,
+            attributes: &[(String, AttrValue)])
+            -> Result<Store, Box<dyn Error>> {
+        let path = path.as_ref().to_path_buf();
+        std::fs::create_dir_all(&path)?;
+
+        write_json(&path.join(".zgroup"), &json!({ "zarr_format": 2 }))?;
+        write_json(&path.join(".zattrs"), &attributes_json(attributes))?;
+
+        Ok(Store { path: path })
+    }
+
+    // add an array of shape over dimensions stored in chunks
+    //  - dtype is a numpy type string ('<f4' or '<f8')
+    pub fn add_array(&self, name: &str, dtype: &str, dimensions: &[String],
+            shape: &[usize], chunks: &[usize],
+            attributes: &[(String, AttrValue)])
+            -> Result<Array, Box<dyn Error>> {
+        let path = self.path.join(name);
+        std::fs::create_dir_all(&path)?;
+
+        let size = match dtype {
+            "<f4" => 4,
+            "<f8" => 8,
+            x => return Err(format!("unsupported zarr dtype '{}'", x).into()),
+        };
+
+        write_json(&path.join(".zarray"), &json!({
+            "chunks": chunks,
+            "compressor": { "id": "zstd", "level": 3 },
+            "dtype": dtype,
+            "fill_value": "NaN",
+            "filters": null,
+            "order": "C",
+            "shape": shape,
+            "zarr_format": 2,
+        }))?;
+
+        // fill values are represented by NaN in written chunks
+        let attributes: Vec<(String, AttrValue)> = attributes.iter()
+            .filter(|x| !["_FillValue", "missing_value", "scale_factor",
+                "add_offset"].contains(&x.0.as_str()))
+            .cloned().collect();
+
+        let mut zattrs = attributes_json(&attributes);
+        zattrs["_ARRAY_DIMENSIONS"] = json!(dimensions);
+        write_json(&path.join(".zattrs"), &zattrs)?;
+
+        Ok(Array {
+            chunks: chunks.to_vec(),
+            path: path,
+            shape: shape.to_vec(),
+            size: size,
+        })
+    }
+}
+
+pub struct Array {
+    chunks: Vec<usize>,
+    path: PathBuf,
+    shape: Vec<usize>,
+    size: usize,
+}
+
+impl Array {
+    // write the chunks covered by a row-major block of values at start
+    //  - start must be chunk aligned and the block must span whole
+    //  chunks except at the end of each dimension
+    pub fn write_block(&self, values: &[f64], start: &[usize],
+            count: &[usize]) -> Result<(), Box<dyn Error>> {
+        let rank = self.shape.len();
+        let chunk_len: usize = self.chunks.iter().product();
+        if count.contains(&0) {
+            return Ok(());
+        }
+
+        // iterate over chunk indices covered by the block
+        let first: Vec<usize> = (0..rank)
+            .map(|k| start[k] / self.chunks[k]).collect();
+        let last: Vec<usize> = (0..rank)
+            .map(|k| (start[k] + count[k] - 1) / self.chunks[k]).collect();
+
+        let mut chunk_index = first.clone();
+        loop {
+            // copy values into the chunk - padding is NaN
+            let mut chunk = vec![f64::NAN; chunk_len];
+            for (offset, value) in chunk.iter_mut().enumerate() {
+                let mut remainder = offset;
+                let mut value_offset = 0;
+                let mut inside = true;
+                for k in 0..rank {
+                    let stride: usize = self.chunks[k + 1..].iter().product();
+                    let position = chunk_index[k] * self.chunks[k]
+                        + remainder / stride - start[k];
+                    remainder %= stride;
+
+                    inside &= position < count[k];
+                    value_offset = value_offset * count[k] + position;
+                }
+
+                if inside {
+                    *value = values[value_offset];
+                }
+            }
+
+            self.write_chunk(&chunk_index, &chunk)?;
+
+            // advance chunk index
+            let mut k = rank;
+            loop {
+                if k == 0 {
+                    return Ok(());
+                }
+
+                k -= 1;
+                chunk_index[k] += 1;
+                if chunk_index[k] <= last[k] {
+                    break;
+                }
+
+                chunk_index[k] = first[k];
+            }
+        }
+    }
+
+    fn write_chunk(&self, chunk_index: &[usize], chunk: &[f64])
+            -> Result<(), Box<dyn Error>> {
+        let mut bytes = Vec::with_capacity(chunk.len() * self.size);
+        for value in chunk.iter() {
+            match self.size {
+                4 => bytes.extend_from_slice(&(*value as f32).to_le_bytes()),
+                _ => bytes.extend_from_slice(&value.to_le_bytes()),
+            }
+        }
+
+        let key: Vec<String> =
+            chunk_index.iter().map(|x| x.to_string()).collect();
+        let key = match key.is_empty() {
+            true => "0".to_string(),
+            false => key.join("."),
+        };
+
+        File::create(self.path.join(key))?
+            .write_all(&zstd::encode_all(&bytes[..], 3)?)?;
+        Ok(())
+    }
+}
+
+fn attributes_json(attributes: &[(String, AttrValue)]) -> Value {
+    let mut object = Map::new();
+    for (name, value) in attributes.iter() {
+        let value = match value {
+            AttrValue::Array(x) => json!(x),
+            AttrValue::Double(x) => json!(x),
+            AttrValue::Int(x) => json!(x),
+            AttrValue::Str(x) => json!(x),
+        };
+
+        object.insert(name.to_string(), value);
+    }
+
+    Value::Object(object)
+}
+
+fn write_json(path: &Path, value: &Value) -> Result<(), Box<dyn Error>> {
+    serde_json::to_writer_pretty(File::create(path)?, value)?;
+    Ok(())
+}
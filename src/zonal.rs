@@ -0,0 +1,122 @@
+use structopt::StructOpt;
+
+use ncproj_rs::dataset::Dataset;
+use ncproj_rs::dumper::Encoding;
+use ncproj_rs::grid::{CellWeight, Grid, LonConvention};
+use ncproj_rs::stat::{Accumulator, Precision, Stat};
+
+use std::collections::HashSet;
+use std::error::Error;
+use std::io::Write;
+use std::path::PathBuf;
+
+#[derive(StructOpt)]
+pub struct Zonal {
+    // netcdf file containing 2d (lat, lon) variables
+    #[structopt(parse(from_os_str), index = 2)]
+    data_file: PathBuf,
+
+    #[structopt(parse(from_os_str), index = 1)]
+    index_file: PathBuf,
+
+    #[structopt(short = "o", long = "output", parse(from_os_str))]
+    output: Option<PathBuf>,
+
+    // comma-separated list of statistics to compute for each feature
+    #[structopt(short = "s", long = "stats", use_delimiter = true,
+        default_value = "min,max,mean,std,median")]
+    stats: Vec<Stat>,
+
+    // comma-separated list of variables to process (default all 2d)
+    #[structopt(short = "v", long = "variables", use_delimiter = true)]
+    variables: Vec<String>,
+}
+
+impl Zonal {
+    pub fn execute(&self) -> Result<(), Box<dyn Error>> {
+        let shapes = ncproj_rs::format::read_index(&self.index_file)?;
+
+        let reader = Dataset::open(&self.data_file)?;
+        let grid = Grid::open(&reader, LonConvention::Auto)?;
+        let (latitudes_len, longitudes_len) =
+            ncproj_rs::grid::read_dimensions(&reader)?;
+
+        // validate index cells against the grid
+        for (x, y, _) in shapes.iter().flat_map(|x| x.1.iter()) {
+            if *x >= longitudes_len || *y >= latitudes_len {
+                return Err(format!("index cell ({}, {}) outside of {}x{} \
+                    grid", x, y, longitudes_len, latitudes_len).into());
+            }
+        }
+
+        // identify time-invariant (lat, lon) variables
+        let dimensions: HashSet<String> =
+            reader.dimensions().iter().map(|x| x.name()).collect();
+
+        let mut features = Vec::new();
+        for variable in reader.variables() {
+            let shape: Vec<usize> = variable.dimensions().iter()
+                .map(|x| x.len()).collect();
+            if dimensions.contains(&variable.name())
+                    || shape != [latitudes_len, longitudes_len]
+                    || (!self.variables.is_empty()
+                        && !self.variables.contains(&variable.name())) {
+                continue;
+            }
+
+            // read and decode values
+            let raw: Vec<f64> = variable.values()?.iter().cloned().collect();
+            let mut values = vec![0f32; raw.len()];
+            Encoding::read(&variable)?.decode_values(&raw, &mut values)
+                .map_err(|e| format!("variable '{}': {}", variable.name(),
+                    e))?;
+
+            features.push((variable.name(), values));
+        }
+
+        for variable in self.variables.iter() {
+            if !features.iter().any(|x| &x.0 == variable) {
+                return Err(format!(
+                    "2d variable '{}' not found", variable).into());
+            }
+        }
+
+        // write csv header
+        let mut writer = ncproj_rs::open_output(&self.output, false)?;
+        write!(writer, "gis_join,area")?;
+        for (feature, _) in features.iter() {
+            for stat in self.stats.iter() {
+                write!(writer, ",{}_{}", stat.name(), feature)?;
+            }
+        }
+        writeln!(writer)?;
+
+        // compute statistics of each shape
+        let collect = self.stats.iter().any(|x| x.requires_values());
+        let mut accumulator = Accumulator::new(collect, Precision::F32);
+        for (gis_join, indices) in shapes.iter() {
+            // covered area (km^2) of the shape
+            let area: f64 = indices.iter().map(|(x, y, weight)|
+                CellWeight::Area.weight(&grid, *x, *y) * *weight as f64).sum();
+            write!(writer, "{},{:.3}", gis_join, area)?;
+
+            for (_, values) in features.iter() {
+                accumulator.reset();
+                for (x, y, weight) in indices.iter() {
+                    let value = values[y * longitudes_len + x];
+                    if value.is_finite() {
+                        accumulator.update(value, *weight);
+                    }
+                }
+
+                for stat in self.stats.iter() {
+                    write!(writer, ",{:.3}", accumulator.compute(stat))?;
+                }
+            }
+            writeln!(writer)?;
+        }
+
+        writer.flush()?;
+        Ok(())
+    }
+}